@@ -1,9 +1,11 @@
-use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, http::{header, StatusCode}};
 use actix_web::error::JsonPayloadError;
 use diesel::result::Error as DieselError;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::models::pagination::Paginated;
+
 /// Standardized API error response structure
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
@@ -142,6 +144,19 @@ pub fn json_error_handler() -> actix_web::web::JsonConfig {
         })
 }
 
+/// Helper function to create query string config with our error handler
+pub fn query_error_handler() -> actix_web::web::QueryConfig {
+    actix_web::web::QueryConfig::default()
+        .error_handler(|err, _| {
+            let error_response = AppError::BadRequest(err.to_string()).error_response();
+            actix_web::error::InternalError::from_response(
+                "Query string error",
+                error_response
+            )
+            .into()
+        })
+}
+
 /// Helper functions to create error responses
 #[allow(dead_code)]
 pub mod response {
@@ -192,6 +207,38 @@ pub mod response {
         HttpResponse::Ok().json(data)
     }
     
+    /// Create a success response for one page of a listing, with the total
+    /// number of matching rows in the `x-total-count` header and `next`/`prev`
+    /// links to the neighbouring pages in the `Link` header
+    pub fn ok_page<T: Serialize>(req: &HttpRequest, page: Paginated<T>) -> HttpResponse {
+        let mut builder = HttpResponse::Ok();
+        builder.insert_header(("x-total-count", page.total_count.to_string()));
+
+        let links: Vec<String> = [(page.next_cursor, "next"), (page.prev_cursor, "prev")]
+            .into_iter()
+            .filter_map(|(cursor, rel)| {
+                cursor.map(|cursor| format!("<{}>; rel=\"{}\"", page_url(req, &cursor), rel))
+            })
+            .collect();
+        if !links.is_empty() {
+            builder.insert_header((header::LINK, links.join(", ")));
+        }
+
+        builder.json(page.items)
+    }
+
+    /// The request URL with its cursor replaced, cursors are base64url so need no escaping
+    fn page_url(req: &HttpRequest, cursor: &str) -> String {
+        let mut params: Vec<&str> = req
+            .query_string()
+            .split('&')
+            .filter(|param| !param.is_empty() && !param.starts_with("cursor="))
+            .collect();
+        let cursor_param = format!("cursor={}", cursor);
+        params.push(&cursor_param);
+        format!("{}?{}", req.path(), params.join("&"))
+    }
+    
     /// Create a created response
     pub fn created<T: Serialize>(data: T) -> HttpResponse {
         HttpResponse::Created().json(data)
@@ -65,6 +65,81 @@ pub fn get_environment() -> String {
     }
 }
 
+/// Get attachment storage backend from environment variable
+/// Defaults to "local" if ATTACHMENT_STORAGE is not set
+pub fn get_attachment_storage() -> String {
+    dotenv().ok();
+    let backend = env::var("ATTACHMENT_STORAGE").unwrap_or_else(|_| "local".to_string());
+
+    match backend.as_str() {
+        "local" | "s3" => backend,
+        _ => panic!("❌ ATTACHMENT_STORAGE must be one of: local, s3")
+    }
+}
+
+/// Get directory for locally stored attachments from environment variable
+/// Defaults to "./uploads" if ATTACHMENT_DIR is not set
+pub fn get_attachment_dir() -> String {
+    dotenv().ok();
+    env::var("ATTACHMENT_DIR").unwrap_or_else(|_| "./uploads".to_string())
+}
+
+/// Get maximum attachment size in bytes from environment variable
+/// Defaults to 10 MiB if ATTACHMENT_MAX_BYTES is not set
+pub fn get_attachment_max_bytes() -> usize {
+    dotenv().ok();
+    env::var("ATTACHMENT_MAX_BYTES")
+        .map(|value| value.parse::<usize>()
+            .expect("❌ ATTACHMENT_MAX_BYTES must be a valid number"))
+        .unwrap_or(10 * 1024 * 1024)
+}
+
+/// Get maximum import file size in bytes from environment variable
+/// Defaults to 5 MiB if IMPORT_MAX_BYTES is not set
+pub fn get_import_max_bytes() -> usize {
+    dotenv().ok();
+    env::var("IMPORT_MAX_BYTES")
+        .map(|value| value.parse::<usize>()
+            .expect("❌ IMPORT_MAX_BYTES must be a valid number"))
+        .unwrap_or(5 * 1024 * 1024)
+}
+
+/// Get maximum account archive size in bytes from environment variable
+/// Defaults to 100 MiB if ARCHIVE_MAX_BYTES is not set, archives carry attachment files
+pub fn get_archive_max_bytes() -> usize {
+    dotenv().ok();
+    env::var("ARCHIVE_MAX_BYTES")
+        .map(|value| value.parse::<usize>()
+            .expect("❌ ARCHIVE_MAX_BYTES must be a valid number"))
+        .unwrap_or(100 * 1024 * 1024)
+}
+
+/// S3-compatible object storage settings
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+/// Get S3 settings from environment variables
+/// Panics if any of S3_ENDPOINT, S3_BUCKET, S3_ACCESS_KEY_ID or S3_SECRET_ACCESS_KEY is not set
+pub fn get_s3_config() -> S3Config {
+    dotenv().ok();
+    S3Config {
+        endpoint: env::var("S3_ENDPOINT")
+            .expect("❌ S3_ENDPOINT environment variable is required when ATTACHMENT_STORAGE=s3"),
+        bucket: env::var("S3_BUCKET")
+            .expect("❌ S3_BUCKET environment variable is required when ATTACHMENT_STORAGE=s3"),
+        region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+        access_key_id: env::var("S3_ACCESS_KEY_ID")
+            .expect("❌ S3_ACCESS_KEY_ID environment variable is required when ATTACHMENT_STORAGE=s3"),
+        secret_access_key: env::var("S3_SECRET_ACCESS_KEY")
+            .expect("❌ S3_SECRET_ACCESS_KEY environment variable is required when ATTACHMENT_STORAGE=s3"),
+    }
+}
+
 /// Validate all required environment variables at startup
 /// Call this function early in main() to fail fast if config is invalid
 pub fn validate_environment() {
@@ -77,6 +152,12 @@ pub fn validate_environment() {
     let _jwt_expiration = get_jwt_expiration_hours();
     let _rust_log = get_rust_log();
     let environment = get_environment();
+    let _attachment_max_bytes = get_attachment_max_bytes();
+    let _import_max_bytes = get_import_max_bytes();
+    let _archive_max_bytes = get_archive_max_bytes();
+    if get_attachment_storage() == "s3" {
+        let _s3_config = get_s3_config();
+    }
     
     println!("✅ All environment variables validated successfully");
     println!("🌍 Environment: {}", environment);
@@ -0,0 +1,354 @@
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::PgConnection;
+use futures_util::StreamExt;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use uuid::Uuid;
+use crate::models::attachment::{Attachment, AttachmentParent, AttachmentUpload, NewAttachment};
+
+use crate::config;
+use crate::config::errors::{AppError, response};
+use crate::database::db_connection::DbConnection;
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::attachment_service;
+use crate::services::attachment_storage::AttachmentStorage;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Upload an attachment to an expense
+#[utoipa::path(
+    post,
+    path = "/api/expenses/{expense_id}/attachments",
+    request_body(content = AttachmentUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Attachment uploaded successfully", body = Attachment),
+        (status = 400, description = "Invalid file"),
+        (status = 404, description = "Expense not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("expense_id" = Uuid, Path, description = "Expense ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn upload_expense_attachment(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    expense_id: web::Path<Uuid>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    upload(req, pool, storage, AttachmentParent::Expense(expense_id.into_inner()), payload).await
+}
+
+/// List attachments of an expense
+#[utoipa::path(
+    get,
+    path = "/api/expenses/{expense_id}/attachments",
+    responses(
+        (status = 200, description = "List of attachments", body = Vec<Attachment>),
+        (status = 404, description = "Expense not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("expense_id" = Uuid, Path, description = "Expense ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn get_expense_attachments(req: HttpRequest, pool: web::Data<DbPool>, expense_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    list(req, pool, AttachmentParent::Expense(expense_id.into_inner()))
+}
+
+/// Download an attachment of an expense
+#[utoipa::path(
+    get,
+    path = "/api/expenses/{expense_id}/attachments/{attachment_id}",
+    responses(
+        (status = 200, description = "Attachment file contents", content_type = "application/octet-stream"),
+        (status = 404, description = "Attachment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("expense_id" = Uuid, Path, description = "Expense ID"),
+        ("attachment_id" = Uuid, Path, description = "Attachment ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn download_expense_attachment(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (expense_id, attachment_id) = path.into_inner();
+    download(req, pool, storage, AttachmentParent::Expense(expense_id), attachment_id).await
+}
+
+/// Delete an attachment of an expense
+#[utoipa::path(
+    delete,
+    path = "/api/expenses/{expense_id}/attachments/{attachment_id}",
+    responses(
+        (status = 200, description = "Attachment deleted successfully", body = Attachment),
+        (status = 404, description = "Attachment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("expense_id" = Uuid, Path, description = "Expense ID"),
+        ("attachment_id" = Uuid, Path, description = "Attachment ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn delete_expense_attachment(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (expense_id, attachment_id) = path.into_inner();
+    delete(req, pool, storage, AttachmentParent::Expense(expense_id), attachment_id).await
+}
+
+/// Upload an attachment to an income
+#[utoipa::path(
+    post,
+    path = "/api/incomes/{income_id}/attachments",
+    request_body(content = AttachmentUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Attachment uploaded successfully", body = Attachment),
+        (status = 400, description = "Invalid file"),
+        (status = 404, description = "Income not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("income_id" = Uuid, Path, description = "Income ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn upload_income_attachment(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    income_id: web::Path<Uuid>,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    upload(req, pool, storage, AttachmentParent::Income(income_id.into_inner()), payload).await
+}
+
+/// List attachments of an income
+#[utoipa::path(
+    get,
+    path = "/api/incomes/{income_id}/attachments",
+    responses(
+        (status = 200, description = "List of attachments", body = Vec<Attachment>),
+        (status = 404, description = "Income not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("income_id" = Uuid, Path, description = "Income ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn get_income_attachments(req: HttpRequest, pool: web::Data<DbPool>, income_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    list(req, pool, AttachmentParent::Income(income_id.into_inner()))
+}
+
+/// Download an attachment of an income
+#[utoipa::path(
+    get,
+    path = "/api/incomes/{income_id}/attachments/{attachment_id}",
+    responses(
+        (status = 200, description = "Attachment file contents", content_type = "application/octet-stream"),
+        (status = 404, description = "Attachment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("income_id" = Uuid, Path, description = "Income ID"),
+        ("attachment_id" = Uuid, Path, description = "Attachment ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn download_income_attachment(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (income_id, attachment_id) = path.into_inner();
+    download(req, pool, storage, AttachmentParent::Income(income_id), attachment_id).await
+}
+
+/// Delete an attachment of an income
+#[utoipa::path(
+    delete,
+    path = "/api/incomes/{income_id}/attachments/{attachment_id}",
+    responses(
+        (status = 200, description = "Attachment deleted successfully", body = Attachment),
+        (status = 404, description = "Attachment not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("income_id" = Uuid, Path, description = "Income ID"),
+        ("attachment_id" = Uuid, Path, description = "Attachment ID")
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "attachments"
+)]
+pub async fn delete_income_attachment(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let (income_id, attachment_id) = path.into_inner();
+    delete(req, pool, storage, AttachmentParent::Income(income_id), attachment_id).await
+}
+
+async fn upload(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    parent: AttachmentParent,
+    payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    {
+        let mut conn = pool.get()?;
+        ensure_owner(&mut conn, parent, user_id)?;
+    }
+
+    let (file_name, content_type, data) = read_file_field(payload, config::get_attachment_max_bytes()).await?;
+    attachment_service::validate_upload(&content_type, &data)?;
+
+    let new_attachment = NewAttachment::new(parent, user_id, file_name, content_type, data.len() as i64);
+    storage.put(&new_attachment.storage_key, data).await?;
+
+    let mut conn = pool.get()?;
+    match attachment_service::create_attachment(&mut conn, &new_attachment) {
+        Ok(attachment) => Ok(response::created(attachment)),
+        Err(error) => {
+            // Don't leave an orphaned file behind when the row could not be written
+            if let Err(cleanup_error) = storage.delete(&new_attachment.storage_key).await {
+                log::warn!("Failed to remove orphaned attachment {}: {}", new_attachment.storage_key, cleanup_error);
+            }
+            Err(error.into())
+        }
+    }
+}
+
+fn list(req: HttpRequest, pool: web::Data<DbPool>, parent: AttachmentParent) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    ensure_owner(&mut conn, parent, user_id)?;
+    let attachments = attachment_service::get_attachments(&mut conn, parent)?;
+    Ok(response::ok(attachments))
+}
+
+async fn download(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    parent: AttachmentParent,
+    attachment_id: Uuid,
+) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let attachment = {
+        let mut conn = pool.get()?;
+        ensure_owner(&mut conn, parent, user_id)?;
+        attachment_service::get_attachment(&mut conn, parent, attachment_id)?
+    };
+
+    let data = storage.get(&attachment.storage_key).await?;
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.content_type)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(attachment.file_name)],
+        })
+        .body(data))
+}
+
+async fn delete(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    parent: AttachmentParent,
+    attachment_id: Uuid,
+) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let attachment = {
+        let mut conn = pool.get()?;
+        ensure_owner(&mut conn, parent, user_id)?;
+        attachment_service::get_attachment(&mut conn, parent, attachment_id)?;
+        attachment_service::delete_attachment(&mut conn, attachment_id)?
+    };
+
+    storage.delete(&attachment.storage_key).await?;
+    Ok(response::ok(attachment))
+}
+
+/// Attachments are only visible to the owner of the transaction, anyone else gets a 404
+fn ensure_owner(conn: &mut DbConnection, parent: AttachmentParent, user_id: Uuid) -> Result<(), AppError> {
+    let owner_id = attachment_service::get_parent_owner(conn, parent)?;
+    if owner_id != user_id {
+        return Err(AppError::NotFound("Resource not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Read the `file` field of a multipart form, enforcing the size limit while streaming
+async fn read_file_field(mut payload: Multipart, max_bytes: usize) -> Result<(String, String, Bytes), AppError> {
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| AppError::BadRequest(format!("Invalid multipart data: {}", e)))?;
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let content_type = field
+            .content_type()
+            .map(|mime| mime.essence_str().to_string())
+            .unwrap_or_default();
+        let file_name = attachment_service::sanitize_file_name(
+            field.content_disposition().and_then(|disposition| disposition.get_filename()),
+        );
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| AppError::BadRequest(format!("Invalid multipart data: {}", e)))?;
+            if data.len() + chunk.len() > max_bytes {
+                return Err(AppError::Validation(format!("File exceeds the maximum size of {} bytes", max_bytes)));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        return Ok((file_name, content_type, data.freeze()));
+    }
+
+    Err(AppError::Validation("Missing multipart field: file".to_string()))
+}
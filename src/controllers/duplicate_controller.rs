@@ -0,0 +1,52 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use crate::models::duplicate::{DuplicateGroup, DuplicateQuery, MergeDuplicatesRequest, MergeDuplicatesResult};
+
+use crate::config::errors::{AppError, response};
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::duplicate_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get groups of the current user's incomes and expenses that look like duplicates
+#[utoipa::path(
+    get,
+    path = "/api/duplicates",
+    responses(
+        (status = 200, description = "Likely duplicate groups, most recent first", body = Vec<DuplicateGroup>),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(DuplicateQuery),
+    tag = "duplicates"
+)]
+pub async fn get_duplicates(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<DuplicateQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let groups = duplicate_service::find_duplicates(&mut conn, user_id, &query)?;
+    Ok(response::ok(groups))
+}
+
+/// Merge duplicates into one transaction
+#[utoipa::path(
+    post,
+    path = "/api/duplicates/merge",
+    request_body = MergeDuplicatesRequest,
+    responses(
+        (status = 200, description = "The kept transaction after merging", body = MergeDuplicatesResult),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Transaction not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "duplicates"
+)]
+pub async fn merge_duplicates(req: HttpRequest, pool: web::Data<DbPool>, request: web::Json<MergeDuplicatesRequest>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let result = duplicate_service::merge_duplicates(&mut conn, user_id, request.into_inner())?;
+    Ok(response::ok(result))
+}
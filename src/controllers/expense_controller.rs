@@ -1,12 +1,14 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use diesel::PgConnection;
 use r2d2::Pool;
 use diesel::r2d2::ConnectionManager;
 use uuid::Uuid;
-use crate::models::expense::{NewExpense, UpdateExpense, Expense};
+use crate::models::expense::{BulkExpenseRequest, BulkExpenseResult, NewExpense, UpdateExpense, Expense, ExpenseQuery, ExpenseWithSplits};
 
 use crate::config::errors::{AppError, response};
+use crate::middleware::batch_id::api_batch;
 use crate::services::expense_service;
+use crate::services::attachment_storage::{self, AttachmentStorage};
 
 type DbPool = Pool<ConnectionManager<PgConnection>>;
 
@@ -15,15 +17,17 @@ type DbPool = Pool<ConnectionManager<PgConnection>>;
     get,
     path = "/api/expenses",
     responses(
-        (status = 200, description = "List of expenses", body = Vec<Expense>),
+        (status = 200, description = "List of expenses, the `x-total-count` header holds the number of matching expenses and the `Link` header the next/prev pages when keyset paging", body = Vec<ExpenseWithSplits>),
+        (status = 400, description = "Invalid query parameters"),
         (status = 500, description = "Internal server error")
     ),
+    params(ExpenseQuery),
     tag = "expenses"
 )]
-pub async fn get_all_expenses(pool: web::Data<DbPool>) -> Result<HttpResponse, AppError> {
+pub async fn get_all_expenses(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ExpenseQuery>) -> Result<HttpResponse, AppError> {
     let mut conn = pool.get()?;
-    let expenses = expense_service::get_all_expenses(&mut conn)?;
-    Ok(response::ok(expenses))
+    let page = expense_service::get_all_expenses(&mut conn, &query)?;
+    Ok(response::ok_page(&req, page))
 }
 
 /// Get expenses by user ID
@@ -31,46 +35,88 @@ pub async fn get_all_expenses(pool: web::Data<DbPool>) -> Result<HttpResponse, A
     get,
     path = "/api/expenses/user/{user_id}",
     responses(
-        (status = 200, description = "List of expenses for user", body = Vec<Expense>),
+        (status = 200, description = "List of expenses for user, the `x-total-count` header holds the number of matching expenses and the `Link` header the next/prev pages when keyset paging", body = Vec<ExpenseWithSplits>),
+        (status = 400, description = "Invalid query parameters"),
         (status = 404, description = "User not found"),
         (status = 500, description = "Internal server error")
     ),
     params(
-        ("user_id" = Uuid, Path, description = "User ID")
+        ("user_id" = Uuid, Path, description = "User ID"),
+        ExpenseQuery
     ),
     tag = "expenses"
 )]
-pub async fn get_expenses_by_user_id(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+pub async fn get_expenses_by_user_id(req: HttpRequest, pool: web::Data<DbPool>, user_id: web::Path<Uuid>, query: web::Query<ExpenseQuery>) -> Result<HttpResponse, AppError> {
     let mut conn = pool.get()?;
-    let expenses = expense_service::get_expenses_by_user_id(&mut conn, user_id.into_inner())?;
-    Ok(response::ok(expenses))
+    let page = expense_service::get_expenses_by_user_id(&mut conn, user_id.into_inner(), &query)?;
+    Ok(response::ok_page(&req, page))
 }
 
 /// Create new expense
+///
+/// Send the same `X-Batch-Id` with related creates to list them under `GET /api/imports?source=api`
+/// and roll them back together with `DELETE /api/imports/{batch_id}`.
 #[utoipa::path(
     post,
     path = "/api/expenses",
     request_body = NewExpense,
     responses(
-        (status = 201, description = "Expense created successfully", body = Expense),
+        (status = 201, description = "Expense created successfully", body = ExpenseWithSplits),
         (status = 400, description = "Invalid input"),
         (status = 500, description = "Internal server error")
     ),
+    params(
+        ("X-Batch-Id" = Option<Uuid>, Header, description = "Client-chosen batch of the signed-in user to add the expense to, created on first use")
+    ),
     tag = "expenses"
 )]
-pub async fn create_expense(pool: web::Data<DbPool>, new_expense: web::Json<NewExpense>) -> Result<HttpResponse, AppError> {
+pub async fn create_expense(req: HttpRequest, pool: web::Data<DbPool>, new_expense: web::Json<NewExpense>) -> Result<HttpResponse, AppError> {
+    let batch = api_batch(&req)?;
     let mut conn = pool.get()?;
-    let expense = expense_service::create_expense(&mut conn, new_expense.into_inner())?;
+    let expense = expense_service::create_expense_in_batch(&mut conn, new_expense.into_inner(), batch)?;
     Ok(response::created(expense))
 }
 
+/// Create, update and delete many expenses in one request
+#[utoipa::path(
+    post,
+    path = "/api/expenses/bulk",
+    request_body = BulkExpenseRequest,
+    responses(
+        (status = 200, description = "Outcome of every operation, in per-item mode failed ones carry their status and error", body = BulkExpenseResult),
+        (status = 400, description = "Invalid input, in atomic mode the message names the failing operation"),
+        (status = 404, description = "Expense of an update or delete not found, atomic mode only"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("X-Batch-Id" = Option<Uuid>, Header, description = "Client-chosen batch of the signed-in user to add the created expenses to, created on first use")
+    ),
+    tag = "expenses"
+)]
+pub async fn bulk_expenses(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    request: web::Json<BulkExpenseRequest>,
+) -> Result<HttpResponse, AppError> {
+    let batch = api_batch(&req)?;
+    let (result, storage_keys) = {
+        let mut conn = pool.get()?;
+        expense_service::bulk_expenses(&mut conn, request.into_inner(), batch)?
+    };
+
+    attachment_storage::remove_files(storage.get_ref(), &storage_keys).await;
+    Ok(response::ok(result))
+}
+
 /// Update expense
 #[utoipa::path(
     put,
     path = "/api/expenses/{expense_id}",
     request_body = UpdateExpense,
     responses(
-        (status = 200, description = "Expense updated successfully", body = Expense),
+        (status = 200, description = "Expense updated successfully", body = ExpenseWithSplits),
+        (status = 400, description = "Invalid input"),
         (status = 404, description = "Expense not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -90,7 +136,7 @@ pub async fn update_expense(pool: web::Data<DbPool>, expense_id: web::Path<Uuid>
     delete,
     path = "/api/expenses/{expense_id}",
     responses(
-        (status = 200, description = "Expense deleted successfully"),
+        (status = 200, description = "Expense deleted successfully", body = Expense),
         (status = 404, description = "Expense not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -99,8 +145,12 @@ pub async fn update_expense(pool: web::Data<DbPool>, expense_id: web::Path<Uuid>
     ),
     tag = "expenses"
 )]
-pub async fn delete_expense(pool: web::Data<DbPool>, expense_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
-    let mut conn = pool.get()?;
-    let expense = expense_service::delete_expense(&mut conn, expense_id.into_inner())?;
+pub async fn delete_expense(pool: web::Data<DbPool>, storage: web::Data<dyn AttachmentStorage>, expense_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let (expense, storage_keys) = {
+        let mut conn = pool.get()?;
+        expense_service::delete_expense(&mut conn, expense_id.into_inner())?
+    };
+
+    attachment_storage::remove_files(storage.get_ref(), &storage_keys).await;
     Ok(response::ok(expense))
 }
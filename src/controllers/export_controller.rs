@@ -0,0 +1,237 @@
+use std::sync::Arc;
+
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::web::Bytes;
+use chrono::NaiveDate;
+use diesel::PgConnection;
+use futures_util::stream::{self, Stream, StreamExt};
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use uuid::Uuid;
+use crate::models::archive::AccountArchive;
+use crate::models::expense::{Expense, ExpenseQuery};
+use crate::models::export::{CsvOptions, QifOptions};
+use crate::models::income::{Income, IncomeQuery};
+use crate::models::pagination::SortOrder;
+use crate::models::transaction::{Transaction, TransactionQuery};
+
+use crate::config::errors::AppError;
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::csv_format::CsvFormat;
+use crate::services::export_service::{self, EXPORT_BATCH_SIZE};
+use crate::services::attachment_storage::AttachmentStorage;
+use crate::services::{archive_service, expense_service, income_service, qif_format, transaction_service};
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Download the current user's incomes as CSV
+///
+/// Takes the same filters as the income listing. Rows are streamed in date order,
+/// newest first unless `order=asc`; `sort`, `limit`, `offset` and `cursor` are ignored.
+/// Text starting with `=`, `+`, `-`, `@`, a tab or a carriage return gets a leading `'` so spreadsheets don't run it as a formula.
+#[utoipa::path(
+    get,
+    path = "/api/export/incomes.csv",
+    responses(
+        (status = 200, description = "Incomes as RFC 4180 CSV", content_type = "text/csv"),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(IncomeQuery, CsvOptions),
+    tag = "export"
+)]
+pub async fn export_incomes(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<IncomeQuery>, options: web::Query<CsvOptions>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let format = CsvFormat::from_options(&options)?;
+    let query = Arc::new(query.into_inner());
+
+    // Load the first batch up front so invalid filters still get a JSON error response
+    let first = {
+        let mut conn = pool.get()?;
+        income_service::export_incomes(&mut conn, user_id, &query, None)?
+    };
+    let body = batch_stream(
+        first,
+        move |incomes, with_header| export_service::income_csv(&format, incomes, with_header),
+        |income: &Income| (income.date, income.id),
+        move |after| income_service::export_incomes(&mut pool.get()?, user_id, &query, Some(after)),
+    );
+
+    Ok(csv_response("incomes.csv", body))
+}
+
+/// Download the current user's expenses as CSV
+///
+/// Takes the same filters as the expense listing. Rows are streamed in date order,
+/// newest first unless `order=asc`; `sort`, `limit`, `offset` and `cursor` are ignored.
+/// Text starting with `=`, `+`, `-`, `@`, a tab or a carriage return gets a leading `'` so spreadsheets don't run it as a formula.
+#[utoipa::path(
+    get,
+    path = "/api/export/expenses.csv",
+    responses(
+        (status = 200, description = "Expenses as RFC 4180 CSV", content_type = "text/csv"),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ExpenseQuery, CsvOptions),
+    tag = "export"
+)]
+pub async fn export_expenses(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ExpenseQuery>, options: web::Query<CsvOptions>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let format = CsvFormat::from_options(&options)?;
+    let query = Arc::new(query.into_inner());
+
+    let first = {
+        let mut conn = pool.get()?;
+        expense_service::export_expenses(&mut conn, user_id, &query, None)?
+    };
+    let body = batch_stream(
+        first,
+        move |expenses, with_header| export_service::expense_csv(&format, expenses, with_header),
+        |expense: &Expense| (expense.date, expense.id),
+        move |after| expense_service::export_expenses(&mut pool.get()?, user_id, &query, Some(after)),
+    );
+
+    Ok(csv_response("expenses.csv", body))
+}
+
+/// Download the current user's ledger as a QIF bank account
+///
+/// Takes the same filters as the transaction feed. Entries are streamed in date order,
+/// oldest first unless `order=desc`; `limit`, `offset` and `cursor` are ignored.
+/// Expense line items are written as split lines.
+#[utoipa::path(
+    get,
+    path = "/api/export/ledger.qif",
+    responses(
+        (status = 200, description = "Incomes and expenses as QIF", content_type = "application/qif"),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(TransactionQuery, QifOptions),
+    tag = "export"
+)]
+pub async fn export_qif(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<TransactionQuery>, options: web::Query<QifOptions>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let date_order = options.date_order.unwrap_or_default();
+    let mut query = query.into_inner();
+    query.order.get_or_insert(SortOrder::Asc);
+    let query = Arc::new(query);
+
+    let first = {
+        let mut conn = pool.get()?;
+        transaction_service::export_transactions(&mut conn, user_id, &query, None)?
+    };
+    let body = batch_stream(
+        first,
+        move |transactions, with_header| qif_format::write_qif(transactions, date_order, with_header),
+        Transaction::key,
+        move |after| transaction_service::export_transactions(&mut pool.get()?, user_id, &query, Some(after)),
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/qif")
+        .insert_header(attachment_disposition("ledger.qif"))
+        .streaming(body))
+}
+
+/// Download everything in the current user's account as a versioned JSON archive
+///
+/// Holds the profile, payees and their rules, imports, incomes, expenses with their
+/// line items and attachment files, goals with their contributions and net worth
+/// items with their snapshots. Restore it on another instance with `POST /api/imports/archive`.
+#[utoipa::path(
+    get,
+    path = "/api/export/full",
+    responses(
+        (status = 200, description = "The account archive", body = AccountArchive),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "export"
+)]
+pub async fn export_full(req: HttpRequest, pool: web::Data<DbPool>, storage: web::Data<dyn AttachmentStorage>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let (mut archive, attachments) = {
+        let mut conn = pool.get()?;
+        archive_service::export_archive(&mut conn, user_id)?
+    };
+
+    for attachment in attachments {
+        match storage.get(&attachment.storage_key).await {
+            Ok(data) => archive.attachments.push(archive_service::archive_attachment(attachment, &data)),
+            // A lost file shouldn't make the rest of the account impossible to export
+            Err(AppError::NotFound(_)) => log::warn!("Attachment file {} missing, left out of the archive", attachment.storage_key),
+            Err(error) => return Err(error),
+        }
+    }
+
+    let file_name = format!("account-{}.json", archive.exported_at.format("%Y-%m-%d"));
+    Ok(HttpResponse::Ok()
+        .insert_header(attachment_disposition(&file_name))
+        .json(archive))
+}
+
+/// Render `first` and then keep loading batches after the last row until one comes back short
+fn batch_stream<T, Render, Key, Fetch>(
+    first: Vec<T>,
+    render: Render,
+    key: Key,
+    fetch: Fetch,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>>
+where
+    T: Send + 'static,
+    Render: Fn(&[T], bool) -> String + Clone + 'static,
+    Key: Fn(&T) -> (NaiveDate, Uuid) + Copy + 'static,
+    Fetch: Fn((NaiveDate, Uuid)) -> Result<Vec<T>, AppError> + Clone + Send + 'static,
+{
+    let next_after = move |rows: &[T]| {
+        rows.last()
+            .filter(|_| rows.len() as i64 >= EXPORT_BATCH_SIZE)
+            .map(key)
+    };
+
+    let header = stream::once({
+        let chunk = Bytes::from(render(&first, true));
+        async move { Ok(chunk) }
+    });
+    let rest = stream::unfold(next_after(&first), move |after| {
+        let render = render.clone();
+        let fetch = fetch.clone();
+        async move {
+            let after = after?;
+            let rows = match web::block(move || fetch(after)).await {
+                Ok(Ok(rows)) => rows,
+                Ok(Err(e)) => return Some((Err(e.into()), None)),
+                Err(e) => return Some((Err(AppError::InternalServer(e.to_string()).into()), None)),
+            };
+            if rows.is_empty() {
+                return None;
+            }
+            Some((Ok(Bytes::from(render(&rows, false))), next_after(&rows)))
+        }
+    });
+
+    header.chain(rest)
+}
+
+fn csv_response<S>(file_name: &str, body: S) -> HttpResponse
+where
+    S: Stream<Item = Result<Bytes, actix_web::Error>> + 'static,
+{
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(attachment_disposition(file_name))
+        .streaming(body)
+}
+
+fn attachment_disposition(file_name: &str) -> ContentDisposition {
+    ContentDisposition {
+        disposition: DispositionType::Attachment,
+        parameters: vec![DispositionParam::Filename(file_name.to_string())],
+    }
+}
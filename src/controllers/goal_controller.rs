@@ -0,0 +1,151 @@
+use actix_web::{web, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use uuid::Uuid;
+use crate::models::goal::{Goal, GoalContribution, GoalProgress, NewGoal, NewGoalContribution, UpdateGoal};
+
+use crate::config::errors::{AppError, response};
+use crate::services::goal_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get goals by user ID
+#[utoipa::path(
+    get,
+    path = "/api/goals/user/{user_id}",
+    responses(
+        (status = 200, description = "List of savings goals for user", body = Vec<Goal>),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    tag = "goals"
+)]
+pub async fn get_goals_by_user_id(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let goals = goal_service::get_goals_by_user_id(&mut conn, user_id.into_inner())?;
+    Ok(response::ok(goals))
+}
+
+/// Create new savings goal
+#[utoipa::path(
+    post,
+    path = "/api/goals",
+    request_body = NewGoal,
+    responses(
+        (status = 201, description = "Goal created successfully", body = Goal),
+        (status = 400, description = "Invalid input"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "goals"
+)]
+pub async fn create_goal(pool: web::Data<DbPool>, new_goal: web::Json<NewGoal>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let goal = goal_service::create_goal(&mut conn, new_goal.into_inner())?;
+    Ok(response::created(goal))
+}
+
+/// Update savings goal
+#[utoipa::path(
+    put,
+    path = "/api/goals/{goal_id}",
+    request_body = UpdateGoal,
+    responses(
+        (status = 200, description = "Goal updated successfully", body = Goal),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Goal not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("goal_id" = Uuid, Path, description = "Goal ID")
+    ),
+    tag = "goals"
+)]
+pub async fn update_goal(pool: web::Data<DbPool>, goal_id: web::Path<Uuid>, update_goal: web::Json<UpdateGoal>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let goal = goal_service::update_goal(&mut conn, goal_id.into_inner(), update_goal.into_inner())?;
+    Ok(response::ok(goal))
+}
+
+/// Delete savings goal and its contributions
+#[utoipa::path(
+    delete,
+    path = "/api/goals/{goal_id}",
+    responses(
+        (status = 200, description = "Goal deleted successfully"),
+        (status = 404, description = "Goal not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("goal_id" = Uuid, Path, description = "Goal ID")
+    ),
+    tag = "goals"
+)]
+pub async fn delete_goal(pool: web::Data<DbPool>, goal_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let goal = goal_service::delete_goal(&mut conn, goal_id.into_inner())?;
+    Ok(response::ok(goal))
+}
+
+/// Get contributions recorded for a goal
+#[utoipa::path(
+    get,
+    path = "/api/goals/{goal_id}/contributions",
+    responses(
+        (status = 200, description = "List of contributions", body = Vec<GoalContribution>),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("goal_id" = Uuid, Path, description = "Goal ID")
+    ),
+    tag = "goals"
+)]
+pub async fn get_contributions(pool: web::Data<DbPool>, goal_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let contributions = goal_service::get_contributions(&mut conn, goal_id.into_inner())?;
+    Ok(response::ok(contributions))
+}
+
+/// Record a manual contribution towards a goal
+#[utoipa::path(
+    post,
+    path = "/api/goals/{goal_id}/contributions",
+    request_body = NewGoalContribution,
+    responses(
+        (status = 201, description = "Contribution recorded successfully", body = GoalContribution),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Goal not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("goal_id" = Uuid, Path, description = "Goal ID")
+    ),
+    tag = "goals"
+)]
+pub async fn add_contribution(pool: web::Data<DbPool>, goal_id: web::Path<Uuid>, new_contribution: web::Json<NewGoalContribution>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let contribution = goal_service::add_contribution(&mut conn, goal_id.into_inner(), new_contribution.into_inner())?;
+    Ok(response::created(contribution))
+}
+
+/// Get progress towards a goal
+#[utoipa::path(
+    get,
+    path = "/api/goals/{goal_id}/progress",
+    responses(
+        (status = 200, description = "Goal progress", body = GoalProgress),
+        (status = 404, description = "Goal not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("goal_id" = Uuid, Path, description = "Goal ID")
+    ),
+    tag = "goals"
+)]
+pub async fn get_goal_progress(pool: web::Data<DbPool>, goal_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let progress = goal_service::get_goal_progress(&mut conn, goal_id.into_inner())?;
+    Ok(response::ok(progress))
+}
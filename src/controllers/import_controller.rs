@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use actix_multipart::Multipart;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::PgConnection;
+use futures_util::StreamExt;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use uuid::Uuid;
+use crate::models::archive::{AccountArchive, ArchiveRestoreResult, ArchiveUpload};
+use crate::models::export::QifOptions;
+use crate::models::import::{CsvImportMapping, CsvImportUpload, ImportBatchQuery, ImportBatchWithCounts, ImportQuery, ImportResult, StatementUpload};
+
+use crate::config;
+use crate::config::errors::{AppError, response};
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::attachment_storage::{self, AttachmentStorage};
+use crate::services::{archive_service, attachment_service, camt_parser, import_service, mt940_parser, ofx_parser, qif_format};
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Import a CSV bank statement into the current user's incomes and expenses
+///
+/// Every row is checked against the column mapping and returned with its errors.
+/// Unless `dry_run=true`, the valid rows are saved in one transaction under a new
+/// import batch that can be undone later.
+#[utoipa::path(
+    post,
+    path = "/api/imports/csv",
+    request_body(content = CsvImportUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Dry run preview of the rows", body = ImportResult),
+        (status = 201, description = "Valid rows imported", body = ImportResult),
+        (status = 400, description = "Invalid file or column mapping"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ImportQuery),
+    tag = "imports"
+)]
+pub async fn import_csv(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ImportQuery>, payload: Multipart) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let form = read_import_form(payload, config::get_import_max_bytes()).await?;
+    let mapping = form
+        .fields
+        .get("mapping")
+        .ok_or_else(|| AppError::Validation("Missing multipart field: mapping".to_string()))?;
+    let mapping: CsvImportMapping = serde_json::from_str(mapping)
+        .map_err(|e| AppError::Validation(format!("Invalid column mapping: {}", e)))?;
+
+    let rows = import_service::parse_csv(&form.data, &mapping)?;
+    let mut conn = pool.get()?;
+    let result = import_service::import_rows(&mut conn, user_id, "csv", form.file_name, rows, query.dry_run.unwrap_or(false))?;
+    Ok(import_response(result))
+}
+
+/// Import an OFX or QFX bank statement into the current user's incomes and expenses
+///
+/// Credits become incomes and debits expenses. Transactions whose `FITID` was already
+/// imported for the same account are skipped.
+#[utoipa::path(
+    post,
+    path = "/api/imports/ofx",
+    request_body(content = StatementUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Dry run preview of the transactions", body = ImportResult),
+        (status = 201, description = "New transactions imported", body = ImportResult),
+        (status = 400, description = "Invalid statement file"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ImportQuery),
+    tag = "imports"
+)]
+pub async fn import_ofx(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ImportQuery>, payload: Multipart) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let form = read_import_form(payload, config::get_import_max_bytes()).await?;
+
+    let rows = ofx_parser::parse_ofx(&form.data)?;
+    let mut conn = pool.get()?;
+    let result = import_service::import_rows(&mut conn, user_id, "ofx", form.file_name, rows, query.dry_run.unwrap_or(false))?;
+    Ok(import_response(result))
+}
+
+/// Import an ISO 20022 camt.053 bank statement into the current user's incomes and expenses
+///
+/// Transactions are dated by their value date and named after the counterparty, with the
+/// remittance information in `description`. Entries whose bank reference was already
+/// imported for the same account are skipped, entries that are not booked yet are rejected.
+#[utoipa::path(
+    post,
+    path = "/api/imports/camt",
+    request_body(content = StatementUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Dry run preview of the transactions", body = ImportResult),
+        (status = 201, description = "New transactions imported", body = ImportResult),
+        (status = 400, description = "Invalid camt statement"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ImportQuery),
+    tag = "imports"
+)]
+pub async fn import_camt(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ImportQuery>, payload: Multipart) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let form = read_import_form(payload, config::get_import_max_bytes()).await?;
+
+    let rows = camt_parser::parse_camt(&form.data)?;
+    let mut conn = pool.get()?;
+    let result = import_service::import_rows(&mut conn, user_id, "camt", form.file_name, rows, query.dry_run.unwrap_or(false))?;
+    Ok(import_response(result))
+}
+
+/// Import a SWIFT MT940 bank statement into the current user's incomes and expenses
+///
+/// Transactions are dated by their value date. Counterparty and remittance text are read
+/// from the `:86:` field. Transactions whose bank reference was already imported for the
+/// same account are skipped.
+#[utoipa::path(
+    post,
+    path = "/api/imports/mt940",
+    request_body(content = StatementUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Dry run preview of the transactions", body = ImportResult),
+        (status = 201, description = "New transactions imported", body = ImportResult),
+        (status = 400, description = "Invalid MT940 statement"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ImportQuery),
+    tag = "imports"
+)]
+pub async fn import_mt940(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ImportQuery>, payload: Multipart) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let form = read_import_form(payload, config::get_import_max_bytes()).await?;
+
+    let rows = mt940_parser::parse_mt940(&form.data)?;
+    let mut conn = pool.get()?;
+    let result = import_service::import_rows(&mut conn, user_id, "mt940", form.file_name, rows, query.dry_run.unwrap_or(false))?;
+    Ok(import_response(result))
+}
+
+/// Import a QIF file from a desktop finance program into the current user's incomes and expenses
+///
+/// Only `!Type:Bank` and `!Type:CCard` sections are read. The QIF category and memo are kept
+/// in `description`; split lines become line items of an expense where they add up, and
+/// separate transactions otherwise.
+#[utoipa::path(
+    post,
+    path = "/api/imports/qif",
+    request_body(content = StatementUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Dry run preview of the transactions", body = ImportResult),
+        (status = 201, description = "Valid transactions imported", body = ImportResult),
+        (status = 400, description = "Invalid QIF file"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ImportQuery, QifOptions),
+    tag = "imports"
+)]
+pub async fn import_qif(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ImportQuery>, options: web::Query<QifOptions>, payload: Multipart) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let form = read_import_form(payload, config::get_import_max_bytes()).await?;
+
+    let rows = qif_format::parse_qif(&form.data, options.date_order.unwrap_or_default())?;
+    let mut conn = pool.get()?;
+    let result = import_service::import_rows(&mut conn, user_id, "qif", form.file_name, rows, query.dry_run.unwrap_or(false))?;
+    Ok(import_response(result))
+}
+
+/// Restore an account archive from `GET /api/export/full` into the current user's account
+///
+/// The account must not have any incomes, expenses, payees, goals, net worth items or
+/// imports yet. Everything in the archive is restored in one transaction with new ids,
+/// and the profile's first and last name are taken over; email and password stay as they are.
+#[utoipa::path(
+    post,
+    path = "/api/imports/archive",
+    request_body(content = ArchiveUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Archive restored", body = ArchiveRestoreResult),
+        (status = 400, description = "Invalid archive or account not empty"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "imports"
+)]
+pub async fn import_archive(req: HttpRequest, pool: web::Data<DbPool>, storage: web::Data<dyn AttachmentStorage>, payload: Multipart) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let form = read_import_form(payload, config::get_archive_max_bytes()).await?;
+    let archive: AccountArchive = serde_json::from_slice(&form.data)
+        .map_err(|e| AppError::Validation(format!("Invalid account archive: {}", e)))?;
+
+    let mut restore = {
+        let mut conn = pool.get()?;
+        archive_service::prepare_restore(&mut conn, user_id, archive)?
+    };
+
+    // Store the attachment files first, like a regular upload, and remove them again on failure
+    let mut stored = Vec::new();
+    let mut result = Ok(());
+    for (key, data) in std::mem::take(&mut restore.files) {
+        if let Err(error) = storage.put(&key, data).await {
+            result = Err(error);
+            break;
+        }
+        stored.push(key);
+    }
+    let result = result.and_then(|_| {
+        let mut conn = pool.get()?;
+        archive_service::restore_archive(&mut conn, user_id, restore)
+    });
+
+    match result {
+        Ok(restored) => Ok(response::created(restored)),
+        Err(error) => {
+            for key in stored {
+                if let Err(cleanup_error) = storage.delete(&key).await {
+                    log::warn!("Failed to remove orphaned attachment {}: {}", key, cleanup_error);
+                }
+            }
+            Err(error)
+        }
+    }
+}
+
+/// Get the current user's imports and `X-Batch-Id` batches, newest first
+#[utoipa::path(
+    get,
+    path = "/api/imports",
+    responses(
+        (status = 200, description = "List of import batches with the number of incomes and expenses left in each", body = Vec<ImportBatchWithCounts>),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ImportBatchQuery),
+    tag = "imports"
+)]
+pub async fn get_import_batches(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ImportBatchQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let batches = import_service::get_batches(&mut conn, user_id, &query)?;
+    Ok(response::ok(batches))
+}
+
+/// Undo an import or roll back an `X-Batch-Id` batch, deleting every income and expense it created, all or nothing
+#[utoipa::path(
+    delete,
+    path = "/api/imports/{batch_id}",
+    responses(
+        (status = 200, description = "Import undone, with the number of incomes and expenses deleted", body = ImportBatchWithCounts),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Import batch not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("batch_id" = Uuid, Path, description = "Import batch ID")
+    ),
+    tag = "imports"
+)]
+pub async fn undo_import(req: HttpRequest, pool: web::Data<DbPool>, storage: web::Data<dyn AttachmentStorage>, batch_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let (batch, storage_keys) = {
+        let mut conn = pool.get()?;
+        import_service::undo_batch(&mut conn, user_id, batch_id.into_inner())?
+    };
+
+    attachment_storage::remove_files(storage.get_ref(), &storage_keys).await;
+    Ok(response::ok(batch))
+}
+
+/// Dry runs only report what would happen, real imports created something
+fn import_response(result: ImportResult) -> HttpResponse {
+    if result.batch_id.is_some() {
+        response::created(result)
+    } else {
+        response::ok(result)
+    }
+}
+
+/// An uploaded statement with the form's other, text fields
+struct ImportForm {
+    file_name: Option<String>,
+    data: Bytes,
+    fields: HashMap<String, String>,
+}
+
+/// Read the `file` field and any text fields of a multipart form, enforcing the size limit while streaming
+async fn read_import_form(mut payload: Multipart, max_bytes: usize) -> Result<ImportForm, AppError> {
+    let mut file = None;
+    let mut fields = HashMap::new();
+    let mut total = 0;
+
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| AppError::BadRequest(format!("Invalid multipart data: {}", e)))?;
+        let name = field.name().unwrap_or_default().to_string();
+        let file_name = field
+            .content_disposition()
+            .and_then(|disposition| disposition.get_filename())
+            .map(|file_name| attachment_service::sanitize_file_name(Some(file_name)));
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| AppError::BadRequest(format!("Invalid multipart data: {}", e)))?;
+            total += chunk.len();
+            if total > max_bytes {
+                return Err(AppError::Validation(format!("Upload exceeds the maximum size of {} bytes", max_bytes)));
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        if name == "file" {
+            file = Some((file_name, data.freeze()));
+        } else {
+            let value = String::from_utf8(data.to_vec())
+                .map_err(|_| AppError::Validation(format!("Multipart field {} must be UTF-8 text", name)))?;
+            fields.insert(name, value);
+        }
+    }
+
+    let (file_name, data) = file.ok_or_else(|| AppError::Validation("Missing multipart field: file".to_string()))?;
+    Ok(ImportForm { file_name, data, fields })
+}
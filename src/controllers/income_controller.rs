@@ -1,12 +1,14 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use diesel::PgConnection;
 use r2d2::Pool;
 use diesel::r2d2::ConnectionManager;
 use uuid::Uuid;
-use crate::models::income::{NewIncome, UpdateIncome, Income, IncomeWithUser};
+use crate::models::income::{BulkIncomeRequest, BulkIncomeResult, NewIncome, UpdateIncome, Income, IncomeQuery, IncomeWithUser};
 
 use crate::config::errors::{AppError, response};
+use crate::middleware::batch_id::api_batch;
 use crate::services::income_service;
+use crate::services::attachment_storage::{self, AttachmentStorage};
 
 
 type DbPool = Pool<ConnectionManager<PgConnection>>;
@@ -16,15 +18,17 @@ type DbPool = Pool<ConnectionManager<PgConnection>>;
     get,
     path = "/api/incomes",
     responses(
-        (status = 200, description = "List of incomes", body = Vec<IncomeWithUser>),
+        (status = 200, description = "List of incomes, the `x-total-count` header holds the number of matching incomes and the `Link` header the next/prev pages when keyset paging", body = Vec<IncomeWithUser>),
+        (status = 400, description = "Invalid query parameters"),
         (status = 500, description = "Internal server error")
     ),
+    params(IncomeQuery),
     tag = "incomes"
 )]
-pub async fn get_all_incomes(pool: web::Data<DbPool>) -> Result<HttpResponse, AppError> {
+pub async fn get_all_incomes(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<IncomeQuery>) -> Result<HttpResponse, AppError> {
     let mut conn = pool.get()?;
-    let incomes = income_service::get_all_incomes(&mut conn)?;
-    Ok(response::ok(incomes))
+    let page = income_service::get_all_incomes(&mut conn, &query)?;
+    Ok(response::ok_page(&req, page))
 }
 
 /// Get incomes by user ID
@@ -32,22 +36,27 @@ pub async fn get_all_incomes(pool: web::Data<DbPool>) -> Result<HttpResponse, Ap
     get,
     path = "/api/incomes/user/{user_id}",
     responses(
-        (status = 200, description = "List of incomes for user", body = Vec<Income>),
+        (status = 200, description = "List of incomes for user, the `x-total-count` header holds the number of matching incomes and the `Link` header the next/prev pages when keyset paging", body = Vec<Income>),
+        (status = 400, description = "Invalid query parameters"),
         (status = 404, description = "User not found"),
         (status = 500, description = "Internal server error")
     ),
     params(
-        ("user_id" = Uuid, Path, description = "User ID")
+        ("user_id" = Uuid, Path, description = "User ID"),
+        IncomeQuery
     ),
     tag = "incomes"
 )]
-pub async fn get_incomes_by_user_id(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+pub async fn get_incomes_by_user_id(req: HttpRequest, pool: web::Data<DbPool>, user_id: web::Path<Uuid>, query: web::Query<IncomeQuery>) -> Result<HttpResponse, AppError> {
     let mut conn = pool.get()?;
-    let incomes = income_service::get_incomes_by_user_id(&mut conn, user_id.into_inner())?;
-    Ok(response::ok(incomes))
+    let page = income_service::get_incomes_by_user_id(&mut conn, user_id.into_inner(), &query)?;
+    Ok(response::ok_page(&req, page))
 }
 
 /// Create new income
+///
+/// Send the same `X-Batch-Id` with related creates to list them under `GET /api/imports?source=api`
+/// and roll them back together with `DELETE /api/imports/{batch_id}`.
 #[utoipa::path(
     post,
     path = "/api/incomes",
@@ -57,14 +66,50 @@ pub async fn get_incomes_by_user_id(pool: web::Data<DbPool>, user_id: web::Path<
         (status = 400, description = "Invalid input"),
         (status = 500, description = "Internal server error")
     ),
+    params(
+        ("X-Batch-Id" = Option<Uuid>, Header, description = "Client-chosen batch of the signed-in user to add the income to, created on first use")
+    ),
     tag = "incomes"
 )]
-pub async fn create_income(pool: web::Data<DbPool>, new_income: web::Json<NewIncome>) -> Result<HttpResponse, AppError> {
+pub async fn create_income(req: HttpRequest, pool: web::Data<DbPool>, new_income: web::Json<NewIncome>) -> Result<HttpResponse, AppError> {
+    let batch = api_batch(&req)?;
     let mut conn = pool.get()?;
-    let income = income_service::create_income(&mut conn, new_income.into_inner())?;
+    let income = income_service::create_income_in_batch(&mut conn, new_income.into_inner(), batch)?;
     Ok(response::created(income))
 }
 
+/// Create, update and delete many incomes in one request
+#[utoipa::path(
+    post,
+    path = "/api/incomes/bulk",
+    request_body = BulkIncomeRequest,
+    responses(
+        (status = 200, description = "Outcome of every operation, in per-item mode failed ones carry their status and error", body = BulkIncomeResult),
+        (status = 400, description = "Invalid input, in atomic mode the message names the failing operation"),
+        (status = 404, description = "Income of an update or delete not found, atomic mode only"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("X-Batch-Id" = Option<Uuid>, Header, description = "Client-chosen batch of the signed-in user to add the created incomes to, created on first use")
+    ),
+    tag = "incomes"
+)]
+pub async fn bulk_incomes(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    storage: web::Data<dyn AttachmentStorage>,
+    request: web::Json<BulkIncomeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let batch = api_batch(&req)?;
+    let (result, storage_keys) = {
+        let mut conn = pool.get()?;
+        income_service::bulk_incomes(&mut conn, request.into_inner(), batch)?
+    };
+
+    attachment_storage::remove_files(storage.get_ref(), &storage_keys).await;
+    Ok(response::ok(result))
+}
+
 /// Update income
 #[utoipa::path(
     put,
@@ -72,6 +117,7 @@ pub async fn create_income(pool: web::Data<DbPool>, new_income: web::Json<NewInc
     request_body = UpdateIncome,
     responses(
         (status = 200, description = "Income updated successfully", body = Income),
+        (status = 400, description = "Invalid input"),
         (status = 404, description = "Income not found"),
         (status = 500, description = "Internal server error")
     ),
@@ -100,8 +146,12 @@ pub async fn update_income(pool: web::Data<DbPool>, income_id: web::Path<Uuid>,
     ),
     tag = "incomes"
 )]
-pub async fn delete_income(pool: web::Data<DbPool>, income_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
-    let mut conn = pool.get()?;
-    let income = income_service::delete_income(&mut conn, income_id.into_inner())?;
+pub async fn delete_income(pool: web::Data<DbPool>, storage: web::Data<dyn AttachmentStorage>, income_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let (income, storage_keys) = {
+        let mut conn = pool.get()?;
+        income_service::delete_income(&mut conn, income_id.into_inner())?
+    };
+
+    attachment_storage::remove_files(storage.get_ref(), &storage_keys).await;
     Ok(response::ok(income))
 }
\ No newline at end of file
@@ -0,0 +1,31 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use crate::models::insight::{AnomalyQuery, ExpenseAnomaly};
+
+use crate::config::errors::{AppError, response};
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::anomaly_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get the current user's expenses that look unusual compared to their history
+#[utoipa::path(
+    get,
+    path = "/api/insights/anomalies",
+    responses(
+        (status = 200, description = "Unusual expenses with the reasons they were flagged, newest first", body = Vec<ExpenseAnomaly>),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(AnomalyQuery),
+    tag = "insights"
+)]
+pub async fn get_anomalies(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<AnomalyQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let anomalies = anomaly_service::get_anomalies(&mut conn, user_id, &query)?;
+    Ok(response::ok(anomalies))
+}
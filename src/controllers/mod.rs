@@ -1,3 +1,15 @@
 pub mod income_controller;
 pub mod expense_controller;
-pub mod auth_controller;
\ No newline at end of file
+pub mod auth_controller;
+pub mod goal_controller;
+pub mod attachment_controller;
+pub mod payee_controller;
+pub mod search_controller;
+pub mod transaction_controller;
+pub mod report_controller;
+pub mod insight_controller;
+pub mod duplicate_controller;
+pub mod net_worth_controller;
+pub mod export_controller;
+pub mod import_controller;
+pub mod rule_controller;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use actix_web::{web, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use uuid::Uuid;
+use crate::models::net_worth::{NetWorthItem, NetWorthItemWithSnapshots, NetWorthSnapshot, NewNetWorthItem, NewNetWorthSnapshot, UpdateNetWorthItem};
+
+use crate::config::errors::{AppError, response};
+use crate::services::net_worth_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get assets and liabilities by user ID
+#[utoipa::path(
+    get,
+    path = "/api/net-worth/items/user/{user_id}",
+    responses(
+        (status = 200, description = "Assets and liabilities with their snapshots", body = Vec<NetWorthItemWithSnapshots>),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    tag = "net-worth"
+)]
+pub async fn get_items_by_user_id(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let items = net_worth_service::get_items_by_user_id(&mut conn, user_id.into_inner())?;
+    Ok(response::ok(items))
+}
+
+/// Create new asset or liability
+#[utoipa::path(
+    post,
+    path = "/api/net-worth/items",
+    request_body = NewNetWorthItem,
+    responses(
+        (status = 201, description = "Item created successfully", body = NetWorthItem),
+        (status = 400, description = "Invalid input"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "net-worth"
+)]
+pub async fn create_item(pool: web::Data<DbPool>, new_item: web::Json<NewNetWorthItem>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let item = net_worth_service::create_item(&mut conn, new_item.into_inner())?;
+    Ok(response::created(item))
+}
+
+/// Update asset or liability
+#[utoipa::path(
+    put,
+    path = "/api/net-worth/items/{item_id}",
+    request_body = UpdateNetWorthItem,
+    responses(
+        (status = 200, description = "Item updated successfully", body = NetWorthItem),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Item not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("item_id" = Uuid, Path, description = "Item ID")
+    ),
+    tag = "net-worth"
+)]
+pub async fn update_item(pool: web::Data<DbPool>, item_id: web::Path<Uuid>, update_item: web::Json<UpdateNetWorthItem>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let item = net_worth_service::update_item(&mut conn, item_id.into_inner(), update_item.into_inner())?;
+    Ok(response::ok(item))
+}
+
+/// Delete asset or liability and its snapshots
+#[utoipa::path(
+    delete,
+    path = "/api/net-worth/items/{item_id}",
+    responses(
+        (status = 200, description = "Item deleted successfully"),
+        (status = 404, description = "Item not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("item_id" = Uuid, Path, description = "Item ID")
+    ),
+    tag = "net-worth"
+)]
+pub async fn delete_item(pool: web::Data<DbPool>, item_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let item = net_worth_service::delete_item(&mut conn, item_id.into_inner())?;
+    Ok(response::ok(item))
+}
+
+/// Record the value of an asset or liability on a date, replacing that day's snapshot
+#[utoipa::path(
+    post,
+    path = "/api/net-worth/items/{item_id}/snapshots",
+    request_body = NewNetWorthSnapshot,
+    responses(
+        (status = 201, description = "Snapshot recorded successfully", body = NetWorthSnapshot),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Item not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("item_id" = Uuid, Path, description = "Item ID")
+    ),
+    tag = "net-worth"
+)]
+pub async fn record_snapshot(pool: web::Data<DbPool>, item_id: web::Path<Uuid>, new_snapshot: web::Json<NewNetWorthSnapshot>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let snapshot = net_worth_service::record_snapshot(&mut conn, item_id.into_inner(), new_snapshot.into_inner())?;
+    Ok(response::created(snapshot))
+}
+
+/// Delete snapshot
+#[utoipa::path(
+    delete,
+    path = "/api/net-worth/snapshots/{snapshot_id}",
+    responses(
+        (status = 200, description = "Snapshot deleted successfully"),
+        (status = 404, description = "Snapshot not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("snapshot_id" = Uuid, Path, description = "Snapshot ID")
+    ),
+    tag = "net-worth"
+)]
+pub async fn delete_snapshot(pool: web::Data<DbPool>, snapshot_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let snapshot = net_worth_service::delete_snapshot(&mut conn, snapshot_id.into_inner())?;
+    Ok(response::ok(snapshot))
+}
@@ -0,0 +1,175 @@
+use actix_web::{web, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use uuid::Uuid;
+use crate::models::payee::{
+    NewPayee, NewPayeeRule, Payee, PayeeNormalizationResult, PayeeRule, PayeeSpending,
+    PayeeSpendingQuery, PayeeWithRules, UpdatePayee,
+};
+
+use crate::config::errors::{AppError, response};
+use crate::services::payee_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get payees and their normalization rules by user ID
+#[utoipa::path(
+    get,
+    path = "/api/payees/user/{user_id}",
+    responses(
+        (status = 200, description = "List of payees for user", body = Vec<PayeeWithRules>),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    tag = "payees"
+)]
+pub async fn get_payees_by_user_id(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let payees = payee_service::get_payees_by_user_id(&mut conn, user_id.into_inner())?;
+    Ok(response::ok(payees))
+}
+
+/// Create new payee
+#[utoipa::path(
+    post,
+    path = "/api/payees",
+    request_body = NewPayee,
+    responses(
+        (status = 201, description = "Payee created successfully", body = Payee),
+        (status = 400, description = "Invalid input"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "payees"
+)]
+pub async fn create_payee(pool: web::Data<DbPool>, new_payee: web::Json<NewPayee>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let payee = payee_service::create_payee(&mut conn, new_payee.into_inner())?;
+    Ok(response::created(payee))
+}
+
+/// Update payee
+#[utoipa::path(
+    put,
+    path = "/api/payees/{payee_id}",
+    request_body = UpdatePayee,
+    responses(
+        (status = 200, description = "Payee updated successfully", body = Payee),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Payee not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("payee_id" = Uuid, Path, description = "Payee ID")
+    ),
+    tag = "payees"
+)]
+pub async fn update_payee(pool: web::Data<DbPool>, payee_id: web::Path<Uuid>, update_payee: web::Json<UpdatePayee>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let payee = payee_service::update_payee(&mut conn, payee_id.into_inner(), update_payee.into_inner())?;
+    Ok(response::ok(payee))
+}
+
+/// Delete payee, its transactions are kept but unassigned
+#[utoipa::path(
+    delete,
+    path = "/api/payees/{payee_id}",
+    responses(
+        (status = 200, description = "Payee deleted successfully", body = Payee),
+        (status = 404, description = "Payee not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("payee_id" = Uuid, Path, description = "Payee ID")
+    ),
+    tag = "payees"
+)]
+pub async fn delete_payee(pool: web::Data<DbPool>, payee_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let payee = payee_service::delete_payee(&mut conn, payee_id.into_inner())?;
+    Ok(response::ok(payee))
+}
+
+/// Add a normalization rule mapping raw names to a payee
+#[utoipa::path(
+    post,
+    path = "/api/payees/{payee_id}/rules",
+    request_body = NewPayeeRule,
+    responses(
+        (status = 201, description = "Rule created successfully", body = PayeeRule),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Payee not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("payee_id" = Uuid, Path, description = "Payee ID")
+    ),
+    tag = "payees"
+)]
+pub async fn add_rule(pool: web::Data<DbPool>, payee_id: web::Path<Uuid>, new_rule: web::Json<NewPayeeRule>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let rule = payee_service::add_rule(&mut conn, payee_id.into_inner(), new_rule.into_inner())?;
+    Ok(response::created(rule))
+}
+
+/// Delete a normalization rule
+#[utoipa::path(
+    delete,
+    path = "/api/payees/rules/{rule_id}",
+    responses(
+        (status = 200, description = "Rule deleted successfully", body = PayeeRule),
+        (status = 404, description = "Rule not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("rule_id" = Uuid, Path, description = "Rule ID")
+    ),
+    tag = "payees"
+)]
+pub async fn delete_rule(pool: web::Data<DbPool>, rule_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let rule = payee_service::delete_rule(&mut conn, rule_id.into_inner())?;
+    Ok(response::ok(rule))
+}
+
+/// Assign payees to existing transactions using the current rules
+#[utoipa::path(
+    post,
+    path = "/api/payees/user/{user_id}/normalize",
+    responses(
+        (status = 200, description = "Number of transactions assigned a payee", body = PayeeNormalizationResult),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    tag = "payees"
+)]
+pub async fn normalize_history(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let result = payee_service::normalize_history(&mut conn, user_id.into_inner())?;
+    Ok(response::ok(result))
+}
+
+/// Get total spend per payee
+#[utoipa::path(
+    get,
+    path = "/api/payees/user/{user_id}/spending",
+    responses(
+        (status = 200, description = "Expense totals per payee", body = Vec<PayeeSpending>),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID"),
+        ("from" = Option<chrono::NaiveDate>, Query, description = "First date to include"),
+        ("to" = Option<chrono::NaiveDate>, Query, description = "Last date to include")
+    ),
+    tag = "payees"
+)]
+pub async fn get_spending_by_payee(pool: web::Data<DbPool>, user_id: web::Path<Uuid>, query: web::Query<PayeeSpendingQuery>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let spending = payee_service::get_spending_by_payee(&mut conn, user_id.into_inner(), &query)?;
+    Ok(response::ok(spending))
+}
@@ -0,0 +1,122 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use crate::models::forecast::{CashFlowForecast, ForecastQuery};
+use crate::models::net_worth::{NetWorthPoint, NetWorthQuery};
+use crate::models::report::{BreakdownQuery, PeriodSummary, SpendingBreakdown, StatementQuery, SummaryQuery};
+
+use crate::config::errors::{AppError, response};
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::{forecast_service, net_worth_service, report_service, statement_pdf};
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get income, expenses and savings per month or year for the current user
+#[utoipa::path(
+    get,
+    path = "/api/reports/summary",
+    responses(
+        (status = 200, description = "Totals per period, oldest first", body = Vec<PeriodSummary>),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(SummaryQuery),
+    tag = "reports"
+)]
+pub async fn get_summary(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<SummaryQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let summary = report_service::get_summary(&mut conn, user_id, &query)?;
+    Ok(response::ok(summary))
+}
+
+/// Get the current user's expenses grouped by item, weekday, day of month or amount band
+#[utoipa::path(
+    get,
+    path = "/api/reports/spending-breakdown",
+    responses(
+        (status = 200, description = "Spending per group with the previous period for comparison", body = SpendingBreakdown),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(BreakdownQuery),
+    tag = "reports"
+)]
+pub async fn get_spending_breakdown(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<BreakdownQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let breakdown = report_service::get_spending_breakdown(&mut conn, user_id, &query)?;
+    Ok(response::ok(breakdown))
+}
+
+/// Project the current user's balance for the coming days
+#[utoipa::path(
+    get,
+    path = "/api/reports/forecast",
+    responses(
+        (status = 200, description = "Projected daily balance with the recurring transactions and spending averages behind it", body = CashFlowForecast),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(ForecastQuery),
+    tag = "reports"
+)]
+pub async fn get_forecast(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<ForecastQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let forecast = forecast_service::get_forecast(&mut conn, user_id, &query)?;
+    Ok(response::ok(forecast))
+}
+
+/// Get the current user's net worth at the end of every month or year
+#[utoipa::path(
+    get,
+    path = "/api/reports/net-worth",
+    responses(
+        (status = 200, description = "Net worth per period, oldest first, filled in from cash flow where snapshots are missing", body = Vec<NetWorthPoint>),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(NetWorthQuery),
+    tag = "reports"
+)]
+pub async fn get_net_worth(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<NetWorthQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let net_worth = net_worth_service::get_net_worth(&mut conn, user_id, &query)?;
+    Ok(response::ok(net_worth))
+}
+
+/// Download the current user's statement for one month as a printable PDF
+#[utoipa::path(
+    get,
+    path = "/api/reports/statement",
+    responses(
+        (status = 200, description = "PDF with the opening and closing balance, income and expense tables and the top items by total", content_type = "application/pdf"),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(StatementQuery),
+    tag = "reports"
+)]
+pub async fn get_statement(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<StatementQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let statement = report_service::get_monthly_statement(&mut conn, user_id, &query)?;
+    let body = statement_pdf::render_statement(&statement);
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("statement-{}.pdf", statement.month.format("%Y-%m")))],
+        })
+        .body(body))
+}
@@ -0,0 +1,132 @@
+use actix_web::{web, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use uuid::Uuid;
+use crate::models::rule::{
+    NewTransactionRule, RuleApplicationResult, RuleSample, RuleTestResult, TransactionRule, UpdateTransactionRule,
+};
+
+use crate::config::errors::{AppError, response};
+use crate::services::rule_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get transaction rules by user ID, in the order they run
+#[utoipa::path(
+    get,
+    path = "/api/rules/user/{user_id}",
+    responses(
+        (status = 200, description = "List of rules for user", body = Vec<TransactionRule>),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    tag = "rules"
+)]
+pub async fn get_rules_by_user_id(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let rules = rule_service::get_rules_by_user_id(&mut conn, user_id.into_inner())?;
+    Ok(response::ok(rules))
+}
+
+/// Create new transaction rule
+#[utoipa::path(
+    post,
+    path = "/api/rules",
+    request_body = NewTransactionRule,
+    responses(
+        (status = 201, description = "Rule created successfully", body = TransactionRule),
+        (status = 400, description = "Invalid input"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "rules"
+)]
+pub async fn create_rule(pool: web::Data<DbPool>, new_rule: web::Json<NewTransactionRule>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let rule = rule_service::create_rule(&mut conn, new_rule.into_inner())?;
+    Ok(response::created(rule))
+}
+
+/// Update transaction rule
+#[utoipa::path(
+    put,
+    path = "/api/rules/{rule_id}",
+    request_body = UpdateTransactionRule,
+    responses(
+        (status = 200, description = "Rule updated successfully", body = TransactionRule),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Rule not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("rule_id" = Uuid, Path, description = "Rule ID")
+    ),
+    tag = "rules"
+)]
+pub async fn update_rule(pool: web::Data<DbPool>, rule_id: web::Path<Uuid>, update_rule: web::Json<UpdateTransactionRule>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let rule = rule_service::update_rule(&mut conn, rule_id.into_inner(), update_rule.into_inner())?;
+    Ok(response::ok(rule))
+}
+
+/// Delete transaction rule, transactions it changed keep their values
+#[utoipa::path(
+    delete,
+    path = "/api/rules/{rule_id}",
+    responses(
+        (status = 200, description = "Rule deleted successfully", body = TransactionRule),
+        (status = 404, description = "Rule not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("rule_id" = Uuid, Path, description = "Rule ID")
+    ),
+    tag = "rules"
+)]
+pub async fn delete_rule(pool: web::Data<DbPool>, rule_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let rule = rule_service::delete_rule(&mut conn, rule_id.into_inner())?;
+    Ok(response::ok(rule))
+}
+
+/// Show which of the user's rules match a sample transaction and what they turn it into
+#[utoipa::path(
+    post,
+    path = "/api/rules/user/{user_id}/test",
+    request_body = RuleSample,
+    responses(
+        (status = 200, description = "Matching rules and the resulting transaction", body = RuleTestResult),
+        (status = 400, description = "Invalid input"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    tag = "rules"
+)]
+pub async fn test_rules(pool: web::Data<DbPool>, user_id: web::Path<Uuid>, sample: web::Json<RuleSample>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let result = rule_service::test_rules(&mut conn, user_id.into_inner(), sample.into_inner())?;
+    Ok(response::ok(result))
+}
+
+/// Re-apply the current rules to all existing transactions of the user
+#[utoipa::path(
+    post,
+    path = "/api/rules/user/{user_id}/apply",
+    responses(
+        (status = 200, description = "Number of transactions the rules changed", body = RuleApplicationResult),
+        (status = 500, description = "Internal server error")
+    ),
+    params(
+        ("user_id" = Uuid, Path, description = "User ID")
+    ),
+    tag = "rules"
+)]
+pub async fn apply_rules(pool: web::Data<DbPool>, user_id: web::Path<Uuid>) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.get()?;
+    let result = rule_service::apply_to_history(&mut conn, user_id.into_inner())?;
+    Ok(response::ok(result))
+}
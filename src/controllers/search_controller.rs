@@ -0,0 +1,31 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use crate::models::search::{SearchQuery, SearchResult};
+
+use crate::config::errors::{AppError, response};
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::search_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Search the current user's incomes and expenses
+#[utoipa::path(
+    get,
+    path = "/api/search",
+    responses(
+        (status = 200, description = "Matching incomes and expenses, best matches first", body = Vec<SearchResult>),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(SearchQuery),
+    tag = "search"
+)]
+pub async fn search(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<SearchQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let results = search_service::search_transactions(&mut conn, user_id, &query)?;
+    Ok(response::ok(results))
+}
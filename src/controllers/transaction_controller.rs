@@ -0,0 +1,31 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::PgConnection;
+use r2d2::Pool;
+use diesel::r2d2::ConnectionManager;
+use crate::models::transaction::{TransactionEntry, TransactionQuery};
+
+use crate::config::errors::{AppError, response};
+use crate::middleware::auth_middleware::current_user_id;
+use crate::services::transaction_service;
+
+type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Get the current user's incomes and expenses as one feed with a running balance
+#[utoipa::path(
+    get,
+    path = "/api/transactions",
+    responses(
+        (status = 200, description = "Incomes and expenses ordered by date, the `x-total-count` header holds the number of matching transactions and the `Link` header the next/prev pages when keyset paging", body = Vec<TransactionEntry>),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "Internal server error")
+    ),
+    params(TransactionQuery),
+    tag = "transactions"
+)]
+pub async fn get_transactions(req: HttpRequest, pool: web::Data<DbPool>, query: web::Query<TransactionQuery>) -> Result<HttpResponse, AppError> {
+    let user_id = current_user_id(&req)?;
+    let mut conn = pool.get()?;
+    let page = transaction_service::get_transactions(&mut conn, user_id, &query)?;
+    Ok(response::ok_page(&req, page))
+}
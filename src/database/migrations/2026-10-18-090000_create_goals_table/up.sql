@@ -0,0 +1,26 @@
+CREATE TABLE goals (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    target_amount NUMERIC NOT NULL,
+    target_date DATE NOT NULL,
+    match_source VARCHAR,
+    match_percent NUMERIC,
+    description TEXT,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE TABLE goal_contributions (
+    id UUID PRIMARY KEY,
+    goal_id UUID NOT NULL,
+    income_id UUID,
+    amount NUMERIC NOT NULL,
+    date DATE NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (goal_id) REFERENCES goals(id) ON DELETE CASCADE,
+    FOREIGN KEY (income_id) REFERENCES incomes(id) ON DELETE CASCADE
+);
@@ -0,0 +1 @@
+DROP TABLE expense_splits;
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE expense_splits (
+    id UUID PRIMARY KEY,
+    expense_id UUID NOT NULL,
+    label VARCHAR NOT NULL,
+    amount NUMERIC NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (expense_id) REFERENCES expenses(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_expense_splits_expense_id ON expense_splits(expense_id);
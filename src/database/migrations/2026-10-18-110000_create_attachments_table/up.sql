@@ -0,0 +1,19 @@
+CREATE TABLE attachments (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    expense_id UUID,
+    income_id UUID,
+    file_name VARCHAR NOT NULL,
+    content_type VARCHAR NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    storage_key VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (expense_id) REFERENCES expenses(id) ON DELETE CASCADE,
+    FOREIGN KEY (income_id) REFERENCES incomes(id) ON DELETE CASCADE,
+    CHECK ((expense_id IS NULL) <> (income_id IS NULL))
+);
+
+CREATE INDEX idx_attachments_expense_id ON attachments(expense_id);
+CREATE INDEX idx_attachments_income_id ON attachments(income_id);
@@ -0,0 +1,4 @@
+ALTER TABLE incomes DROP COLUMN payer_id;
+ALTER TABLE expenses DROP COLUMN payee_id;
+DROP TABLE payee_rules;
+DROP TABLE payees;
@@ -0,0 +1,26 @@
+CREATE TABLE payees (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE payee_rules (
+    id UUID PRIMARY KEY,
+    payee_id UUID NOT NULL,
+    match_type VARCHAR NOT NULL,
+    pattern VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (payee_id) REFERENCES payees(id) ON DELETE CASCADE,
+    CHECK (match_type IN ('exact', 'starts_with', 'contains'))
+);
+
+ALTER TABLE expenses ADD COLUMN payee_id UUID REFERENCES payees(id) ON DELETE SET NULL;
+ALTER TABLE incomes ADD COLUMN payer_id UUID REFERENCES payees(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_expenses_payee_id ON expenses(payee_id);
+CREATE INDEX idx_incomes_payer_id ON incomes(payer_id);
@@ -0,0 +1,2 @@
+DROP INDEX idx_incomes_user_date_id;
+DROP INDEX idx_expenses_user_date_id;
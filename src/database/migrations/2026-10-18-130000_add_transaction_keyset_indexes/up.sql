@@ -0,0 +1,3 @@
+-- Supports keyset pagination over (date, id) within a user's history
+CREATE INDEX idx_expenses_user_date_id ON expenses(user_id, date, id);
+CREATE INDEX idx_incomes_user_date_id ON incomes(user_id, date, id);
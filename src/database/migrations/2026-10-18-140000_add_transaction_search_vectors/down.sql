@@ -0,0 +1,5 @@
+DROP FUNCTION escape_html(TEXT);
+DROP INDEX idx_incomes_search_vector;
+DROP INDEX idx_expenses_search_vector;
+ALTER TABLE incomes DROP COLUMN search_vector;
+ALTER TABLE expenses DROP COLUMN search_vector;
@@ -0,0 +1,19 @@
+-- Full-text search vectors, the name weighs more than the description.
+-- Not part of schema.rs: Diesel has no tsvector type, search queries use raw SQL.
+ALTER TABLE expenses ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', item_name), 'A') ||
+    setweight(to_tsvector('english', coalesce(description, '')), 'B')
+) STORED;
+
+ALTER TABLE incomes ADD COLUMN search_vector tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', source), 'A') ||
+    setweight(to_tsvector('english', coalesce(description, '')), 'B')
+) STORED;
+
+CREATE INDEX idx_expenses_search_vector ON expenses USING GIN (search_vector);
+CREATE INDEX idx_incomes_search_vector ON incomes USING GIN (search_vector);
+
+-- Used to escape text before ts_headline wraps matches in <mark> tags
+CREATE FUNCTION escape_html(input TEXT) RETURNS TEXT AS $$
+    SELECT replace(replace(replace(input, '&', '&amp;'), '<', '&lt;'), '>', '&gt;')
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1,2 @@
+DROP TABLE net_worth_snapshots;
+DROP TABLE net_worth_items;
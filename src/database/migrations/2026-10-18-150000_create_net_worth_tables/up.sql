@@ -0,0 +1,26 @@
+CREATE TABLE net_worth_items (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    category VARCHAR,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    CHECK (kind IN ('asset', 'liability'))
+);
+
+CREATE TABLE net_worth_snapshots (
+    id UUID PRIMARY KEY,
+    item_id UUID NOT NULL,
+    date DATE NOT NULL,
+    value NUMERIC NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (item_id) REFERENCES net_worth_items(id) ON DELETE CASCADE,
+    UNIQUE (item_id, date),
+    CHECK (value >= 0)
+);
+
+CREATE INDEX idx_net_worth_items_user_id ON net_worth_items(user_id);
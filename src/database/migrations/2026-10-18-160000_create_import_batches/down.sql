@@ -0,0 +1,3 @@
+ALTER TABLE expenses DROP COLUMN import_batch_id;
+ALTER TABLE incomes DROP COLUMN import_batch_id;
+DROP TABLE import_batches;
@@ -0,0 +1,15 @@
+CREATE TABLE import_batches (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    source VARCHAR NOT NULL,
+    file_name VARCHAR,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+ALTER TABLE incomes ADD COLUMN import_batch_id UUID REFERENCES import_batches(id);
+ALTER TABLE expenses ADD COLUMN import_batch_id UUID REFERENCES import_batches(id);
+
+CREATE INDEX idx_import_batches_user_id ON import_batches(user_id);
+CREATE INDEX idx_incomes_import_batch_id ON incomes(import_batch_id);
+CREATE INDEX idx_expenses_import_batch_id ON expenses(import_batch_id);
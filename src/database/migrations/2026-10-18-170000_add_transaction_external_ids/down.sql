@@ -0,0 +1,2 @@
+ALTER TABLE expenses DROP COLUMN external_account, DROP COLUMN external_id;
+ALTER TABLE incomes DROP COLUMN external_account, DROP COLUMN external_id;
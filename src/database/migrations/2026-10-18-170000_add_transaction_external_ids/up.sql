@@ -0,0 +1,6 @@
+-- Account and bank reference of imported transactions, used to skip rows imported before
+ALTER TABLE incomes ADD COLUMN external_account VARCHAR, ADD COLUMN external_id VARCHAR;
+ALTER TABLE expenses ADD COLUMN external_account VARCHAR, ADD COLUMN external_id VARCHAR;
+
+CREATE INDEX idx_incomes_external_id ON incomes(user_id, external_id) WHERE external_id IS NOT NULL;
+CREATE INDEX idx_expenses_external_id ON expenses(user_id, external_id) WHERE external_id IS NOT NULL;
@@ -0,0 +1,17 @@
+CREATE TABLE transaction_rules (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    name VARCHAR NOT NULL,
+    position INTEGER NOT NULL,
+    applies_to VARCHAR NOT NULL DEFAULT 'all',
+    conditions JSONB NOT NULL,
+    actions JSONB NOT NULL,
+    stop_processing BOOLEAN NOT NULL DEFAULT FALSE,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    CHECK (applies_to IN ('all', 'income', 'expense'))
+);
+
+CREATE INDEX idx_transaction_rules_user_id_position ON transaction_rules(user_id, position);
@@ -24,13 +24,72 @@ mod database;
         controllers::income_controller::get_all_incomes,
         controllers::income_controller::get_incomes_by_user_id,
         controllers::income_controller::create_income,
+        controllers::income_controller::bulk_incomes,
         controllers::income_controller::update_income,
         controllers::income_controller::delete_income,
         controllers::expense_controller::get_all_expenses,
         controllers::expense_controller::get_expenses_by_user_id,
         controllers::expense_controller::create_expense,
+        controllers::expense_controller::bulk_expenses,
         controllers::expense_controller::update_expense,
         controllers::expense_controller::delete_expense,
+        controllers::goal_controller::get_goals_by_user_id,
+        controllers::goal_controller::create_goal,
+        controllers::goal_controller::update_goal,
+        controllers::goal_controller::delete_goal,
+        controllers::goal_controller::get_contributions,
+        controllers::goal_controller::add_contribution,
+        controllers::goal_controller::get_goal_progress,
+        controllers::attachment_controller::upload_expense_attachment,
+        controllers::attachment_controller::get_expense_attachments,
+        controllers::attachment_controller::download_expense_attachment,
+        controllers::attachment_controller::delete_expense_attachment,
+        controllers::attachment_controller::upload_income_attachment,
+        controllers::attachment_controller::get_income_attachments,
+        controllers::attachment_controller::download_income_attachment,
+        controllers::attachment_controller::delete_income_attachment,
+        controllers::payee_controller::get_payees_by_user_id,
+        controllers::payee_controller::create_payee,
+        controllers::payee_controller::update_payee,
+        controllers::payee_controller::delete_payee,
+        controllers::payee_controller::add_rule,
+        controllers::payee_controller::delete_rule,
+        controllers::payee_controller::normalize_history,
+        controllers::payee_controller::get_spending_by_payee,
+        controllers::search_controller::search,
+        controllers::transaction_controller::get_transactions,
+        controllers::report_controller::get_summary,
+        controllers::report_controller::get_spending_breakdown,
+        controllers::report_controller::get_forecast,
+        controllers::report_controller::get_net_worth,
+        controllers::report_controller::get_statement,
+        controllers::insight_controller::get_anomalies,
+        controllers::duplicate_controller::get_duplicates,
+        controllers::duplicate_controller::merge_duplicates,
+        controllers::net_worth_controller::get_items_by_user_id,
+        controllers::net_worth_controller::create_item,
+        controllers::net_worth_controller::update_item,
+        controllers::net_worth_controller::delete_item,
+        controllers::net_worth_controller::record_snapshot,
+        controllers::net_worth_controller::delete_snapshot,
+        controllers::export_controller::export_incomes,
+        controllers::export_controller::export_expenses,
+        controllers::export_controller::export_qif,
+        controllers::export_controller::export_full,
+        controllers::import_controller::import_csv,
+        controllers::import_controller::import_ofx,
+        controllers::import_controller::import_qif,
+        controllers::import_controller::import_camt,
+        controllers::import_controller::import_mt940,
+        controllers::import_controller::import_archive,
+        controllers::import_controller::get_import_batches,
+        controllers::import_controller::undo_import,
+        controllers::rule_controller::get_rules_by_user_id,
+        controllers::rule_controller::create_rule,
+        controllers::rule_controller::update_rule,
+        controllers::rule_controller::delete_rule,
+        controllers::rule_controller::test_rules,
+        controllers::rule_controller::apply_rules,
     ),
     components(
         schemas(
@@ -44,15 +103,111 @@ mod database;
             models::income::NewIncome,
             models::income::UpdateIncome,
             models::income::IncomeWithUser,
+            models::income::IncomeSortField,
+            models::income::IncomeBulkOperation,
+            models::income::BulkIncomeRequest,
+            models::income::IncomeBulkItem,
+            models::income::BulkIncomeResult,
+            models::pagination::SortOrder,
             models::expense::Expense,
             models::expense::NewExpense,
-            models::expense::UpdateExpense
+            models::expense::UpdateExpense,
+            models::expense::ExpenseSplit,
+            models::expense::NewExpenseSplit,
+            models::expense::ExpenseWithSplits,
+            models::expense::ExpenseSortField,
+            models::expense::ExpenseBulkOperation,
+            models::expense::BulkExpenseRequest,
+            models::expense::ExpenseBulkItem,
+            models::expense::BulkExpenseResult,
+            models::bulk::BulkMode,
+            models::goal::Goal,
+            models::goal::NewGoal,
+            models::goal::UpdateGoal,
+            models::goal::GoalContribution,
+            models::goal::NewGoalContribution,
+            models::goal::GoalProgress,
+            models::attachment::Attachment,
+            models::attachment::AttachmentUpload,
+            models::payee::Payee,
+            models::payee::NewPayee,
+            models::payee::UpdatePayee,
+            models::payee::PayeeRule,
+            models::payee::NewPayeeRule,
+            models::payee::PayeeWithRules,
+            models::payee::PayeeNormalizationResult,
+            models::payee::PayeeSpending,
+            models::search::SearchResult,
+            models::transaction::TransactionKind,
+            models::transaction::Transaction,
+            models::transaction::TransactionEntry,
+            models::report::ReportPeriod,
+            models::report::PeriodSummary,
+            models::report::BreakdownDimension,
+            models::report::BreakdownEntry,
+            models::report::SpendingBreakdown,
+            models::forecast::Cadence,
+            models::forecast::RecurringTransaction,
+            models::forecast::VariableSpending,
+            models::forecast::ForecastDay,
+            models::forecast::CashFlowForecast,
+            models::insight::AnomalyKind,
+            models::insight::ExpenseAnomaly,
+            models::duplicate::DuplicateCandidate,
+            models::duplicate::DuplicateGroup,
+            models::duplicate::MergeDuplicatesRequest,
+            models::duplicate::MergeDuplicatesResult,
+            models::net_worth::NetWorthItem,
+            models::net_worth::NewNetWorthItem,
+            models::net_worth::UpdateNetWorthItem,
+            models::net_worth::NetWorthSnapshot,
+            models::net_worth::NewNetWorthSnapshot,
+            models::net_worth::NetWorthItemWithSnapshots,
+            models::net_worth::NetWorthPoint,
+            models::import::ImportBatch,
+            models::import::ImportBatchWithCounts,
+            models::import::CsvColumn,
+            models::import::AmountSign,
+            models::import::CsvImportMapping,
+            models::import::ImportRow,
+            models::import::ImportResult,
+            models::import::CsvImportUpload,
+            models::import::StatementUpload,
+            models::export::QifDateOrder,
+            models::archive::AccountArchive,
+            models::archive::ArchiveProfile,
+            models::archive::ArchivedAttachment,
+            models::archive::ArchiveRestoreResult,
+            models::archive::ArchiveUpload,
+            models::rule::TransactionRule,
+            models::rule::NewTransactionRule,
+            models::rule::UpdateTransactionRule,
+            models::rule::RuleField,
+            models::rule::RuleOperator,
+            models::rule::RuleCondition,
+            models::rule::RuleActionKind,
+            models::rule::RuleAction,
+            models::rule::RuleSample,
+            models::rule::RuleTestResult,
+            models::rule::RuleApplicationResult
         )
     ),
     tags(
         (name = "auth", description = "Authentication endpoints"),
         (name = "incomes", description = "Income management endpoints"),
-        (name = "expenses", description = "Expense management endpoints")
+        (name = "expenses", description = "Expense management endpoints"),
+        (name = "goals", description = "Savings goal endpoints"),
+        (name = "attachments", description = "Receipt and document attachment endpoints"),
+        (name = "payees", description = "Payee and merchant normalization endpoints"),
+        (name = "search", description = "Full-text transaction search endpoints"),
+        (name = "transactions", description = "Combined income and expense feed endpoints"),
+        (name = "reports", description = "Aggregated financial report endpoints"),
+        (name = "insights", description = "Spending insight endpoints"),
+        (name = "duplicates", description = "Duplicate transaction detection and merge endpoints"),
+        (name = "net-worth", description = "Asset and liability tracking endpoints"),
+        (name = "export", description = "Data export endpoints"),
+        (name = "imports", description = "Bank statement import endpoints"),
+        (name = "rules", description = "Transaction rule endpoints")
     )
 )]
 struct ApiDoc;
@@ -79,6 +234,7 @@ async fn main() -> io::Result<()> {
     database::db_migrations::run_migrations(&mut conn);
 
     let openapi = ApiDoc::openapi();
+    let attachment_storage = services::attachment_storage::create_storage();
 
     HttpServer::new(move || {
         // Configure custom logger
@@ -95,17 +251,20 @@ async fn main() -> io::Result<()> {
                 "origin",
                 "x-requested-with",
                 "access-control-request-method",
-                "access-control-request-headers"
+                "access-control-request-headers",
+                "x-batch-id"
             ])
-            .expose_headers(vec!["content-type", "x-total-count"])
+            .expose_headers(vec!["content-type", "x-total-count", "link"])
             .max_age(3600)
             .supports_credentials();
 
         App::new()
             .app_data(web::Data::new(pool.clone()))
+            .app_data(web::Data::from(attachment_storage.clone()))
             .wrap(cors)
             .wrap(logger)
             .app_data(config::errors::json_error_handler())
+            .app_data(config::errors::query_error_handler())
             .configure(routes::configure)
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
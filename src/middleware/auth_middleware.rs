@@ -1,7 +1,10 @@
-use actix_web::{dev::ServiceRequest, Error, HttpMessage};
+use actix_web::{dev::ServiceRequest, Error, HttpMessage, HttpRequest};
 use actix_web_httpauth::extractors::bearer::{BearerAuth, Config};
 use actix_web_httpauth::extractors::AuthenticationError;
+use uuid::Uuid;
 
+use crate::config::errors::AppError;
+use crate::models::auth::Claims;
 use crate::services::auth_service::AuthService;
 
 /// JWT token validator middleware
@@ -57,4 +60,15 @@ pub async fn jwt_validator(
             Err((AuthenticationError::from(config).into(), req))
         }
     }
-} 
\ No newline at end of file
+}
+
+/// Get the authenticated user's ID from the claims added by `jwt_validator`
+pub fn current_user_id(req: &HttpRequest) -> Result<Uuid, AppError> {
+    let extensions = req.extensions();
+    let claims = extensions
+        .get::<Claims>()
+        .ok_or_else(|| AppError::Unauthorized("Missing authentication claims".to_string()))?;
+
+    Uuid::parse_str(&claims.sub)
+        .map_err(|_| AppError::Unauthorized("Invalid user ID in token".to_string()))
+}
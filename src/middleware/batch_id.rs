@@ -0,0 +1,24 @@
+use actix_web::HttpRequest;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::middleware::auth_middleware::current_user_id;
+use crate::models::import::ApiBatch;
+
+/// Header clients send to group the incomes and expenses they create into a batch
+/// that can be listed and rolled back like an import
+pub const BATCH_ID_HEADER: &str = "X-Batch-Id";
+
+/// The client's batch from the `X-Batch-Id` header, if one was sent, owned by the signed-in user
+pub fn api_batch(req: &HttpRequest) -> Result<Option<ApiBatch>, AppError> {
+    let Some(value) = req.headers().get(BATCH_ID_HEADER) else {
+        return Ok(None);
+    };
+
+    let id = value
+        .to_str()
+        .ok()
+        .and_then(|value| Uuid::parse_str(value.trim()).ok())
+        .ok_or_else(|| AppError::Validation(format!("{} must be a UUID", BATCH_ID_HEADER)))?;
+    Ok(Some(ApiBatch { id, user_id: current_user_id(req)? }))
+}
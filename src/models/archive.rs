@@ -0,0 +1,119 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+use crate::models::expense::{Expense, ExpenseSplit};
+use crate::models::goal::{Goal, GoalContribution};
+use crate::models::import::ImportBatch;
+use crate::models::income::Income;
+use crate::models::net_worth::{NetWorthItem, NetWorthSnapshot};
+use crate::models::payee::{Payee, PayeeRule};
+use crate::models::rule::TransactionRule;
+
+/// Identifies an account archive, whatever instance produced it
+pub const ARCHIVE_FORMAT: &str = "finstack-account-archive";
+/// Bumped whenever the layout of the archive changes incompatibly
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// Everything an account holds, one list per table, to move it to another instance.
+///
+/// Rows keep their original ids so references between them can be followed, restoring
+/// gives every row a new id.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AccountArchive {
+    #[schema(example = "finstack-account-archive")]
+    pub format: String,
+    #[schema(example = 1)]
+    pub version: u32,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub exported_at: NaiveDateTime,
+    pub profile: ArchiveProfile,
+    pub payees: Vec<Payee>,
+    pub payee_rules: Vec<PayeeRule>,
+    pub import_batches: Vec<ImportBatch>,
+    pub incomes: Vec<Income>,
+    pub expenses: Vec<Expense>,
+    pub expense_splits: Vec<ExpenseSplit>,
+    pub attachments: Vec<ArchivedAttachment>,
+    pub goals: Vec<Goal>,
+    pub goal_contributions: Vec<GoalContribution>,
+    pub net_worth_items: Vec<NetWorthItem>,
+    pub net_worth_snapshots: Vec<NetWorthSnapshot>,
+    /// Missing from archives exported before rules existed
+    #[serde(default)]
+    pub transaction_rules: Vec<TransactionRule>,
+}
+
+/// The user's profile, the password never leaves the instance
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ArchiveProfile {
+    #[schema(example = "John")]
+    pub first_name: String,
+    #[schema(example = "Doe")]
+    pub last_name: String,
+    /// Informational only, restoring keeps the email of the target account
+    #[schema(example = "john@example.com")]
+    pub email: String,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+}
+
+/// An attachment together with its file contents
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedAttachment {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub expense_id: Option<Uuid>,
+    #[schema(example = json!(null))]
+    pub income_id: Option<Uuid>,
+    #[schema(example = "receipt.pdf")]
+    pub file_name: String,
+    #[schema(example = "application/pdf")]
+    pub content_type: String,
+    /// Base64 encoded file contents
+    #[schema(example = "JVBERi0xLjQK")]
+    pub content: String,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+/// How many rows of each kind were restored from an archive
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ArchiveRestoreResult {
+    #[schema(example = 12)]
+    pub payees: usize,
+    #[schema(example = 3)]
+    pub payee_rules: usize,
+    #[schema(example = 2)]
+    pub import_batches: usize,
+    #[schema(example = 24)]
+    pub incomes: usize,
+    #[schema(example = 310)]
+    pub expenses: usize,
+    #[schema(example = 18)]
+    pub expense_splits: usize,
+    #[schema(example = 5)]
+    pub attachments: usize,
+    #[schema(example = 2)]
+    pub goals: usize,
+    #[schema(example = 14)]
+    pub goal_contributions: usize,
+    #[schema(example = 4)]
+    pub net_worth_items: usize,
+    #[schema(example = 36)]
+    pub net_worth_snapshots: usize,
+    #[schema(example = 6)]
+    pub transaction_rules: usize,
+}
+
+/// Multipart archive restore form, documentation only
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct ArchiveUpload {
+    /// An archive downloaded from `GET /api/export/full`
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
@@ -0,0 +1,88 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+use crate::models::schema::attachments;
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
+#[diesel(table_name = attachments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Attachment {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub expense_id: Option<Uuid>,
+    #[schema(example = json!(null))]
+    pub income_id: Option<Uuid>,
+    #[schema(example = "receipt.pdf")]
+    pub file_name: String,
+    #[schema(example = "application/pdf")]
+    pub content_type: String,
+    #[schema(example = 48213)]
+    pub size_bytes: i64,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = attachments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewAttachment {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub expense_id: Option<Uuid>,
+    pub income_id: Option<Uuid>,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl NewAttachment {
+    pub fn new(parent: AttachmentParent, user_id: Uuid, file_name: String, content_type: String, size_bytes: i64) -> Self {
+        let now = chrono::Utc::now().naive_utc();
+        let id = Uuid::new_v4();
+        let (expense_id, income_id) = match parent {
+            AttachmentParent::Expense(expense_id) => (Some(expense_id), None),
+            AttachmentParent::Income(income_id) => (None, Some(income_id)),
+        };
+
+        NewAttachment {
+            id,
+            user_id,
+            expense_id,
+            income_id,
+            file_name,
+            content_type,
+            size_bytes,
+            storage_key: format!("{}/{}", user_id, id),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// The transaction an attachment belongs to
+#[derive(Debug, Clone, Copy)]
+pub enum AttachmentParent {
+    Expense(Uuid),
+    Income(Uuid),
+}
+
+/// Multipart upload form, documentation only
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct AttachmentUpload {
+    /// Receipt image (JPEG, PNG, WebP, HEIC) or PDF document
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
@@ -0,0 +1,16 @@
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+/// Most operations a single bulk request may carry
+pub const MAX_BULK_OPERATIONS: usize = 1000;
+
+/// How a bulk request treats operations that fail
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkMode {
+    /// Run all operations in one transaction, the first failure rolls every one of them back
+    #[default]
+    Atomic,
+    /// Run each operation on its own and report the outcome of each one
+    PerItem,
+}
@@ -0,0 +1,65 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::transaction::{Transaction, TransactionKind};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DuplicateQuery {
+    /// Only look for duplicate incomes or only expenses
+    pub kind: Option<TransactionKind>,
+    /// Largest number of days between duplicates, defaults to 3
+    pub window_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DuplicateCandidate {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    /// The expense item name or income source
+    #[schema(example = "AMAZON MKTP")]
+    pub name: String,
+    #[schema(example = "2024-03-20")]
+    pub date: NaiveDate,
+    #[schema(example = "Imported from bank statement")]
+    pub description: Option<String>,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+}
+
+/// Transactions of the same kind and amount, close in date and with similar names
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateGroup {
+    pub kind: TransactionKind,
+    #[schema(example = "42.99")]
+    pub amount: Decimal,
+    /// The transaction entered first, suggested as the one to keep
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub suggested_keep_id: Uuid,
+    /// Oldest first
+    pub transactions: Vec<DuplicateCandidate>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MergeDuplicatesRequest {
+    pub kind: TransactionKind,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub keep_id: Uuid,
+    /// Transactions to delete after folding their descriptions and attachments into `keep_id`
+    pub merge_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MergeDuplicatesResult {
+    pub kept: Transaction,
+    #[schema(example = 1)]
+    pub merged_count: usize,
+}
+
+#[derive(Debug, diesel::QueryableByName)]
+pub struct DuplicateCandidateId {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+}
@@ -3,10 +3,13 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use rust_decimal::Decimal;
-use utoipa::ToSchema;
-use crate::models::schema::expenses;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::bulk::BulkMode;
+use crate::models::insight::AnomalyKind;
+use crate::models::pagination::SortOrder;
+use crate::models::schema::{expenses, expense_splits};
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable, ToSchema)]
 #[diesel(table_name = expenses)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Expense {
@@ -26,6 +29,17 @@ pub struct Expense {
     pub created_at: NaiveDateTime,
     #[schema(example = "2024-03-20T10:00:00")]
     pub updated_at: NaiveDateTime,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payee_id: Option<Uuid>,
+    /// Import that created the expense, if any
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub import_batch_id: Option<Uuid>,
+    /// Bank account of the imported statement
+    #[schema(example = "DE89370400440532013000")]
+    pub external_account: Option<String>,
+    /// The bank's own reference for the transaction, such as an OFX `FITID`
+    #[schema(example = "20240320-0001")]
+    pub external_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Insertable, ToSchema)]
@@ -38,8 +52,25 @@ pub struct NewExpense {
     pub item_name: String,
     #[schema(example = "50.00")]
     pub amount: Decimal,
+    /// Defaults to today when omitted
+    #[schema(example = "2024-03-20")]
+    pub date: Option<chrono::NaiveDate>,
     #[schema(example = "Weekly groceries")]
     pub description: Option<String>,
+    /// Resolved from `item_name` with the payee normalization rules when omitted
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payee_id: Option<Uuid>,
+    /// Set by imports, never taken from the request body
+    #[serde(skip)]
+    pub import_batch_id: Option<Uuid>,
+    #[serde(skip)]
+    pub external_account: Option<String>,
+    #[serde(skip)]
+    pub external_id: Option<String>,
+    /// Optional line items, their amounts must add up to `amount`
+    #[serde(default)]
+    #[diesel(skip_insertion)]
+    pub splits: Vec<NewExpenseSplit>,
 }
 
 impl NewExpense {
@@ -50,10 +81,14 @@ impl NewExpense {
             user_id: self.user_id,
             item_name: self.item_name,
             amount: self.amount,
-            date: now.date(),
+            date: self.date.unwrap_or(now.date()),
             description: self.description,
             created_at: now,
             updated_at: now,
+            payee_id: self.payee_id,
+            import_batch_id: self.import_batch_id,
+            external_account: self.external_account,
+            external_id: self.external_id,
         }
     }
 }
@@ -70,5 +105,132 @@ pub struct UpdateExpense {
     pub date: Option<chrono::NaiveDate>,
     #[schema(example = "Dinner with friends")]
     pub description: Option<String>,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payee_id: Option<Uuid>,
     pub updated_at: Option<NaiveDateTime>,
-} 
\ No newline at end of file
+    /// Replaces all line items of the expense when present, an empty list removes them
+    #[diesel(skip_update)]
+    pub splits: Option<Vec<NewExpenseSplit>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Associations, Insertable, ToSchema)]
+#[diesel(table_name = expense_splits)]
+#[diesel(belongs_to(Expense))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ExpenseSplit {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub expense_id: Uuid,
+    #[schema(example = "Household")]
+    pub label: String,
+    #[schema(example = "12.50")]
+    pub amount: Decimal,
+    #[schema(example = "Cleaning supplies")]
+    pub note: Option<String>,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NewExpenseSplit {
+    #[schema(example = "Household")]
+    pub label: String,
+    #[schema(example = "12.50")]
+    pub amount: Decimal,
+    #[schema(example = "Cleaning supplies")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpenseWithSplits {
+    #[serde(flatten)]
+    pub expense: Expense,
+    pub splits: Vec<ExpenseSplit>,
+    /// Reasons the expense looks unusual for the user, empty for ordinary expenses
+    pub anomalies: Vec<AnomalyKind>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExpenseQuery {
+    /// Only return expenses having a line item with this label (case-insensitive)
+    pub split_label: Option<String>,
+    /// First date to include
+    pub from: Option<chrono::NaiveDate>,
+    /// Last date to include
+    pub to: Option<chrono::NaiveDate>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    #[param(value_type = Option<String>, example = "10.00")]
+    pub min_amount: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    #[param(value_type = Option<String>, example = "250.00")]
+    pub max_amount: Option<Decimal>,
+    /// Case-insensitive text search in `item_name` and `description`
+    pub search: Option<String>,
+    /// Sort field, defaults to `date`
+    pub sort: Option<ExpenseSortField>,
+    /// Sort direction, defaults to `desc`
+    pub order: Option<SortOrder>,
+    /// Maximum number of rows to return, all rows when omitted
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Opaque cursor from the `Link` header of a previous page, only valid when sorting by date
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpenseSortField {
+    #[default]
+    Date,
+    Amount,
+    ItemName,
+    CreatedAt,
+} 
+
+/// One change in a bulk request, tagged with `op`
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ExpenseBulkOperation {
+    Create { data: NewExpense },
+    Update { id: Uuid, data: UpdateExpense },
+    Delete { id: Uuid },
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkExpenseRequest {
+    /// Defaults to `atomic`
+    #[serde(default)]
+    pub mode: BulkMode,
+    /// Run in the order given, at most 1000
+    pub operations: Vec<ExpenseBulkOperation>,
+}
+
+/// Outcome of one operation, at the same position as in the request
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpenseBulkItem {
+    #[schema(example = 0)]
+    pub index: usize,
+    /// HTTP status the operation would have had as a single call
+    #[schema(example = 201)]
+    pub status: u16,
+    /// The created, updated or deleted expense, when the operation succeeded
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Option<Uuid>,
+    /// The expense as stored, not given for deletes
+    pub expense: Option<ExpenseWithSplits>,
+    #[schema(example = json!(null))]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkExpenseResult {
+    #[schema(example = 98)]
+    pub succeeded: usize,
+    #[schema(example = 2)]
+    pub failed: usize,
+    pub results: Vec<ExpenseBulkItem>,
+}
@@ -0,0 +1,32 @@
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+/// How CSV files are written, so they open cleanly in spreadsheets set to other locales
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CsvOptions {
+    /// Field separator, a single character, defaults to `,`
+    #[param(example = ";")]
+    pub delimiter: Option<String>,
+    /// Decimal separator in amounts, `.` (default) or `,`
+    #[param(example = ",")]
+    pub decimal_separator: Option<String>,
+}
+
+/// Order of day and month in QIF dates, which the format leaves up to the program
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QifDateOrder {
+    /// `12/31/2024`, as written by US versions of Quicken and Microsoft Money
+    #[default]
+    Mdy,
+    /// `31/12/2024`
+    Dmy,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QifOptions {
+    /// Date order of the QIF file, defaults to `mdy`
+    pub date_order: Option<QifDateOrder>,
+}
@@ -0,0 +1,81 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::transaction::TransactionKind;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ForecastQuery {
+    /// Number of days to project, defaults to 30
+    pub days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Cadence {
+    Weekly,
+    Biweekly,
+    Monthly,
+    Yearly,
+}
+
+/// An income or expense that repeats on a regular schedule in the user's history
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecurringTransaction {
+    pub kind: TransactionKind,
+    /// Name of the most recent occurrence
+    #[schema(example = "Rent")]
+    pub name: String,
+    pub cadence: Cadence,
+    /// Median of the latest occurrences
+    #[schema(example = "900.00")]
+    pub amount: Decimal,
+    #[schema(example = 12)]
+    pub occurrences: usize,
+    #[schema(example = "2024-03-01")]
+    pub last_date: NaiveDate,
+    #[schema(example = "2024-04-01")]
+    pub next_date: NaiveDate,
+}
+
+/// Average daily spending on an item that does not follow a schedule
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VariableSpending {
+    /// Normalized item name
+    #[schema(example = "groceries")]
+    pub item_name: String,
+    #[schema(example = "12.40")]
+    pub daily_average: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ForecastDay {
+    #[schema(example = "2024-03-21")]
+    pub date: NaiveDate,
+    /// Scheduled and recurring income expected on the day
+    #[schema(example = "0.00")]
+    pub income: Decimal,
+    /// Scheduled and recurring expenses plus the estimated variable spending
+    #[schema(example = "24.80")]
+    pub expenses: Decimal,
+    /// Projected balance at the end of the day
+    #[schema(example = "1495.95")]
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CashFlowForecast {
+    /// The last day of actual history, the series starts the day after
+    #[schema(example = "2024-03-20")]
+    pub as_of: NaiveDate,
+    /// All income minus all expenses up to and including `as_of`
+    #[schema(example = "1520.75")]
+    pub starting_balance: Decimal,
+    /// Estimated spending per day outside of recurring expenses
+    #[schema(example = "24.80")]
+    pub daily_variable_spending: Decimal,
+    pub recurring: Vec<RecurringTransaction>,
+    pub variable_spending: Vec<VariableSpending>,
+    pub series: Vec<ForecastDay>,
+}
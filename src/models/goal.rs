@@ -0,0 +1,126 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+use crate::models::schema::{goals, goal_contributions};
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
+#[diesel(table_name = goals)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Goal {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Emergency fund")]
+    pub name: String,
+    #[schema(example = "10000.00")]
+    pub target_amount: Decimal,
+    #[schema(example = "2025-12-31")]
+    pub target_date: NaiveDate,
+    /// Incomes whose `source` contains this text (case-insensitive) contribute to the goal
+    #[schema(example = "Salary")]
+    pub match_source: Option<String>,
+    /// Percentage of each matching income that is contributed, defaults to 100
+    #[schema(example = "10.00")]
+    pub match_percent: Option<Decimal>,
+    #[schema(example = "Six months of expenses")]
+    pub description: Option<String>,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewGoal {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Emergency fund")]
+    pub name: String,
+    #[schema(example = "10000.00")]
+    pub target_amount: Decimal,
+    #[schema(example = "2025-12-31")]
+    pub target_date: NaiveDate,
+    #[schema(example = "Salary")]
+    pub match_source: Option<String>,
+    #[schema(example = "10.00")]
+    pub match_percent: Option<Decimal>,
+    #[schema(example = "Six months of expenses")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsChangeset, ToSchema)]
+#[diesel(table_name = goals)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UpdateGoal {
+    #[schema(example = "New car")]
+    pub name: Option<String>,
+    #[schema(example = "15000.00")]
+    pub target_amount: Option<Decimal>,
+    #[schema(example = "2026-06-30")]
+    pub target_date: Option<NaiveDate>,
+    #[schema(example = "Bonus")]
+    pub match_source: Option<String>,
+    #[schema(example = "50.00")]
+    pub match_percent: Option<Decimal>,
+    #[schema(example = "Down payment")]
+    pub description: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
+#[diesel(table_name = goal_contributions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GoalContribution {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub goal_id: Uuid,
+    /// Set when the contribution was recorded from a matching income
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub income_id: Option<Uuid>,
+    #[schema(example = "250.00")]
+    pub amount: Decimal,
+    #[schema(example = "2024-03-20")]
+    pub date: NaiveDate,
+    #[schema(example = "Birthday money")]
+    pub note: Option<String>,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewGoalContribution {
+    #[schema(example = "250.00")]
+    pub amount: Decimal,
+    #[schema(example = "2024-03-20")]
+    pub date: NaiveDate,
+    #[schema(example = "Birthday money")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GoalProgress {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub goal_id: Uuid,
+    #[schema(example = "10000.00")]
+    pub target_amount: Decimal,
+    #[schema(example = "2500.00")]
+    pub saved_amount: Decimal,
+    #[schema(example = "7500.00")]
+    pub remaining_amount: Decimal,
+    #[schema(example = "25.00")]
+    pub percent_complete: Decimal,
+    #[schema(example = "2025-12-31")]
+    pub target_date: NaiveDate,
+    #[schema(example = 15)]
+    pub months_remaining: i64,
+    /// Amount to contribute each remaining month to reach the target on time
+    #[schema(example = "500.00")]
+    pub required_monthly_contribution: Decimal,
+}
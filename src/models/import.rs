@@ -0,0 +1,219 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::expense::NewExpenseSplit;
+use crate::models::schema::import_batches;
+use crate::models::transaction::TransactionKind;
+
+/// A set of incomes and expenses created together by one import, so they can be undone together
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
+#[diesel(table_name = import_batches)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ImportBatch {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    /// File format the rows came from, or `api` for a batch created with the `X-Batch-Id` header
+    #[schema(example = "csv")]
+    pub source: String,
+    #[schema(example = "statement-2024-03.csv")]
+    pub file_name: Option<String>,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+}
+
+/// An import batch with the number of its incomes and expenses that still exist
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportBatchWithCounts {
+    #[serde(flatten)]
+    pub batch: ImportBatch,
+    #[schema(example = 3)]
+    pub income_count: i64,
+    #[schema(example = 42)]
+    pub expense_count: i64,
+}
+
+/// The batch named by a request's `X-Batch-Id` header, belonging to the signed-in user
+#[derive(Debug, Clone, Copy)]
+pub struct ApiBatch {
+    pub id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// A CSV column, by header name or by zero-based position
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum CsvColumn {
+    Index(usize),
+    Name(String),
+}
+
+/// How the sign of a single amount column tells incomes and expenses apart
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AmountSign {
+    /// Bank account style, money going out is negative
+    #[default]
+    NegativeIsExpense,
+    /// Credit card style, charges are positive and payments negative
+    PositiveIsExpense,
+}
+
+/// Where to find each field in a bank's CSV statement
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CsvImportMapping {
+    /// Whether the first row holds column names, defaults to `true`
+    #[serde(default = "default_has_header")]
+    #[schema(example = true)]
+    pub has_header: bool,
+    /// Field separator, defaults to `,`
+    #[schema(example = ";")]
+    pub delimiter: Option<String>,
+    /// Decimal separator in amounts, `.` (default) or `,`
+    #[schema(example = ",")]
+    pub decimal_separator: Option<String>,
+    #[schema(example = "Booking date")]
+    pub date_column: CsvColumn,
+    /// `chrono` format of the dates, defaults to `%Y-%m-%d`
+    #[schema(example = "%d.%m.%Y")]
+    pub date_format: Option<String>,
+    /// Single signed amount column, interpreted with `amount_sign`
+    #[schema(example = "Amount")]
+    pub amount_column: Option<CsvColumn>,
+    #[serde(default)]
+    pub amount_sign: AmountSign,
+    /// Column with money going out, used together with `credit_column` instead of `amount_column`
+    #[schema(example = "Debit")]
+    pub debit_column: Option<CsvColumn>,
+    /// Column with money coming in
+    #[schema(example = "Credit")]
+    pub credit_column: Option<CsvColumn>,
+    /// The bank's transaction text, stored as the expense item name or income source
+    #[schema(example = "Payee")]
+    pub description_column: CsvColumn,
+    /// Extra text stored in the transaction's `description`
+    #[schema(example = "Reference")]
+    pub memo_column: Option<CsvColumn>,
+}
+
+fn default_has_header() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    /// Only validate and preview the rows without saving anything, defaults to `false`
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportBatchQuery {
+    /// Only batches of this source, e.g. `csv`, `ofx` or `api` for those created with the `X-Batch-Id` header
+    pub source: Option<String>,
+}
+
+/// A transaction read from an imported file, with whatever could not be understood about it
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportRow {
+    /// Line of the file the row starts on, counting from 1, or the position of the transaction in the statement
+    #[schema(example = 2)]
+    pub line: usize,
+    pub kind: Option<TransactionKind>,
+    #[schema(example = "2024-03-20")]
+    pub date: Option<NaiveDate>,
+    #[schema(example = "REWE Markt")]
+    pub name: Option<String>,
+    /// Always positive, `kind` tells the direction
+    #[schema(example = "23.45")]
+    pub amount: Option<Decimal>,
+    #[schema(example = "Card payment 1234")]
+    pub description: Option<String>,
+    /// Account the statement belongs to, when the format names one
+    #[schema(example = "DE89370400440532013000")]
+    pub external_account: Option<String>,
+    /// The bank's reference for the transaction, used to recognize it in later imports
+    #[schema(example = "20240320-0001")]
+    pub external_id: Option<String>,
+    /// Line items of an expense, from formats that support split transactions
+    pub splits: Vec<NewExpenseSplit>,
+    /// Imported before, or listed twice in the file, and skipped
+    #[schema(example = false)]
+    pub duplicate: bool,
+    /// Empty when the row can be imported
+    pub errors: Vec<String>,
+}
+
+impl ImportRow {
+    pub fn new(line: usize) -> Self {
+        ImportRow {
+            line,
+            kind: None,
+            date: None,
+            name: None,
+            amount: None,
+            description: None,
+            external_account: None,
+            external_id: None,
+            splits: Vec::new(),
+            duplicate: false,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Valid and not imported before
+    pub fn is_importable(&self) -> bool {
+        self.is_valid() && !self.duplicate
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportResult {
+    /// Batch to undo the import with, absent on dry runs
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub batch_id: Option<Uuid>,
+    #[schema(example = false)]
+    pub dry_run: bool,
+    /// Rows that are, or on a dry run would be, imported
+    #[schema(example = 45)]
+    pub valid_count: usize,
+    #[schema(example = 1)]
+    pub error_count: usize,
+    /// Valid rows skipped because they were imported before
+    #[schema(example = 0)]
+    pub duplicate_count: usize,
+    #[schema(example = 3)]
+    pub income_count: usize,
+    #[schema(example = 42)]
+    pub expense_count: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+/// Multipart statement import form, documentation only
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct StatementUpload {
+    /// The statement file
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+/// Multipart CSV import form, documentation only
+#[derive(ToSchema)]
+#[allow(dead_code)]
+pub struct CsvImportUpload {
+    /// The CSV statement, UTF-8 or Latin-1
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    /// Column mapping as JSON
+    pub mapping: CsvImportMapping,
+}
@@ -5,7 +5,9 @@ use rust_decimal::Decimal;
 use crate::models::schema::incomes;
 use diesel::{Queryable, Selectable, Insertable, AsChangeset};
 use crate::models::user::User;
-use utoipa::ToSchema;
+use crate::models::pagination::SortOrder;
+use crate::models::bulk::BulkMode;
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
 #[diesel(table_name = incomes)]
@@ -28,6 +30,17 @@ pub struct Income {
     pub created_at: NaiveDateTime,
     #[schema(example = "2024-03-20T10:00:00")]
     pub updated_at: NaiveDateTime,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payer_id: Option<Uuid>,
+    /// Import that created the income, if any
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub import_batch_id: Option<Uuid>,
+    /// Bank account of the imported statement
+    #[schema(example = "DE89370400440532013000")]
+    pub external_account: Option<String>,
+    /// The bank's own reference for the transaction, such as an OFX `FITID`
+    #[schema(example = "20240320-0001")]
+    pub external_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -49,10 +62,21 @@ pub struct NewIncome {
     #[serde(with = "rust_decimal::serde::float")]
     #[diesel(sql_type = diesel::sql_types::Numeric)]
     pub amount: Decimal,
+    /// Defaults to today when omitted
     #[schema(example = "2024-03-20")]
-    pub date: NaiveDate,
+    pub date: Option<NaiveDate>,
     #[schema(example = "Monthly salary")]
     pub description: Option<String>,
+    /// Resolved from `source` with the payee normalization rules when omitted
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payer_id: Option<Uuid>,
+    /// Set by imports, never taken from the request body
+    #[serde(skip)]
+    pub import_batch_id: Option<Uuid>,
+    #[serde(skip)]
+    pub external_account: Option<String>,
+    #[serde(skip)]
+    pub external_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, AsChangeset, ToSchema)]
@@ -67,5 +91,87 @@ pub struct UpdateIncome {
     pub date: Option<NaiveDate>,
     #[schema(example = "Project payment")]
     pub description: Option<String>,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payer_id: Option<Uuid>,
     pub updated_at: Option<NaiveDateTime>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IncomeQuery {
+    /// First date to include
+    pub from: Option<NaiveDate>,
+    /// Last date to include
+    pub to: Option<NaiveDate>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    #[param(value_type = Option<String>, example = "10.00")]
+    pub min_amount: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    #[param(value_type = Option<String>, example = "250.00")]
+    pub max_amount: Option<Decimal>,
+    /// Case-insensitive text search in `source` and `description`
+    pub search: Option<String>,
+    /// Sort field, defaults to `date`
+    pub sort: Option<IncomeSortField>,
+    /// Sort direction, defaults to `desc`
+    pub order: Option<SortOrder>,
+    /// Maximum number of rows to return, all rows when omitted
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Opaque cursor from the `Link` header of a previous page, only valid when sorting by date
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IncomeSortField {
+    #[default]
+    Date,
+    Amount,
+    Source,
+    CreatedAt,
+}
+
+/// One change in a bulk request, tagged with `op`
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum IncomeBulkOperation {
+    Create { data: NewIncome },
+    Update { id: Uuid, data: UpdateIncome },
+    Delete { id: Uuid },
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkIncomeRequest {
+    /// Defaults to `atomic`
+    #[serde(default)]
+    pub mode: BulkMode,
+    /// Run in the order given, at most 1000
+    pub operations: Vec<IncomeBulkOperation>,
+}
+
+/// Outcome of one operation, at the same position as in the request
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IncomeBulkItem {
+    #[schema(example = 0)]
+    pub index: usize,
+    /// HTTP status the operation would have had as a single call
+    #[schema(example = 201)]
+    pub status: u16,
+    /// The created, updated or deleted income, when the operation succeeded
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Option<Uuid>,
+    /// The income as stored, not given for deletes
+    pub income: Option<Income>,
+    #[schema(example = json!(null))]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkIncomeResult {
+    #[schema(example = 98)]
+    pub succeeded: usize,
+    #[schema(example = 2)]
+    pub failed: usize,
+    pub results: Vec<IncomeBulkItem>,
+}
@@ -0,0 +1,66 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Nullable, Numeric};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::expense::Expense;
+
+/// Why an expense looks unusual for the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    /// At least three times the median amount of the user's expenses with the same item name
+    UnusualAmount,
+    /// Another expense with the same item name and amount on the same day
+    DuplicateCharge,
+    /// The first expense for an item name, larger than nine in ten of the user's expenses
+    NewMerchant,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AnomalyQuery {
+    /// First date to include
+    pub from: Option<NaiveDate>,
+    /// Last date to include
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct AnomalyRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Bool)]
+    pub unusual_amount: bool,
+    #[diesel(sql_type = Bool)]
+    pub duplicate_charge: bool,
+    #[diesel(sql_type = Bool)]
+    pub new_merchant: bool,
+    #[diesel(sql_type = Nullable<Numeric>)]
+    pub typical_amount: Option<Decimal>,
+}
+
+impl AnomalyRow {
+    pub fn kinds(&self) -> Vec<AnomalyKind> {
+        [
+            (self.unusual_amount, AnomalyKind::UnusualAmount),
+            (self.duplicate_charge, AnomalyKind::DuplicateCharge),
+            (self.new_merchant, AnomalyKind::NewMerchant),
+        ]
+        .into_iter()
+        .filter_map(|(flagged, kind)| flagged.then_some(kind))
+        .collect()
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpenseAnomaly {
+    #[serde(flatten)]
+    pub expense: Expense,
+    pub anomalies: Vec<AnomalyKind>,
+    /// Median amount of the user's expenses with the same item name
+    #[schema(example = "4.50")]
+    pub typical_amount: Option<Decimal>,
+}
@@ -3,3 +3,19 @@ pub mod income;
 pub mod expense;
 pub mod schema;
 pub mod auth;
+pub mod goal;
+pub mod attachment;
+pub mod payee;
+pub mod pagination;
+pub mod search;
+pub mod transaction;
+pub mod report;
+pub mod forecast;
+pub mod insight;
+pub mod duplicate;
+pub mod net_worth;
+pub mod export;
+pub mod import;
+pub mod archive;
+pub mod rule;
+pub mod bulk;
\ No newline at end of file
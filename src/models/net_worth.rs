@@ -0,0 +1,130 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::report::ReportPeriod;
+use crate::models::schema::{net_worth_items, net_worth_snapshots};
+
+/// Something the user owns or owes, valued through dated snapshots
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable, ToSchema)]
+#[diesel(table_name = net_worth_items)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NetWorthItem {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Checking account")]
+    pub name: String,
+    /// Either `asset` or `liability`
+    #[schema(example = "asset")]
+    pub kind: String,
+    #[schema(example = "bank_account")]
+    pub category: Option<String>,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewNetWorthItem {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Mortgage")]
+    pub name: String,
+    #[schema(example = "liability")]
+    pub kind: String,
+    #[schema(example = "mortgage")]
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsChangeset, ToSchema)]
+#[diesel(table_name = net_worth_items)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UpdateNetWorthItem {
+    #[schema(example = "Family car")]
+    pub name: Option<String>,
+    #[schema(example = "asset")]
+    pub kind: Option<String>,
+    #[schema(example = "vehicle")]
+    pub category: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+/// The value of an item on a date, always positive, liabilities are subtracted from net worth
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Associations, Insertable, ToSchema)]
+#[diesel(table_name = net_worth_snapshots)]
+#[diesel(belongs_to(NetWorthItem, foreign_key = item_id))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NetWorthSnapshot {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub item_id: Uuid,
+    #[schema(example = "2024-03-31")]
+    pub date: NaiveDate,
+    #[schema(example = "2450.00")]
+    pub value: Decimal,
+    #[schema(example = "From the bank statement")]
+    pub note: Option<String>,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewNetWorthSnapshot {
+    #[schema(example = "2024-03-31")]
+    pub date: NaiveDate,
+    #[schema(example = "2450.00")]
+    pub value: Decimal,
+    #[schema(example = "From the bank statement")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NetWorthItemWithSnapshots {
+    #[serde(flatten)]
+    pub item: NetWorthItem,
+    /// Newest first
+    pub snapshots: Vec<NetWorthSnapshot>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NetWorthQuery {
+    /// Spacing of the points, defaults to `month`
+    pub period: Option<ReportPeriod>,
+    /// First date to include, defaults to a year (or five years by year) before `to`
+    pub from: Option<NaiveDate>,
+    /// Last date to include, defaults to today
+    pub to: Option<NaiveDate>,
+}
+
+/// Net worth at the end of a period
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NetWorthPoint {
+    #[schema(example = "2024-03-31")]
+    pub date: NaiveDate,
+    /// Total of the latest asset snapshots on the anchor date
+    #[schema(example = "32450.00")]
+    pub assets: Decimal,
+    /// Total of the latest liability snapshots on the anchor date
+    #[schema(example = "180000.00")]
+    pub liabilities: Decimal,
+    /// Snapshot date the point is based on, the latest one up to `date` or else the first after it
+    #[schema(example = "2024-03-01")]
+    pub anchor_date: Option<NaiveDate>,
+    /// Incomes minus expenses between the anchor date and `date`
+    #[schema(example = "830.00")]
+    pub cash_flow_adjustment: Decimal,
+    #[schema(example = "-146720.00")]
+    pub net_worth: Decimal,
+    /// False when `date` is itself a snapshot date and no cash flow had to be added
+    #[schema(example = true)]
+    pub reconstructed: bool,
+}
@@ -0,0 +1,45 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use uuid::Uuid;
+use utoipa::ToSchema;
+
+/// Largest page a client may request with `limit`
+pub const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Page size used when a cursor is given without a `limit`
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// One page of a listing together with the number of rows matching the filters
+#[derive(Debug)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total_count: i64,
+    /// Opaque cursor for the page after this one, if there is one
+    pub next_cursor: Option<String>,
+    /// Opaque cursor for the page before this one, if there is one
+    pub prev_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorDirection {
+    Next,
+    Prev,
+}
+
+/// Position in a listing sorted by `(date, id)`, handed out signed as an opaque string
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor {
+    pub date: NaiveDate,
+    pub id: Uuid,
+    pub direction: CursorDirection,
+    /// The sort direction the cursor was issued for
+    pub order: SortOrder,
+}
@@ -0,0 +1,104 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+use crate::models::schema::{payees, payee_rules};
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable, ToSchema)]
+#[diesel(table_name = payees)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Payee {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Amazon")]
+    pub name: String,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewPayee {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Amazon")]
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, AsChangeset, ToSchema)]
+#[diesel(table_name = payees)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UpdatePayee {
+    #[schema(example = "Amazon")]
+    pub name: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Identifiable, Associations, Insertable, ToSchema)]
+#[diesel(table_name = payee_rules)]
+#[diesel(belongs_to(Payee))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PayeeRule {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payee_id: Uuid,
+    /// One of `exact`, `starts_with` or `contains`
+    #[schema(example = "contains")]
+    pub match_type: String,
+    /// Compared against the normalized raw name, see `payee_service::normalize_name`
+    #[schema(example = "amzn")]
+    pub pattern: String,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewPayeeRule {
+    #[schema(example = "contains")]
+    pub match_type: String,
+    #[schema(example = "AMZN")]
+    pub pattern: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PayeeWithRules {
+    #[serde(flatten)]
+    pub payee: Payee,
+    pub rules: Vec<PayeeRule>,
+}
+
+/// Number of existing transactions that were assigned a payee
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PayeeNormalizationResult {
+    #[schema(example = 42)]
+    pub expenses_updated: usize,
+    #[schema(example = 3)]
+    pub incomes_updated: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PayeeSpending {
+    /// Empty for expenses that are not assigned to a payee
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub payee_id: Option<Uuid>,
+    #[schema(example = "Amazon")]
+    pub payee_name: Option<String>,
+    #[schema(example = "1234.56")]
+    pub total_amount: Decimal,
+    #[schema(example = 17)]
+    pub expense_count: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PayeeSpendingQuery {
+    pub from: Option<chrono::NaiveDate>,
+    pub to: Option<chrono::NaiveDate>,
+}
@@ -0,0 +1,167 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{Date, Nullable, Numeric};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::expense::Expense;
+use crate::models::income::Income;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    #[default]
+    Month,
+    Year,
+}
+
+impl ReportPeriod {
+    /// The `date_trunc` field name for the period
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::Month => "month",
+            ReportPeriod::Year => "year",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SummaryQuery {
+    /// Length of each period, defaults to `month`
+    pub period: Option<ReportPeriod>,
+    /// First date to include
+    pub from: Option<NaiveDate>,
+    /// Last date to include
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, QueryableByName, ToSchema)]
+pub struct PeriodSummary {
+    /// First day of the month or year
+    #[diesel(sql_type = Date)]
+    #[schema(example = "2024-03-01")]
+    pub period_start: NaiveDate,
+    #[diesel(sql_type = Numeric)]
+    #[schema(example = "3500.00")]
+    pub total_income: Decimal,
+    #[diesel(sql_type = Numeric)]
+    #[schema(example = "2800.00")]
+    pub total_expenses: Decimal,
+    #[diesel(sql_type = Numeric)]
+    #[schema(example = "700.00")]
+    pub net_savings: Decimal,
+    /// Net savings as a percentage of income, empty for periods without income
+    #[diesel(sql_type = Nullable<Numeric>)]
+    #[schema(example = "20.00")]
+    pub savings_rate: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakdownDimension {
+    /// Normalized item name, so "Coffee", "coffee " and "COFFEE!" group together
+    Item,
+    Weekday,
+    DayOfMonth,
+    AmountBand,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BreakdownQuery {
+    pub dimension: BreakdownDimension,
+    /// First date of the period
+    pub from: NaiveDate,
+    /// Last date of the period, it is compared with the same number of days before `from`
+    pub to: NaiveDate,
+}
+
+/// Totals of one group in the current and the previous period
+#[derive(Debug, QueryableByName)]
+pub struct BreakdownRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub key: String,
+    #[diesel(sql_type = diesel::sql_types::Integer)]
+    pub position: i32,
+    #[diesel(sql_type = Numeric)]
+    pub total_amount: Decimal,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub expense_count: i64,
+    #[diesel(sql_type = Numeric)]
+    pub previous_total_amount: Decimal,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BreakdownEntry {
+    /// Normalized item name, weekday name, day of the month or amount band such as `10-50`
+    #[schema(example = "coffee")]
+    pub key: String,
+    #[schema(example = "84.50")]
+    pub total_amount: Decimal,
+    #[schema(example = 23)]
+    pub expense_count: i64,
+    /// Share of the period's total spending
+    #[schema(example = "12.35")]
+    pub percent_of_total: Decimal,
+    #[schema(example = "70.00")]
+    pub previous_total_amount: Decimal,
+    /// Change against the previous period, empty when nothing was spent then
+    #[schema(example = "20.71")]
+    pub change_percent: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SpendingBreakdown {
+    pub dimension: BreakdownDimension,
+    #[schema(example = "2024-03-01")]
+    pub from: NaiveDate,
+    #[schema(example = "2024-03-31")]
+    pub to: NaiveDate,
+    #[schema(example = "2024-01-30")]
+    pub previous_from: NaiveDate,
+    #[schema(example = "2024-02-29")]
+    pub previous_to: NaiveDate,
+    #[schema(example = "684.20")]
+    pub total_amount: Decimal,
+    #[schema(example = "702.10")]
+    pub previous_total_amount: Decimal,
+    /// Items by total spent, the other dimensions in their natural order
+    pub entries: Vec<BreakdownEntry>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatementQuery {
+    /// Month to cover as `YYYY-MM`, defaults to the previous month
+    #[param(example = "2024-03")]
+    pub month: Option<String>,
+}
+
+/// Everything printed on a monthly statement
+#[derive(Debug)]
+pub struct MonthlyStatement {
+    /// First day of the month
+    pub month: NaiveDate,
+    pub account_name: String,
+    pub email: String,
+    /// All income minus all expenses dated before the month
+    pub opening_balance: Decimal,
+    pub total_income: Decimal,
+    pub total_expenses: Decimal,
+    pub closing_balance: Decimal,
+    pub incomes: Vec<Income>,
+    pub expenses: Vec<Expense>,
+    pub top_items: Vec<StatementItem>,
+}
+
+/// Expenses of the month grouped by payee, or by item name when they have none
+#[derive(Debug, QueryableByName)]
+pub struct StatementItem {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub name: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub expense_count: i64,
+    #[diesel(sql_type = Numeric)]
+    pub total_amount: Decimal,
+}
@@ -0,0 +1,156 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+use crate::models::schema::transaction_rules;
+use crate::models::transaction::TransactionKind;
+
+/// A user-defined rule that rewrites incoming transactions matching all of its conditions
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Identifiable, Insertable, ToSchema)]
+#[diesel(table_name = transaction_rules)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TransactionRule {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Short Uber trips")]
+    pub name: String,
+    /// Rules run in ascending position, each one seeing the changes of the ones before
+    #[schema(example = 1)]
+    pub position: i32,
+    /// One of `all`, `income` or `expense`
+    #[schema(example = "expense")]
+    pub applies_to: String,
+    #[schema(value_type = Vec<RuleCondition>)]
+    pub conditions: serde_json::Value,
+    #[schema(value_type = Vec<RuleAction>)]
+    pub actions: serde_json::Value,
+    /// Skip the remaining rules once this one matched
+    #[schema(example = false)]
+    pub stop_processing: bool,
+    #[schema(example = true)]
+    pub enabled: bool,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub created_at: NaiveDateTime,
+    #[schema(example = "2024-03-20T10:00:00")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NewTransactionRule {
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub user_id: Uuid,
+    #[schema(example = "Short Uber trips")]
+    pub name: String,
+    /// Defaults to after the user's last rule
+    #[schema(example = 1)]
+    pub position: Option<i32>,
+    /// One of `all` (default), `income` or `expense`
+    #[schema(example = "expense")]
+    pub applies_to: Option<String>,
+    /// All conditions must match
+    pub conditions: Vec<RuleCondition>,
+    /// Applied in order
+    pub actions: Vec<RuleAction>,
+    #[schema(example = false)]
+    pub stop_processing: Option<bool>,
+    #[schema(example = true)]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateTransactionRule {
+    #[schema(example = "Short Uber trips")]
+    pub name: Option<String>,
+    #[schema(example = 2)]
+    pub position: Option<i32>,
+    #[schema(example = "all")]
+    pub applies_to: Option<String>,
+    pub conditions: Option<Vec<RuleCondition>>,
+    pub actions: Option<Vec<RuleAction>>,
+    pub stop_processing: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+/// The transaction field a condition looks at
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleField {
+    /// The expense's `item_name` or the income's `source`
+    #[serde(alias = "item_name", alias = "source")]
+    Name,
+    Description,
+    Amount,
+}
+
+/// Text operators ignore case, the comparison operators only work on `amount`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOperator {
+    Contains,
+    Equals,
+    StartsWith,
+    EndsWith,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleCondition {
+    pub field: RuleField,
+    pub operator: RuleOperator,
+    /// Text to look for, or a number for `amount`
+    #[schema(example = "UBER")]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleActionKind {
+    /// Replace the expense's `item_name` or the income's `source`
+    SetName,
+    SetDescription,
+    /// Add text to the end of the description, unless it is already there
+    AppendDescription,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RuleAction {
+    pub action: RuleActionKind,
+    #[schema(example = "Uber ride")]
+    pub value: String,
+}
+
+/// A transaction as rules see it, also the sample to try the rules on
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RuleSample {
+    pub kind: TransactionKind,
+    /// The expense's `item_name` or the income's `source`
+    #[schema(example = "UBER *TRIP 8XK2")]
+    pub name: String,
+    #[schema(example = "23.40")]
+    pub amount: Decimal,
+    #[schema(example = json!(null))]
+    pub description: Option<String>,
+}
+
+/// Which rules matched a sample, in the order they ran, and the sample after them
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RuleTestResult {
+    pub matched_rules: Vec<TransactionRule>,
+    pub result: RuleSample,
+}
+
+/// Number of existing transactions the rules changed
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RuleApplicationResult {
+    #[schema(example = 42)]
+    pub expenses_updated: usize,
+    #[schema(example = 3)]
+    pub incomes_updated: usize,
+}
@@ -1,5 +1,32 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    attachments (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        expense_id -> Nullable<Uuid>,
+        income_id -> Nullable<Uuid>,
+        file_name -> Varchar,
+        content_type -> Varchar,
+        size_bytes -> Int8,
+        storage_key -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    expense_splits (id) {
+        id -> Uuid,
+        expense_id -> Uuid,
+        label -> Varchar,
+        amount -> Numeric,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     expenses (id) {
         id -> Uuid,
@@ -10,6 +37,48 @@ diesel::table! {
         description -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        payee_id -> Nullable<Uuid>,
+        import_batch_id -> Nullable<Uuid>,
+        external_account -> Nullable<Varchar>,
+        external_id -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    goal_contributions (id) {
+        id -> Uuid,
+        goal_id -> Uuid,
+        income_id -> Nullable<Uuid>,
+        amount -> Numeric,
+        date -> Date,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    goals (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Varchar,
+        target_amount -> Numeric,
+        target_date -> Date,
+        match_source -> Nullable<Varchar>,
+        match_percent -> Nullable<Numeric>,
+        description -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    import_batches (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        source -> Varchar,
+        file_name -> Nullable<Varchar>,
+        created_at -> Timestamp,
     }
 }
 
@@ -23,6 +92,71 @@ diesel::table! {
         description -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        payer_id -> Nullable<Uuid>,
+        import_batch_id -> Nullable<Uuid>,
+        external_account -> Nullable<Varchar>,
+        external_id -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    net_worth_items (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Varchar,
+        kind -> Varchar,
+        category -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    net_worth_snapshots (id) {
+        id -> Uuid,
+        item_id -> Uuid,
+        date -> Date,
+        value -> Numeric,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    payee_rules (id) {
+        id -> Uuid,
+        payee_id -> Uuid,
+        match_type -> Varchar,
+        pattern -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    payees (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    transaction_rules (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Varchar,
+        position -> Int4,
+        applies_to -> Varchar,
+        conditions -> Jsonb,
+        actions -> Jsonb,
+        stop_processing -> Bool,
+        enabled -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -38,11 +172,38 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(attachments -> expenses (expense_id));
+diesel::joinable!(attachments -> incomes (income_id));
+diesel::joinable!(attachments -> users (user_id));
+diesel::joinable!(expense_splits -> expenses (expense_id));
+diesel::joinable!(expenses -> import_batches (import_batch_id));
+diesel::joinable!(expenses -> payees (payee_id));
 diesel::joinable!(expenses -> users (user_id));
+diesel::joinable!(goal_contributions -> goals (goal_id));
+diesel::joinable!(goal_contributions -> incomes (income_id));
+diesel::joinable!(goals -> users (user_id));
+diesel::joinable!(import_batches -> users (user_id));
+diesel::joinable!(incomes -> import_batches (import_batch_id));
+diesel::joinable!(incomes -> payees (payer_id));
 diesel::joinable!(incomes -> users (user_id));
+diesel::joinable!(net_worth_items -> users (user_id));
+diesel::joinable!(net_worth_snapshots -> net_worth_items (item_id));
+diesel::joinable!(payee_rules -> payees (payee_id));
+diesel::joinable!(payees -> users (user_id));
+diesel::joinable!(transaction_rules -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    attachments,
+    expense_splits,
     expenses,
+    goal_contributions,
+    goals,
+    import_batches,
     incomes,
+    net_worth_items,
+    net_worth_snapshots,
+    payee_rules,
+    payees,
+    transaction_rules,
     users,
 );
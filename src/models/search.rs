@@ -0,0 +1,46 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{Date, Float4, Numeric, Text};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    /// Search terms, supports quoted phrases, `or` and `-excluded` words
+    #[param(example = "dentist bill")]
+    pub q: String,
+    /// Maximum number of results, defaults to 20
+    pub limit: Option<i64>,
+}
+
+/// An income or expense matching a search, best matches first
+#[derive(Debug, Serialize, QueryableByName, ToSchema)]
+pub struct SearchResult {
+    /// Either `income` or `expense`
+    #[diesel(sql_type = Text)]
+    #[schema(example = "expense")]
+    pub kind: String,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    #[schema(example = "123e4567-e89b-12d3-a456-426614174000")]
+    pub id: Uuid,
+    /// The expense item name or income source
+    #[diesel(sql_type = Text)]
+    #[schema(example = "Dentist")]
+    pub title: String,
+    #[diesel(sql_type = Numeric)]
+    #[schema(example = "180.00")]
+    pub amount: Decimal,
+    #[diesel(sql_type = Date)]
+    #[schema(example = "2024-04-12")]
+    pub date: NaiveDate,
+    /// HTML-escaped excerpt with matching words wrapped in `<mark>` tags
+    #[diesel(sql_type = Text)]
+    #[schema(example = "<mark>Dentist</mark> - cleaning and x-ray <mark>bill</mark>")]
+    pub snippet: String,
+    #[diesel(sql_type = Float4)]
+    #[schema(example = 0.6)]
+    pub rank: f32,
+}
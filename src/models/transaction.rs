@@ -0,0 +1,98 @@
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Date, Numeric, Text};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::expense::ExpenseWithSplits;
+use crate::models::income::Income;
+use crate::models::pagination::SortOrder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    Income,
+    Expense,
+}
+
+impl TransactionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Income => "income",
+            TransactionKind::Expense => "expense",
+        }
+    }
+}
+
+/// An income or an expense, tagged with `kind`
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Transaction {
+    Income(Income),
+    Expense(ExpenseWithSplits),
+}
+
+impl Transaction {
+    /// Position of the transaction in `(date, id)` order
+    pub fn key(&self) -> (NaiveDate, Uuid) {
+        match self {
+            Transaction::Income(income) => (income.date, income.id),
+            Transaction::Expense(expense) => (expense.expense.date, expense.expense.id),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransactionEntry {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    /// Incomes minus expenses over the user's whole history up to and including this transaction
+    #[schema(example = "1520.75")]
+    pub running_balance: Decimal,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TransactionQuery {
+    /// Only return incomes or only expenses
+    pub kind: Option<TransactionKind>,
+    /// First date to include
+    pub from: Option<NaiveDate>,
+    /// Last date to include
+    pub to: Option<NaiveDate>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    #[param(value_type = Option<String>, example = "10.00")]
+    pub min_amount: Option<Decimal>,
+    #[serde(default, with = "rust_decimal::serde::str_option")]
+    #[param(value_type = Option<String>, example = "250.00")]
+    pub max_amount: Option<Decimal>,
+    /// Case-insensitive text search in the expense item name or income source and the description
+    pub search: Option<String>,
+    /// Date direction, defaults to `desc`
+    pub order: Option<SortOrder>,
+    /// Maximum number of rows to return, all rows when omitted
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// Opaque cursor from the `Link` header of a previous page
+    pub cursor: Option<String>,
+}
+
+/// Position of a transaction in the combined ledger, before its row is loaded
+#[derive(Debug, QueryableByName)]
+pub struct LedgerRow {
+    #[diesel(sql_type = Text)]
+    pub kind: String,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Date)]
+    pub date: NaiveDate,
+    #[diesel(sql_type = Numeric)]
+    pub running_balance: Decimal,
+}
+
+#[derive(Debug, QueryableByName)]
+pub struct LedgerCount {
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+}
@@ -6,7 +6,7 @@ use utoipa::ToSchema;
 use crate::models::schema::users;
 use crate::models::income::Income;
 
-#[derive(Debug, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable, Insertable, ToSchema)]
 #[diesel(table_name = users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct User {
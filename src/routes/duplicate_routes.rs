@@ -0,0 +1,15 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::duplicate_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/duplicates")
+            .wrap(auth)
+            .route("", web::get().to(duplicate_controller::get_duplicates))
+            .route("/merge", web::post().to(duplicate_controller::merge_duplicates))
+    );
+}
@@ -1,6 +1,6 @@
 use actix_web::web;
 use actix_web_httpauth::middleware::HttpAuthentication;
-use crate::controllers::expense_controller;
+use crate::controllers::{attachment_controller, expense_controller};
 use crate::middleware::auth_middleware::jwt_validator;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
@@ -11,8 +11,13 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .wrap(auth)
             .route("", web::get().to(expense_controller::get_all_expenses))
             .route("", web::post().to(expense_controller::create_expense))
+            .route("/bulk", web::post().to(expense_controller::bulk_expenses))
             .route("/{user_id}", web::get().to(expense_controller::get_expenses_by_user_id))
             .route("/{expense_id}", web::put().to(expense_controller::update_expense))
             .route("/{expense_id}", web::delete().to(expense_controller::delete_expense))
+            .route("/{expense_id}/attachments", web::get().to(attachment_controller::get_expense_attachments))
+            .route("/{expense_id}/attachments", web::post().to(attachment_controller::upload_expense_attachment))
+            .route("/{expense_id}/attachments/{attachment_id}", web::get().to(attachment_controller::download_expense_attachment))
+            .route("/{expense_id}/attachments/{attachment_id}", web::delete().to(attachment_controller::delete_expense_attachment))
     );
 }
@@ -0,0 +1,17 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::export_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/export")
+            .wrap(auth)
+            .route("/incomes.csv", web::get().to(export_controller::export_incomes))
+            .route("/expenses.csv", web::get().to(export_controller::export_expenses))
+            .route("/ledger.qif", web::get().to(export_controller::export_qif))
+            .route("/full", web::get().to(export_controller::export_full))
+    );
+}
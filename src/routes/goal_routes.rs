@@ -0,0 +1,20 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::goal_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/goals")
+            .wrap(auth)
+            .route("", web::post().to(goal_controller::create_goal))
+            .route("/user/{user_id}", web::get().to(goal_controller::get_goals_by_user_id))
+            .route("/{goal_id}", web::put().to(goal_controller::update_goal))
+            .route("/{goal_id}", web::delete().to(goal_controller::delete_goal))
+            .route("/{goal_id}/contributions", web::get().to(goal_controller::get_contributions))
+            .route("/{goal_id}/contributions", web::post().to(goal_controller::add_contribution))
+            .route("/{goal_id}/progress", web::get().to(goal_controller::get_goal_progress))
+    );
+}
@@ -0,0 +1,21 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::import_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/imports")
+            .wrap(auth)
+            .route("", web::get().to(import_controller::get_import_batches))
+            .route("/csv", web::post().to(import_controller::import_csv))
+            .route("/ofx", web::post().to(import_controller::import_ofx))
+            .route("/qif", web::post().to(import_controller::import_qif))
+            .route("/camt", web::post().to(import_controller::import_camt))
+            .route("/mt940", web::post().to(import_controller::import_mt940))
+            .route("/archive", web::post().to(import_controller::import_archive))
+            .route("/{batch_id}", web::delete().to(import_controller::undo_import))
+    );
+}
@@ -1,6 +1,6 @@
 use actix_web::web;
 use actix_web_httpauth::middleware::HttpAuthentication;
-use crate::controllers::income_controller;
+use crate::controllers::{attachment_controller, income_controller};
 use crate::middleware::auth_middleware::jwt_validator;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
@@ -12,7 +12,12 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .route("", web::get().to(income_controller::get_all_incomes))
             .route("/{user_id}", web::get().to(income_controller::get_incomes_by_user_id))
             .route("", web::post().to(income_controller::create_income))
+            .route("/bulk", web::post().to(income_controller::bulk_incomes))
             .route("/{income_id}", web::put().to(income_controller::update_income))
             .route("/{income_id}", web::delete().to(income_controller::delete_income))
+            .route("/{income_id}/attachments", web::get().to(attachment_controller::get_income_attachments))
+            .route("/{income_id}/attachments", web::post().to(attachment_controller::upload_income_attachment))
+            .route("/{income_id}/attachments/{attachment_id}", web::get().to(attachment_controller::download_income_attachment))
+            .route("/{income_id}/attachments/{attachment_id}", web::delete().to(attachment_controller::delete_income_attachment))
     );
 } 
\ No newline at end of file
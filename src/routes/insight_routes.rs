@@ -0,0 +1,14 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::insight_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/insights")
+            .wrap(auth)
+            .route("/anomalies", web::get().to(insight_controller::get_anomalies))
+    );
+}
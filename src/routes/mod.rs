@@ -2,6 +2,17 @@ mod income_routes;
 mod expense_routes;
 mod health_routes;
 mod auth_routes;
+mod goal_routes;
+mod payee_routes;
+mod search_routes;
+mod transaction_routes;
+mod report_routes;
+mod insight_routes;
+mod duplicate_routes;
+mod net_worth_routes;
+mod export_routes;
+mod import_routes;
+mod rule_routes;
 
 use actix_web::web;
 
@@ -12,5 +23,16 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                 .configure(auth_routes::configure)
                 .configure(income_routes::configure)
                 .configure(expense_routes::configure)
+                .configure(goal_routes::configure)
+                .configure(payee_routes::configure)
+                .configure(search_routes::configure)
+                .configure(transaction_routes::configure)
+                .configure(report_routes::configure)
+                .configure(insight_routes::configure)
+                .configure(duplicate_routes::configure)
+                .configure(net_worth_routes::configure)
+                .configure(export_routes::configure)
+                .configure(import_routes::configure)
+                .configure(rule_routes::configure)
         );
 } 
\ No newline at end of file
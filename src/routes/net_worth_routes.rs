@@ -0,0 +1,19 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::net_worth_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/net-worth")
+            .wrap(auth)
+            .route("/items", web::post().to(net_worth_controller::create_item))
+            .route("/items/user/{user_id}", web::get().to(net_worth_controller::get_items_by_user_id))
+            .route("/items/{item_id}", web::put().to(net_worth_controller::update_item))
+            .route("/items/{item_id}", web::delete().to(net_worth_controller::delete_item))
+            .route("/items/{item_id}/snapshots", web::post().to(net_worth_controller::record_snapshot))
+            .route("/snapshots/{snapshot_id}", web::delete().to(net_worth_controller::delete_snapshot))
+    );
+}
@@ -0,0 +1,21 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::payee_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/payees")
+            .wrap(auth)
+            .route("", web::post().to(payee_controller::create_payee))
+            .route("/user/{user_id}", web::get().to(payee_controller::get_payees_by_user_id))
+            .route("/user/{user_id}/normalize", web::post().to(payee_controller::normalize_history))
+            .route("/user/{user_id}/spending", web::get().to(payee_controller::get_spending_by_payee))
+            .route("/rules/{rule_id}", web::delete().to(payee_controller::delete_rule))
+            .route("/{payee_id}", web::put().to(payee_controller::update_payee))
+            .route("/{payee_id}", web::delete().to(payee_controller::delete_payee))
+            .route("/{payee_id}/rules", web::post().to(payee_controller::add_rule))
+    );
+}
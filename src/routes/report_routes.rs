@@ -0,0 +1,18 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::report_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/reports")
+            .wrap(auth)
+            .route("/summary", web::get().to(report_controller::get_summary))
+            .route("/spending-breakdown", web::get().to(report_controller::get_spending_breakdown))
+            .route("/forecast", web::get().to(report_controller::get_forecast))
+            .route("/net-worth", web::get().to(report_controller::get_net_worth))
+            .route("/statement", web::get().to(report_controller::get_statement))
+    );
+}
@@ -0,0 +1,19 @@
+use actix_web::web;
+use actix_web_httpauth::middleware::HttpAuthentication;
+use crate::controllers::rule_controller;
+use crate::middleware::auth_middleware::jwt_validator;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    let auth = HttpAuthentication::bearer(jwt_validator);
+    
+    cfg.service(
+        web::scope("/rules")
+            .wrap(auth)
+            .route("", web::post().to(rule_controller::create_rule))
+            .route("/user/{user_id}", web::get().to(rule_controller::get_rules_by_user_id))
+            .route("/user/{user_id}/test", web::post().to(rule_controller::test_rules))
+            .route("/user/{user_id}/apply", web::post().to(rule_controller::apply_rules))
+            .route("/{rule_id}", web::put().to(rule_controller::update_rule))
+            .route("/{rule_id}", web::delete().to(rule_controller::delete_rule))
+    );
+}
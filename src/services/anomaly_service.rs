@@ -0,0 +1,104 @@
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Date, Nullable};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::expense::Expense;
+use crate::models::insight::{AnomalyKind, AnomalyQuery, AnomalyRow, ExpenseAnomaly};
+use crate::models::schema::expenses;
+use crate::database::db_connection::DbConnection;
+use crate::services::query_filters::validate_date_range;
+
+/// Anomaly checks over the expenses of the users selected by `{scope}`, returning the rows matching `{filter}`.
+/// Both are filled in from fixed strings in this module. Item names are normalized like
+/// payee_service::normalize_name, statistics need enough history to mean something.
+const ANOMALY_SQL: &str = "
+    WITH scope AS (
+        SELECT id, user_id, amount, date, created_at,
+               trim(regexp_replace(lower(item_name), '[^[:alnum:]]+', ' ', 'g')) AS item
+        FROM expenses
+        WHERE {scope}
+    ), ranked AS (
+        SELECT *,
+               ROW_NUMBER() OVER (PARTITION BY user_id, item ORDER BY date, created_at, id) AS item_rank,
+               COUNT(*) OVER (PARTITION BY user_id, item, amount, date) AS same_day_count
+        FROM scope
+    ), item_stats AS (
+        SELECT user_id, item, COUNT(*) AS item_count,
+               percentile_cont(0.5) WITHIN GROUP (ORDER BY amount)::numeric AS median_amount
+        FROM scope
+        GROUP BY user_id, item
+    ), user_stats AS (
+        SELECT user_id, COUNT(*) AS user_count,
+               percentile_cont(0.9) WITHIN GROUP (ORDER BY amount)::numeric AS large_amount
+        FROM scope
+        GROUP BY user_id
+    ), flags AS (
+        SELECT ranked.id, ranked.user_id, ranked.date,
+               (item_stats.item_count >= 4 AND ranked.amount >= 3 * item_stats.median_amount) AS unusual_amount,
+               ranked.same_day_count > 1 AS duplicate_charge,
+               (ranked.item_rank = 1 AND user_stats.user_count >= 10 AND ranked.amount > user_stats.large_amount) AS new_merchant,
+               CASE WHEN item_stats.item_count > 1 THEN round(item_stats.median_amount, 2) END AS typical_amount
+        FROM ranked
+        JOIN item_stats ON item_stats.user_id = ranked.user_id AND item_stats.item = ranked.item
+        JOIN user_stats ON user_stats.user_id = ranked.user_id
+    )
+    SELECT id, unusual_amount, duplicate_charge, new_merchant, typical_amount
+    FROM flags
+    WHERE {filter}";
+
+/// Anomaly flags of the given expenses, measured against the history of the users owning them
+pub fn get_anomaly_flags(connection: &mut DbConnection, expense_ids: &[Uuid]) -> Result<HashMap<Uuid, Vec<AnomalyKind>>, diesel::result::Error> {
+    if expense_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let sql = ANOMALY_SQL
+        .replace("{scope}", "user_id IN (SELECT user_id FROM expenses WHERE id = ANY($1))")
+        .replace("{filter}", "id = ANY($1)");
+    let rows = diesel::sql_query(sql)
+        .bind::<Array<diesel::sql_types::Uuid>, _>(expense_ids)
+        .load::<AnomalyRow>(connection)?;
+
+    Ok(rows.into_iter().map(|row| (row.id, row.kinds())).collect())
+}
+
+/// The user's unusual expenses, newest first
+pub fn get_anomalies(connection: &mut DbConnection, user_id: Uuid, query: &AnomalyQuery) -> Result<Vec<ExpenseAnomaly>, AppError> {
+    validate_date_range(query.from, query.to)?;
+
+    let sql = ANOMALY_SQL.replace("{scope}", "user_id = $1").replace(
+        "{filter}",
+        "($2::date IS NULL OR date >= $2) AND ($3::date IS NULL OR date <= $3) \
+         AND (unusual_amount OR duplicate_charge OR new_merchant)",
+    );
+    let rows = diesel::sql_query(sql)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<Nullable<Date>, _>(query.from)
+        .bind::<Nullable<Date>, _>(query.to)
+        .load::<AnomalyRow>(connection)?;
+
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let mut flagged: HashMap<Uuid, Expense> = expenses::table
+        .filter(expenses::id.eq_any(ids))
+        .select(Expense::as_select())
+        .load::<Expense>(connection)?
+        .into_iter()
+        .map(|expense| (expense.id, expense))
+        .collect();
+
+    let mut anomalies: Vec<ExpenseAnomaly> = rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(ExpenseAnomaly {
+                anomalies: row.kinds(),
+                typical_amount: row.typical_amount,
+                expense: flagged.remove(&row.id)?,
+            })
+        })
+        .collect();
+    anomalies.sort_by_key(|anomaly| std::cmp::Reverse((anomaly.expense.date, anomaly.expense.created_at)));
+
+    Ok(anomalies)
+}
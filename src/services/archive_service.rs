@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+
+use actix_web::web::Bytes;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::database::db_connection::DbConnection;
+use crate::models::archive::{AccountArchive, ArchiveProfile, ArchiveRestoreResult, ArchivedAttachment, ARCHIVE_FORMAT, ARCHIVE_VERSION};
+use crate::models::attachment::{Attachment, AttachmentParent, NewAttachment};
+use crate::models::expense::{Expense, ExpenseSplit};
+use crate::models::goal::{Goal, GoalContribution};
+use crate::models::import::ImportBatch;
+use crate::models::income::Income;
+use crate::models::net_worth::{NetWorthItem, NetWorthSnapshot};
+use crate::models::rule::TransactionRule;
+use crate::models::payee::{Payee, PayeeRule};
+use crate::models::schema::{
+    attachments, expense_splits, expenses, goal_contributions, goals, import_batches, incomes, net_worth_items,
+    net_worth_snapshots, payee_rules, payees, transaction_rules, users,
+};
+use crate::models::user::User;
+use crate::services::{attachment_service, rule_service};
+
+/// Rows per INSERT, well below Postgres' limit of 65535 bind parameters for the widest table
+const INSERT_BATCH_SIZE: usize = 1000;
+
+/// Insert rows in batches of `INSERT_BATCH_SIZE`
+macro_rules! insert_batches {
+    ($connection:expr, $table:expr, $rows:expr) => {
+        for batch in $rows.chunks(INSERT_BATCH_SIZE) {
+            diesel::insert_into($table).values(batch).execute($connection)?;
+        }
+    };
+}
+
+/// An archive with every row given a new id and owned by the restoring user
+pub struct PreparedRestore {
+    archive: AccountArchive,
+    attachments: Vec<NewAttachment>,
+    /// Attachment files by storage key, to be stored before the rows are written
+    pub files: Vec<(String, Bytes)>,
+}
+
+/// Everything the user has, except the attachment file contents which live in attachment storage.
+/// The archive's `attachments` are left empty and returned separately to be filled in.
+pub fn export_archive(connection: &mut DbConnection, user_id: Uuid) -> Result<(AccountArchive, Vec<Attachment>), diesel::result::Error> {
+    let user = users::table.find(user_id).select(User::as_select()).first::<User>(connection)?;
+
+    let archive = AccountArchive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: Utc::now().naive_utc(),
+        profile: ArchiveProfile {
+            first_name: user.first_name,
+            last_name: user.last_name,
+            email: user.email,
+            created_at: user.created_at,
+        },
+        payees: payees::table
+            .filter(payees::user_id.eq(user_id))
+            .order(payees::created_at.asc())
+            .select(Payee::as_select())
+            .load(connection)?,
+        payee_rules: payee_rules::table
+            .inner_join(payees::table)
+            .filter(payees::user_id.eq(user_id))
+            .order(payee_rules::created_at.asc())
+            .select(PayeeRule::as_select())
+            .load(connection)?,
+        import_batches: import_batches::table
+            .filter(import_batches::user_id.eq(user_id))
+            .order(import_batches::created_at.asc())
+            .select(ImportBatch::as_select())
+            .load(connection)?,
+        incomes: incomes::table
+            .filter(incomes::user_id.eq(user_id))
+            .order((incomes::date.asc(), incomes::id.asc()))
+            .select(Income::as_select())
+            .load(connection)?,
+        expenses: expenses::table
+            .filter(expenses::user_id.eq(user_id))
+            .order((expenses::date.asc(), expenses::id.asc()))
+            .select(Expense::as_select())
+            .load(connection)?,
+        expense_splits: expense_splits::table
+            .inner_join(expenses::table)
+            .filter(expenses::user_id.eq(user_id))
+            .order(expense_splits::created_at.asc())
+            .select(ExpenseSplit::as_select())
+            .load(connection)?,
+        attachments: Vec::new(),
+        goals: goals::table
+            .filter(goals::user_id.eq(user_id))
+            .order(goals::created_at.asc())
+            .select(Goal::as_select())
+            .load(connection)?,
+        goal_contributions: goal_contributions::table
+            .inner_join(goals::table)
+            .filter(goals::user_id.eq(user_id))
+            .order((goal_contributions::date.asc(), goal_contributions::id.asc()))
+            .select(GoalContribution::as_select())
+            .load(connection)?,
+        net_worth_items: net_worth_items::table
+            .filter(net_worth_items::user_id.eq(user_id))
+            .order(net_worth_items::created_at.asc())
+            .select(NetWorthItem::as_select())
+            .load(connection)?,
+        net_worth_snapshots: net_worth_snapshots::table
+            .inner_join(net_worth_items::table)
+            .filter(net_worth_items::user_id.eq(user_id))
+            .order((net_worth_snapshots::date.asc(), net_worth_snapshots::id.asc()))
+            .select(NetWorthSnapshot::as_select())
+            .load(connection)?,
+        transaction_rules: transaction_rules::table
+            .filter(transaction_rules::user_id.eq(user_id))
+            .order((transaction_rules::position.asc(), transaction_rules::created_at.asc()))
+            .select(TransactionRule::as_select())
+            .load(connection)?,
+    };
+
+    let attachments = attachments::table
+        .filter(attachments::user_id.eq(user_id))
+        .order(attachments::created_at.asc())
+        .select(Attachment::as_select())
+        .load::<Attachment>(connection)?;
+
+    Ok((archive, attachments))
+}
+
+pub fn archive_attachment(attachment: Attachment, data: &[u8]) -> ArchivedAttachment {
+    ArchivedAttachment {
+        id: attachment.id,
+        expense_id: attachment.expense_id,
+        income_id: attachment.income_id,
+        file_name: attachment.file_name,
+        content_type: attachment.content_type,
+        content: STANDARD.encode(data),
+        created_at: attachment.created_at,
+        updated_at: attachment.updated_at,
+    }
+}
+
+/// Check an archive can be restored for the user and give its rows new ids.
+///
+/// New ids let the same archive be restored next to the account it came from. References
+/// between rows are followed to the new ids and must all point to rows in the archive.
+pub fn prepare_restore(connection: &mut DbConnection, user_id: Uuid, mut archive: AccountArchive) -> Result<PreparedRestore, AppError> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(AppError::Validation(format!("Not an account archive, expected format '{}'", ARCHIVE_FORMAT)));
+    }
+    if archive.version != ARCHIVE_VERSION {
+        return Err(AppError::Validation(format!(
+            "Archive version {} is not supported, expected version {}",
+            archive.version, ARCHIVE_VERSION
+        )));
+    }
+    ensure_empty_account(connection, user_id)?;
+
+    let mut payee_ids = IdMap::new("payee");
+    for payee in &mut archive.payees {
+        payee.id = payee_ids.assign(payee.id)?;
+        payee.user_id = user_id;
+    }
+    for rule in &mut archive.payee_rules {
+        rule.id = Uuid::new_v4();
+        rule.payee_id = payee_ids.get(rule.payee_id)?;
+    }
+
+    let mut batch_ids = IdMap::new("import batch");
+    for batch in &mut archive.import_batches {
+        batch.id = batch_ids.assign(batch.id)?;
+        batch.user_id = user_id;
+    }
+
+    let mut income_ids = IdMap::new("income");
+    for income in &mut archive.incomes {
+        income.id = income_ids.assign(income.id)?;
+        income.user_id = user_id;
+        income.payer_id = payee_ids.get_optional(income.payer_id)?;
+        income.import_batch_id = batch_ids.get_optional(income.import_batch_id)?;
+    }
+
+    let mut expense_ids = IdMap::new("expense");
+    for expense in &mut archive.expenses {
+        expense.id = expense_ids.assign(expense.id)?;
+        expense.user_id = user_id;
+        expense.payee_id = payee_ids.get_optional(expense.payee_id)?;
+        expense.import_batch_id = batch_ids.get_optional(expense.import_batch_id)?;
+    }
+    for split in &mut archive.expense_splits {
+        split.id = Uuid::new_v4();
+        split.expense_id = expense_ids.get(split.expense_id)?;
+    }
+
+    let mut attachments = Vec::with_capacity(archive.attachments.len());
+    let mut files = Vec::with_capacity(archive.attachments.len());
+    for attachment in archive.attachments.drain(..) {
+        let parent = match (attachment.expense_id, attachment.income_id) {
+            (Some(expense_id), None) => AttachmentParent::Expense(expense_ids.get(expense_id)?),
+            (None, Some(income_id)) => AttachmentParent::Income(income_ids.get(income_id)?),
+            _ => {
+                return Err(AppError::Validation(format!(
+                    "Attachment {} must belong to exactly one expense or income",
+                    attachment.id
+                )))
+            }
+        };
+        let data = STANDARD
+            .decode(&attachment.content)
+            .map_err(|_| AppError::Validation(format!("Attachment {} content is not valid base64", attachment.id)))?;
+        attachment_service::validate_upload(&attachment.content_type, &data)?;
+
+        let mut new_attachment = NewAttachment::new(parent, user_id, attachment.file_name, attachment.content_type, data.len() as i64);
+        new_attachment.created_at = attachment.created_at;
+        new_attachment.updated_at = attachment.updated_at;
+        files.push((new_attachment.storage_key.clone(), Bytes::from(data)));
+        attachments.push(new_attachment);
+    }
+
+    let mut goal_ids = IdMap::new("goal");
+    for goal in &mut archive.goals {
+        goal.id = goal_ids.assign(goal.id)?;
+        goal.user_id = user_id;
+    }
+    for contribution in &mut archive.goal_contributions {
+        contribution.id = Uuid::new_v4();
+        contribution.goal_id = goal_ids.get(contribution.goal_id)?;
+        contribution.income_id = income_ids.get_optional(contribution.income_id)?;
+    }
+
+    let mut item_ids = IdMap::new("net worth item");
+    for item in &mut archive.net_worth_items {
+        item.id = item_ids.assign(item.id)?;
+        item.user_id = user_id;
+    }
+    for snapshot in &mut archive.net_worth_snapshots {
+        snapshot.id = Uuid::new_v4();
+        snapshot.item_id = item_ids.get(snapshot.item_id)?;
+    }
+
+    for rule in &mut archive.transaction_rules {
+        rule_service::validate_rule(rule)?;
+        rule.id = Uuid::new_v4();
+        rule.user_id = user_id;
+    }
+
+    Ok(PreparedRestore { archive, attachments, files })
+}
+
+/// Write a prepared archive in one transaction, including the profile's names.
+/// Rows are inserted as they are, so restored incomes don't record goal contributions again.
+pub fn restore_archive(connection: &mut DbConnection, user_id: Uuid, restore: PreparedRestore) -> Result<ArchiveRestoreResult, AppError> {
+    let PreparedRestore { archive, attachments, .. } = restore;
+
+    connection.transaction::<_, AppError, _>(|conn| {
+        // Checked again here in case something was created since the restore was prepared
+        ensure_empty_account(conn, user_id)?;
+
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::first_name.eq(&archive.profile.first_name),
+                users::last_name.eq(&archive.profile.last_name),
+                users::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+
+        insert_batches!(conn, payees::table, archive.payees);
+        insert_batches!(conn, payee_rules::table, archive.payee_rules);
+        insert_batches!(conn, import_batches::table, archive.import_batches);
+        insert_batches!(conn, incomes::table, archive.incomes);
+        insert_batches!(conn, expenses::table, archive.expenses);
+        insert_batches!(conn, expense_splits::table, archive.expense_splits);
+        insert_batches!(conn, attachments::table, attachments);
+        insert_batches!(conn, goals::table, archive.goals);
+        insert_batches!(conn, goal_contributions::table, archive.goal_contributions);
+        insert_batches!(conn, net_worth_items::table, archive.net_worth_items);
+        insert_batches!(conn, net_worth_snapshots::table, archive.net_worth_snapshots);
+        insert_batches!(conn, transaction_rules::table, archive.transaction_rules);
+
+        Ok(ArchiveRestoreResult {
+            payees: archive.payees.len(),
+            payee_rules: archive.payee_rules.len(),
+            import_batches: archive.import_batches.len(),
+            incomes: archive.incomes.len(),
+            expenses: archive.expenses.len(),
+            expense_splits: archive.expense_splits.len(),
+            attachments: attachments.len(),
+            goals: archive.goals.len(),
+            goal_contributions: archive.goal_contributions.len(),
+            net_worth_items: archive.net_worth_items.len(),
+            net_worth_snapshots: archive.net_worth_snapshots.len(),
+            transaction_rules: archive.transaction_rules.len(),
+        })
+    })
+}
+
+/// Archives are only restored into accounts without any data, never merged
+fn ensure_empty_account(connection: &mut DbConnection, user_id: Uuid) -> Result<(), AppError> {
+    let has_data = diesel::select(diesel::dsl::exists(incomes::table.filter(incomes::user_id.eq(user_id))))
+        .get_result::<bool>(connection)?
+        || diesel::select(diesel::dsl::exists(expenses::table.filter(expenses::user_id.eq(user_id)))).get_result::<bool>(connection)?
+        || diesel::select(diesel::dsl::exists(payees::table.filter(payees::user_id.eq(user_id)))).get_result::<bool>(connection)?
+        || diesel::select(diesel::dsl::exists(goals::table.filter(goals::user_id.eq(user_id)))).get_result::<bool>(connection)?
+        || diesel::select(diesel::dsl::exists(net_worth_items::table.filter(net_worth_items::user_id.eq(user_id)))).get_result::<bool>(connection)?
+        || diesel::select(diesel::dsl::exists(import_batches::table.filter(import_batches::user_id.eq(user_id)))).get_result::<bool>(connection)?
+        || diesel::select(diesel::dsl::exists(transaction_rules::table.filter(transaction_rules::user_id.eq(user_id)))).get_result::<bool>(connection)?;
+
+    if has_data {
+        return Err(AppError::Validation(
+            "Archives can only be restored into an empty account, this one already has data".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Old ids of one kind of row mapped to the new ids they are restored with
+struct IdMap {
+    kind: &'static str,
+    ids: HashMap<Uuid, Uuid>,
+}
+
+impl IdMap {
+    fn new(kind: &'static str) -> Self {
+        IdMap { kind, ids: HashMap::new() }
+    }
+
+    fn assign(&mut self, old: Uuid) -> Result<Uuid, AppError> {
+        let new = Uuid::new_v4();
+        if self.ids.insert(old, new).is_some() {
+            return Err(AppError::Validation(format!("Archive lists {} {} more than once", self.kind, old)));
+        }
+        Ok(new)
+    }
+
+    fn get(&self, old: Uuid) -> Result<Uuid, AppError> {
+        self.ids
+            .get(&old)
+            .copied()
+            .ok_or_else(|| AppError::Validation(format!("Archive references {} {} which it does not contain", self.kind, old)))
+    }
+
+    fn get_optional(&self, old: Option<Uuid>) -> Result<Option<Uuid>, AppError> {
+        old.map(|old| self.get(old)).transpose()
+    }
+}
@@ -0,0 +1,122 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::attachment::{Attachment, AttachmentParent, NewAttachment};
+use crate::models::schema::{attachments, expenses, incomes};
+use crate::database::db_connection::DbConnection;
+
+/// Content types accepted for uploads, with the leading bytes every such file starts with
+const ALLOWED_CONTENT_TYPES: &[(&str, &[u8])] = &[
+    ("application/pdf", b"%PDF-"),
+    ("image/jpeg", &[0xFF, 0xD8, 0xFF]),
+    ("image/png", &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]),
+    ("image/webp", b"RIFF"),
+    ("image/heic", &[]),
+];
+
+/// Get the ID of the user owning the expense or income
+pub fn get_parent_owner(connection: &mut DbConnection, parent: AttachmentParent) -> Result<Uuid, diesel::result::Error> {
+    match parent {
+        AttachmentParent::Expense(expense_id) => expenses::table
+            .find(expense_id)
+            .select(expenses::user_id)
+            .first(connection),
+        AttachmentParent::Income(income_id) => incomes::table
+            .find(income_id)
+            .select(incomes::user_id)
+            .first(connection),
+    }
+}
+
+pub fn get_attachments(connection: &mut DbConnection, parent: AttachmentParent) -> Result<Vec<Attachment>, diesel::result::Error> {
+    let query = attachments::table.into_boxed();
+    let query = match parent {
+        AttachmentParent::Expense(expense_id) => query.filter(attachments::expense_id.eq(expense_id)),
+        AttachmentParent::Income(income_id) => query.filter(attachments::income_id.eq(income_id)),
+    };
+
+    query
+        .order(attachments::created_at.asc())
+        .select(Attachment::as_select())
+        .load(connection)
+}
+
+pub fn get_attachment(connection: &mut DbConnection, parent: AttachmentParent, attachment_id: Uuid) -> Result<Attachment, diesel::result::Error> {
+    let query = attachments::table
+        .filter(attachments::id.eq(attachment_id))
+        .into_boxed();
+    let query = match parent {
+        AttachmentParent::Expense(expense_id) => query.filter(attachments::expense_id.eq(expense_id)),
+        AttachmentParent::Income(income_id) => query.filter(attachments::income_id.eq(income_id)),
+    };
+
+    query
+        .select(Attachment::as_select())
+        .first(connection)
+}
+
+pub fn create_attachment(connection: &mut DbConnection, new_attachment: &NewAttachment) -> Result<Attachment, diesel::result::Error> {
+    diesel::insert_into(attachments::table)
+        .values(new_attachment)
+        .returning(Attachment::as_returning())
+        .get_result(connection)
+}
+
+/// Storage keys of the attachments of the given expenses and incomes. Deleting a transaction
+/// deletes its attachment rows with it, the caller removes the files once that is committed.
+pub fn get_storage_keys(connection: &mut DbConnection, expense_ids: &[Uuid], income_ids: &[Uuid]) -> Result<Vec<String>, diesel::result::Error> {
+    attachments::table
+        .filter(attachments::expense_id.eq_any(expense_ids).or(attachments::income_id.eq_any(income_ids)))
+        .select(attachments::storage_key)
+        .load(connection)
+}
+
+pub fn delete_attachment(connection: &mut DbConnection, attachment_id: Uuid) -> Result<Attachment, diesel::result::Error> {
+    diesel::delete(attachments::table.find(attachment_id))
+        .get_result(connection)
+}
+
+/// Check the declared content type is allowed and the file contents actually look like it
+pub fn validate_upload(content_type: &str, data: &[u8]) -> Result<(), AppError> {
+    if data.is_empty() {
+        return Err(AppError::Validation("Uploaded file is empty".to_string()));
+    }
+
+    let signature = ALLOWED_CONTENT_TYPES
+        .iter()
+        .find(|(allowed, _)| *allowed == content_type)
+        .map(|(_, signature)| *signature)
+        .ok_or_else(|| AppError::Validation(format!(
+            "Unsupported content type '{}', expected one of: {}",
+            content_type,
+            ALLOWED_CONTENT_TYPES.iter().map(|(allowed, _)| *allowed).collect::<Vec<_>>().join(", ")
+        )))?;
+
+    let matches = match content_type {
+        "image/webp" => data.starts_with(signature) && data.get(8..12) == Some(b"WEBP".as_slice()),
+        // HEIC files carry an ISO BMFF `ftyp` box with a HEIF brand
+        "image/heic" => data.get(4..8) == Some(b"ftyp".as_slice())
+            && data.get(8..12).is_some_and(|brand| matches!(brand, b"heic" | b"heix" | b"mif1" | b"msf1")),
+        _ => data.starts_with(signature),
+    };
+    if !matches {
+        return Err(AppError::Validation(format!("File contents do not match content type '{}'", content_type)));
+    }
+
+    Ok(())
+}
+
+/// Strip any client-side directories and control characters from an uploaded file name
+pub fn sanitize_file_name(file_name: Option<&str>) -> String {
+    let name = file_name
+        .and_then(|name| name.rsplit(['/', '\\']).next())
+        .map(|name| name.chars().filter(|c| !c.is_control() && *c != '"').collect::<String>())
+        .unwrap_or_default();
+
+    if name.trim().is_empty() {
+        "attachment".to_string()
+    } else {
+        name.trim().to_string()
+    }
+}
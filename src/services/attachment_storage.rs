@@ -0,0 +1,219 @@
+use actix_web::web::{self, Bytes};
+use async_trait::async_trait;
+use object_store::aws::{AmazonS3, AmazonS3Builder};
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config;
+use crate::config::errors::AppError;
+
+/// Backend that stores the raw bytes of transaction attachments
+#[async_trait]
+pub trait AttachmentStorage: Send + Sync {
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), AppError>;
+    async fn get(&self, key: &str) -> Result<Bytes, AppError>;
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+/// Create the storage backend selected by ATTACHMENT_STORAGE
+pub fn create_storage() -> Arc<dyn AttachmentStorage> {
+    match config::get_attachment_storage().as_str() {
+        "s3" => {
+            log::info!("Using S3-compatible attachment storage");
+            Arc::new(S3Storage::new(config::get_s3_config()))
+        }
+        _ => {
+            let root = config::get_attachment_dir();
+            log::info!("Using local attachment storage at: {}", root);
+            Arc::new(LocalStorage::new(root))
+        }
+    }
+}
+
+/// Remove the files of attachments whose rows were deleted with their transactions.
+/// The deletion is already committed, so failures are only logged.
+pub async fn remove_files(storage: &dyn AttachmentStorage, keys: &[String]) {
+    for key in keys {
+        if let Err(error) = storage.delete(key).await {
+            log::warn!("Failed to remove attachment file {}: {}", key, error);
+        }
+    }
+}
+
+/// Stores attachments as files below a root directory
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, AppError> {
+        // Keys are generated by us, but never let one escape the root directory
+        if key.split('/').any(|part| part.is_empty() || part == "." || part == "..") {
+            return Err(AppError::InternalServer(format!("Invalid storage key: {}", key)));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl AttachmentStorage for LocalStorage {
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), AppError> {
+        let path = self.path_for(key)?;
+        web::block(move || {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, &data)
+        })
+        .await
+        .map_err(|e| AppError::InternalServer(e.to_string()))?
+        .map_err(storage_error)
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, AppError> {
+        let path = self.path_for(key)?;
+        let data = web::block(move || std::fs::read(path))
+            .await
+            .map_err(|e| AppError::InternalServer(e.to_string()))?
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => AppError::NotFound("Attachment file not found".to_string()),
+                _ => storage_error(e),
+            })?;
+        Ok(Bytes::from(data))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let path = self.path_for(key)?;
+        web::block(move || match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        })
+        .await
+        .map_err(|e| AppError::InternalServer(e.to_string()))?
+        .map_err(storage_error)
+    }
+}
+
+/// Stores attachments in an S3-compatible bucket (AWS S3, MinIO, ...)
+pub struct S3Storage {
+    store: AmazonS3,
+}
+
+impl S3Storage {
+    pub fn new(s3_config: config::S3Config) -> Self {
+        let store = AmazonS3Builder::new()
+            .with_endpoint(&s3_config.endpoint)
+            .with_bucket_name(&s3_config.bucket)
+            .with_region(&s3_config.region)
+            .with_access_key_id(&s3_config.access_key_id)
+            .with_secret_access_key(&s3_config.secret_access_key)
+            // Path-style requests over plain HTTP so local stand-ins like MinIO work
+            .with_virtual_hosted_style_request(false)
+            .with_allow_http(s3_config.endpoint.starts_with("http://"))
+            .build()
+            .expect("❌ Invalid S3 attachment storage configuration");
+
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl AttachmentStorage for S3Storage {
+    async fn put(&self, key: &str, data: Bytes) -> Result<(), AppError> {
+        self.store
+            .put(&ObjectPath::from(key), data.into())
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, AppError> {
+        let result = self.store
+            .get(&ObjectPath::from(key))
+            .await
+            .map_err(|e| match e {
+                object_store::Error::NotFound { .. } => AppError::NotFound("Attachment file not found".to_string()),
+                _ => storage_error(e),
+            })?;
+        result.bytes().await.map_err(storage_error)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        match self.store.delete(&ObjectPath::from(key)).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+}
+
+fn storage_error(error: impl std::fmt::Display) -> AppError {
+    log::error!("Attachment storage error: {}", error);
+    AppError::InternalServer("Attachment storage failed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_storage() -> (LocalStorage, PathBuf) {
+        let root = std::env::temp_dir().join(format!("attachments-{}", Uuid::new_v4()));
+        (LocalStorage::new(&root), root)
+    }
+
+    async fn assert_round_trip(storage: &dyn AttachmentStorage, key: &str) {
+        storage.put(key, Bytes::from_static(b"%PDF-1.7 first")).await.unwrap();
+        assert_eq!(storage.get(key).await.unwrap(), Bytes::from_static(b"%PDF-1.7 first"));
+
+        storage.put(key, Bytes::from_static(b"%PDF-1.7 second")).await.unwrap();
+        assert_eq!(storage.get(key).await.unwrap(), Bytes::from_static(b"%PDF-1.7 second"));
+
+        storage.delete(key).await.unwrap();
+        assert!(matches!(storage.get(key).await, Err(AppError::NotFound(_))));
+        // Deleting a file that is already gone is not an error
+        storage.delete(key).await.unwrap();
+    }
+
+    #[actix_web::test]
+    async fn local_storage_round_trip() {
+        let (storage, root) = temp_storage();
+        assert_round_trip(&storage, "user/expense/receipt.pdf").await;
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[actix_web::test]
+    async fn local_storage_rejects_keys_leaving_the_root() {
+        let (storage, root) = temp_storage();
+        for key in ["../outside", "user/../../outside", "/etc/passwd", "user//file", "./file", ""] {
+            assert!(matches!(storage.put(key, Bytes::new()).await, Err(AppError::InternalServer(_))), "{}", key);
+            assert!(matches!(storage.get(key).await, Err(AppError::InternalServer(_))), "{}", key);
+        }
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    #[actix_web::test]
+    async fn remove_files_skips_missing_files() {
+        let (storage, root) = temp_storage();
+        storage.put("user/a.pdf", Bytes::from_static(b"%PDF-")).await.unwrap();
+
+        remove_files(&storage, &["user/a.pdf".to_string(), "user/missing.pdf".to_string()]).await;
+        assert!(matches!(storage.get("user/a.pdf").await, Err(AppError::NotFound(_))));
+        std::fs::remove_dir_all(root).ok();
+    }
+
+    /// Run with a bucket on MinIO or S3, for example:
+    /// `S3_ENDPOINT=http://localhost:9000 S3_BUCKET=attachments S3_ACCESS_KEY_ID=minioadmin
+    /// S3_SECRET_ACCESS_KEY=minioadmin cargo test s3_storage -- --ignored`
+    #[actix_web::test]
+    #[ignore = "needs an S3-compatible server configured with the S3_* variables"]
+    async fn s3_storage_round_trip() {
+        let storage = S3Storage::new(config::get_s3_config());
+        assert_round_trip(&storage, &format!("tests/{}/receipt.pdf", Uuid::new_v4())).await;
+    }
+}
@@ -0,0 +1,61 @@
+use actix_web::ResponseError;
+use diesel::prelude::*;
+
+use crate::config::errors::AppError;
+use crate::database::db_connection::DbConnection;
+use crate::models::bulk::{BulkMode, MAX_BULK_OPERATIONS};
+
+/// A successful operation: the status it would have had as a single call and what it returns
+pub struct BulkSuccess<T> {
+    pub status: u16,
+    pub id: uuid::Uuid,
+    pub record: Option<T>,
+    /// Attachment files of a deleted record, removed once the request is committed
+    pub storage_keys: Vec<String>,
+}
+
+/// Run the operations of a bulk request in order.
+///
+/// In atomic mode the first failure rolls back everything and is returned, naming the operation.
+/// In per-item mode each operation commits on its own and failures are kept in the list.
+pub fn run_bulk<O, T>(
+    connection: &mut DbConnection,
+    mode: BulkMode,
+    operations: Vec<O>,
+    mut run: impl FnMut(&mut DbConnection, O) -> Result<BulkSuccess<T>, AppError>,
+) -> Result<Vec<Result<BulkSuccess<T>, AppError>>, AppError> {
+    if operations.len() > MAX_BULK_OPERATIONS {
+        return Err(AppError::Validation(format!(
+            "A bulk request may carry at most {} operations",
+            MAX_BULK_OPERATIONS
+        )));
+    }
+
+    match mode {
+        BulkMode::Atomic => connection.transaction(|connection| {
+            operations
+                .into_iter()
+                .enumerate()
+                .map(|(index, operation)| run(connection, operation).map(Ok).map_err(|e| at_operation(index, e)))
+                .collect()
+        }),
+        BulkMode::PerItem => Ok(operations.into_iter().map(|operation| run(connection, operation)).collect()),
+    }
+}
+
+/// Status and message reported for an operation that failed in per-item mode
+pub fn failure(error: &AppError) -> (u16, String) {
+    (error.status_code().as_u16(), error.to_string())
+}
+
+fn at_operation(index: usize, error: AppError) -> AppError {
+    let context = |msg: String| format!("Operation {}: {}", index, msg);
+    match error {
+        AppError::Database(msg) => AppError::Database(context(msg)),
+        AppError::Validation(msg) => AppError::Validation(context(msg)),
+        AppError::NotFound(msg) => AppError::NotFound(context(msg)),
+        AppError::Unauthorized(msg) => AppError::Unauthorized(context(msg)),
+        AppError::BadRequest(msg) => AppError::BadRequest(context(msg)),
+        AppError::InternalServer(msg) => AppError::InternalServer(context(msg)),
+    }
+}
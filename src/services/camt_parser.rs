@@ -0,0 +1,300 @@
+use chrono::NaiveDate;
+use roxmltree::{Document, Node};
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::import::ImportRow;
+use crate::services::import_service::{decode_text, set_amount, set_date};
+
+/// Read the entries of an ISO 20022 camt.053 bank statement. The camt.052 and camt.054
+/// variants share the entry layout and are read the same way.
+///
+/// Transactions are dated by their value date, falling back to the booking date. A batch
+/// booking with amounts on each of its transaction details becomes one row per transaction.
+pub fn parse_camt(data: &[u8]) -> Result<Vec<ImportRow>, AppError> {
+    let text = decode_text(data);
+    let document = Document::parse(&text).map_err(|e| AppError::Validation(format!("Invalid camt XML: {}", e)))?;
+
+    let reports: Vec<Node> = document
+        .root_element()
+        .children()
+        .filter(Node::is_element)
+        .flat_map(|message| message.children().filter(|node| matches!(node.tag_name().name(), "Stmt" | "Rpt" | "Ntfctn")))
+        .collect();
+    if reports.is_empty() {
+        return Err(AppError::Validation("Not a camt statement, no Stmt, Rpt or Ntfctn element found".to_string()));
+    }
+
+    let mut rows = Vec::new();
+    for report in reports {
+        let account = text_at(report, &["Acct", "Id", "IBAN"]).or_else(|| text_at(report, &["Acct", "Id", "Othr", "Id"]));
+        for entry in children(report, "Ntry") {
+            let line = document.text_pos_at(entry.range().start).row as usize;
+            rows.extend(entry_rows(line, entry, account.as_deref()));
+        }
+    }
+
+    if rows.is_empty() {
+        return Err(AppError::Validation("The camt statement contains no entries".to_string()));
+    }
+    Ok(rows)
+}
+
+fn entry_rows(line: usize, entry: Node, account: Option<&str>) -> Vec<ImportRow> {
+    let details: Vec<Node> = children(entry, "NtryDtls").flat_map(|batch| children(batch, "TxDtls")).collect();
+    let entry_reference = text_at(entry, &["AcctSvcrRef"]);
+
+    // A batch booking only splits into transactions when each of them carries its amount
+    if details.len() > 1 && details.iter().all(|detail| detail_amount(*detail).is_some()) {
+        return details
+            .iter()
+            .enumerate()
+            .map(|(index, detail)| {
+                let fallback_reference = entry_reference.as_ref().map(|reference| format!("{}-{}", reference, index + 1));
+                transaction_row(line, entry, Some(*detail), account, detail_amount(*detail), fallback_reference)
+            })
+            .collect();
+    }
+
+    vec![transaction_row(line, entry, details.first().copied(), account, None, entry_reference)]
+}
+
+fn transaction_row(line: usize, entry: Node, detail: Option<Node>, account: Option<&str>, amount: Option<Node>, fallback_reference: Option<String>) -> ImportRow {
+    let mut row = ImportRow::new(line);
+    row.external_account = account.map(str::to_string);
+
+    let status = text_at(entry, &["Sts", "Cd"]).or_else(|| text_at(entry, &["Sts"]));
+    if let Some(status) = status.filter(|status| status != "BOOK") {
+        row.errors.push(format!("Entry status is {}, only booked entries are imported", status));
+    }
+
+    let date = text_at(entry, &["ValDt", "Dt"])
+        .or_else(|| text_at(entry, &["ValDt", "DtTm"]))
+        .or_else(|| text_at(entry, &["BookgDt", "Dt"]))
+        .or_else(|| text_at(entry, &["BookgDt", "DtTm"]));
+    match date {
+        Some(value) => match value.get(..10).and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()) {
+            Some(date) => set_date(&mut row, date),
+            None => row.errors.push(format!("Invalid date '{}'", value)),
+        },
+        None => row.errors.push("Missing ValDt and BookgDt".to_string()),
+    }
+
+    let credit_debit = detail
+        .and_then(|detail| text_at(detail, &["CdtDbtInd"]))
+        .or_else(|| text_at(entry, &["CdtDbtInd"]));
+    let amount = amount.or_else(|| child(entry, "Amt")).and_then(|amount| amount.text()).map(str::trim);
+    match (amount, credit_debit.as_deref()) {
+        (Some(value), Some(indicator @ ("CRDT" | "DBIT"))) => match value.parse::<Decimal>() {
+            Ok(amount) if indicator == "DBIT" => set_amount(&mut row, -amount),
+            Ok(amount) => set_amount(&mut row, amount),
+            Err(_) => row.errors.push(format!("Invalid amount '{}'", value)),
+        },
+        (None, _) => row.errors.push("Missing Amt".to_string()),
+        (Some(_), indicator) => row.errors.push(format!("Invalid CdtDbtInd '{}'", indicator.unwrap_or_default())),
+    }
+
+    // The other side is the debtor of money coming in and the creditor of money going out
+    let counterparty = detail.and_then(|detail| {
+        let role = if credit_debit.as_deref() == Some("CRDT") { "Dbtr" } else { "Cdtr" };
+        text_at(detail, &["RltdPties", role, "Nm"]).or_else(|| text_at(detail, &["RltdPties", role, "Pty", "Nm"]))
+    });
+    let remittance = detail.and_then(remittance_info);
+    let additional = detail
+        .and_then(|detail| text_at(detail, &["AddtlTxInf"]))
+        .or_else(|| text_at(entry, &["AddtlNtryInf"]));
+
+    match counterparty {
+        Some(name) => {
+            row.name = Some(name);
+            row.description = remittance.or(additional);
+        }
+        None => {
+            row.name = additional.clone().or_else(|| remittance.clone());
+            row.description = remittance.filter(|remittance| row.name.as_ref() != Some(remittance));
+        }
+    }
+    if row.name.is_none() {
+        row.errors.push("Missing counterparty and remittance information".to_string());
+    }
+
+    row.external_id = detail
+        .and_then(|detail| text_at(detail, &["Refs", "AcctSvcrRef"]))
+        .or(fallback_reference);
+
+    row
+}
+
+/// Unstructured remittance lines joined together, or the creditor reference of a structured one
+fn remittance_info(detail: Node) -> Option<String> {
+    let information = child(detail, "RmtInf")?;
+    let lines: Vec<&str> = children(information, "Ustrd")
+        .filter_map(|line| line.text())
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if !lines.is_empty() {
+        return Some(lines.join(" "));
+    }
+    children(information, "Strd").find_map(|structured| text_at(structured, &["CdtrRefInf", "Ref"]))
+}
+
+fn detail_amount<'a, 'input>(detail: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    child(detail, "Amt").or_else(|| path(detail, &["AmtDtls", "TxAmt", "Amt"]))
+}
+
+fn children<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    children(node, name).next()
+}
+
+fn path<'a, 'input>(node: Node<'a, 'input>, names: &[&'static str]) -> Option<Node<'a, 'input>> {
+    names.iter().try_fold(node, |node, name| child(node, name))
+}
+
+/// Trimmed text of the element at `names` below `node`, if it has any
+fn text_at(node: Node, names: &[&'static str]) -> Option<String> {
+    path(node, names)
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transaction::TransactionKind;
+    use crate::services::test_support::amount;
+
+    fn statement(entries: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+      {}
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#,
+            entries
+        )
+    }
+
+    #[test]
+    fn parse_camt_picks_the_counterparty_by_direction() {
+        let xml = statement(
+            r#"<Ntry>
+        <Amt Ccy="EUR">1500.00</Amt><CdtDbtInd>CRDT</CdtDbtInd><Sts>BOOK</Sts>
+        <BookgDt><Dt>2024-03-20</Dt></BookgDt><ValDt><Dt>2024-03-21</Dt></ValDt>
+        <NtryDtls><TxDtls>
+          <Refs><AcctSvcrRef>TX-1</AcctSvcrRef></Refs>
+          <RltdPties><Dbtr><Nm>Acme Corp</Nm></Dbtr><Cdtr><Nm>Jane Doe</Nm></Cdtr></RltdPties>
+          <RmtInf><Ustrd>Salary</Ustrd><Ustrd>March</Ustrd></RmtInf>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">42.10</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2024-03-22</Dt></BookgDt>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>INV-2024-03</EndToEndId></Refs>
+          <RltdPties><Dbtr><Nm>Jane Doe</Nm></Dbtr><Cdtr><Pty><Nm>City Power</Nm></Pty></Cdtr></RltdPties>
+        </TxDtls></NtryDtls>
+      </Ntry>"#,
+        );
+        let rows = parse_camt(xml.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let salary = &rows[0];
+        assert_eq!(salary.external_account.as_deref(), Some("DE89370400440532013000"));
+        assert_eq!(salary.date, NaiveDate::from_ymd_opt(2024, 3, 21));
+        assert_eq!((salary.kind, salary.amount), (Some(TransactionKind::Income), amount("1500.00")));
+        assert_eq!(salary.name.as_deref(), Some("Acme Corp"));
+        assert_eq!(salary.description.as_deref(), Some("Salary March"));
+        assert_eq!(salary.external_id.as_deref(), Some("TX-1"));
+
+        let bill = &rows[1];
+        assert_eq!(bill.date, NaiveDate::from_ymd_opt(2024, 3, 22));
+        assert_eq!((bill.kind, bill.amount), (Some(TransactionKind::Expense), amount("42.10")));
+        assert_eq!(bill.name.as_deref(), Some("City Power"));
+        // The end-to-end id comes from the payer, only the bank's reference identifies the entry
+        assert_eq!(bill.external_id, None);
+        assert!(bill.errors.is_empty());
+    }
+
+    #[test]
+    fn parse_camt_splits_batch_bookings() {
+        let xml = statement(
+            r#"<Ntry>
+        <Amt Ccy="EUR">30.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>BOOK</Sts>
+        <ValDt><Dt>2024-03-20</Dt></ValDt><AcctSvcrRef>BATCH-7</AcctSvcrRef>
+        <NtryDtls>
+          <Btch><NbOfTxs>2</NbOfTxs></Btch>
+          <TxDtls>
+            <AmtDtls><TxAmt><Amt Ccy="EUR">20.00</Amt></TxAmt></AmtDtls>
+            <RltdPties><Cdtr><Nm>Shop A</Nm></Cdtr></RltdPties>
+          </TxDtls>
+          <TxDtls>
+            <Amt Ccy="EUR">10.00</Amt>
+            <Refs><AcctSvcrRef>OWN-2</AcctSvcrRef></Refs>
+            <RltdPties><Cdtr><Nm>Shop B</Nm></Cdtr></RltdPties>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>"#,
+        );
+        let rows = parse_camt(xml.as_bytes()).unwrap();
+
+        let summary: Vec<_> = rows.iter().map(|row| (row.name.as_deref(), row.amount, row.external_id.as_deref())).collect();
+        assert_eq!(summary, vec![
+            (Some("Shop A"), amount("20.00"), Some("BATCH-7-1")),
+            (Some("Shop B"), amount("10.00"), Some("OWN-2")),
+        ]);
+        assert!(rows.iter().all(|row| row.kind == Some(TransactionKind::Expense)));
+    }
+
+    #[test]
+    fn parse_camt_keeps_a_batch_without_detail_amounts_whole() {
+        let xml = statement(
+            r#"<Ntry>
+        <Amt Ccy="EUR">30.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts>BOOK</Sts>
+        <ValDt><Dt>2024-03-20</Dt></ValDt><AcctSvcrRef>BATCH-8</AcctSvcrRef>
+        <AddtlNtryInf>Direct debits</AddtlNtryInf>
+        <NtryDtls>
+          <TxDtls><RmtInf><Ustrd>First</Ustrd></RmtInf></TxDtls>
+          <TxDtls><RmtInf><Ustrd>Second</Ustrd></RmtInf></TxDtls>
+        </NtryDtls>
+      </Ntry>"#,
+        );
+        let rows = parse_camt(xml.as_bytes()).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].amount, amount("30.00"));
+        assert_eq!(rows[0].name.as_deref(), Some("Direct debits"));
+        assert_eq!(rows[0].description.as_deref(), Some("First"));
+        assert_eq!(rows[0].external_id.as_deref(), Some("BATCH-8"));
+    }
+
+    #[test]
+    fn parse_camt_flags_entries_not_booked() {
+        let xml = statement(
+            r#"<Ntry>
+        <Amt Ccy="EUR">5.00</Amt><CdtDbtInd>DBIT</CdtDbtInd><Sts><Cd>PDNG</Cd></Sts>
+        <BookgDt><Dt>2024-03-20</Dt></BookgDt><AddtlNtryInf>Card payment</AddtlNtryInf>
+      </Ntry>"#,
+        );
+        let rows = parse_camt(xml.as_bytes()).unwrap();
+
+        assert_eq!(rows[0].errors, vec!["Entry status is PDNG, only booked entries are imported".to_string()]);
+    }
+
+    #[test]
+    fn parse_camt_rejects_other_documents() {
+        assert!(parse_camt(b"<Document><Other/></Document>").is_err());
+        assert!(parse_camt(statement("").as_bytes()).is_err());
+        assert!(parse_camt(b"not xml").is_err());
+    }
+}
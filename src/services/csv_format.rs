@@ -0,0 +1,152 @@
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::export::CsvOptions;
+
+/// RFC 4180 CSV with a configurable delimiter and decimal separator
+#[derive(Debug, Clone, Copy)]
+pub struct CsvFormat {
+    delimiter: char,
+    decimal_separator: char,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        CsvFormat { delimiter: ',', decimal_separator: '.' }
+    }
+}
+
+impl CsvFormat {
+    pub fn from_options(options: &CsvOptions) -> Result<Self, AppError> {
+        let mut format = CsvFormat::default();
+
+        if let Some(delimiter) = &options.delimiter {
+            let mut chars = delimiter.chars();
+            format.delimiter = match (chars.next(), chars.next()) {
+                (Some(c), None) if !matches!(c, '"' | '\r' | '\n') => c,
+                _ => return Err(AppError::BadRequest("'delimiter' must be a single character other than a quote or line break".to_string())),
+            };
+        }
+        if let Some(separator) = &options.decimal_separator {
+            format.decimal_separator = match separator.as_str() {
+                "." => '.',
+                "," => ',',
+                _ => return Err(AppError::BadRequest("'decimal_separator' must be '.' or ','".to_string())),
+            };
+        }
+
+        Ok(format)
+    }
+
+    pub fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    /// Append one record terminated by CRLF, quoting fields that need it
+    pub fn write_record<'a>(&self, out: &mut String, fields: impl IntoIterator<Item = &'a str>) {
+        for (index, field) in fields.into_iter().enumerate() {
+            if index > 0 {
+                out.push(self.delimiter);
+            }
+            let needs_quotes = field
+                .chars()
+                .any(|c| c == self.delimiter || matches!(c, '"' | '\r' | '\n'));
+            if needs_quotes {
+                out.push('"');
+                out.push_str(&field.replace('"', "\"\""));
+                out.push('"');
+            } else {
+                out.push_str(field);
+            }
+        }
+        out.push_str("\r\n");
+    }
+
+    /// Read an amount such as `-1.234,56`, `(12.50)` or `EUR 7.00`, ignoring currency
+    /// symbols, spaces and thousands separators
+    pub fn parse_decimal(&self, text: &str) -> Option<Decimal> {
+        let separator = self.decimal_separator;
+        let trimmed = text.trim_matches(|c: char| {
+            !(c.is_ascii_digit() || matches!(c, '-' | '+' | '(' | ')') || c == separator)
+        });
+
+        let (negative, unsigned) = if let Some(inner) = trimmed.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+            (true, inner)
+        } else if let Some(rest) = trimmed.strip_prefix('-').or_else(|| trimmed.strip_suffix('-')) {
+            (true, rest)
+        } else {
+            (false, trimmed.strip_prefix('+').unwrap_or(trimmed))
+        };
+        // A currency symbol may also sit between the sign and the digits, as in `-$5.00`
+        let unsigned = unsigned.trim_matches(|c: char| !(c.is_ascii_digit() || c == separator));
+
+        let digits: String = unsigned
+            .chars()
+            .filter_map(|c| match c {
+                c if c.is_ascii_digit() => Some(c),
+                c if c == separator => Some('.'),
+                // Thousands separators
+                '.' | ',' | ' ' | '\'' | '\u{a0}' => None,
+                _ => Some('x'),
+            })
+            .collect();
+        let value = digits.parse::<Decimal>().ok()?;
+        Some(if negative { -value } else { value })
+    }
+
+    /// The exact decimal value as stored, never going through a float
+    pub fn decimal(&self, value: Decimal) -> String {
+        let text = value.to_string();
+        if self.decimal_separator == '.' {
+            text
+        } else {
+            text.replace('.', &self.decimal_separator.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::amount;
+
+    const DECIMAL_COMMA: CsvFormat = CsvFormat { delimiter: ';', decimal_separator: ',' };
+
+    #[test]
+    fn parse_decimal_reads_signs() {
+        let format = CsvFormat::default();
+        assert_eq!(format.parse_decimal("12.50"), amount("12.50"));
+        assert_eq!(format.parse_decimal("+12.50"), amount("12.50"));
+        assert_eq!(format.parse_decimal("-12.50"), amount("-12.50"));
+        assert_eq!(format.parse_decimal("12.50-"), amount("-12.50"));
+        assert_eq!(format.parse_decimal("(12.50)"), amount("-12.50"));
+        assert_eq!(format.parse_decimal(" (1,000.00) "), amount("-1000.00"));
+    }
+
+    #[test]
+    fn parse_decimal_ignores_currency_text() {
+        let format = CsvFormat::default();
+        assert_eq!(format.parse_decimal("EUR 7.00"), amount("7.00"));
+        assert_eq!(format.parse_decimal("$1,234.56"), amount("1234.56"));
+        assert_eq!(format.parse_decimal("-£3.20"), amount("-3.20"));
+        assert_eq!(format.parse_decimal("($ 5.00)"), amount("-5.00"));
+        assert_eq!(DECIMAL_COMMA.parse_decimal("1 234,56 €"), amount("1234.56"));
+    }
+
+    #[test]
+    fn parse_decimal_skips_thousands_separators() {
+        assert_eq!(CsvFormat::default().parse_decimal("1,234,567.89"), amount("1234567.89"));
+        assert_eq!(CsvFormat::default().parse_decimal("1'234.50"), amount("1234.50"));
+        assert_eq!(DECIMAL_COMMA.parse_decimal("-1.234,56"), amount("-1234.56"));
+        assert_eq!(DECIMAL_COMMA.parse_decimal("1\u{a0}234,5"), amount("1234.5"));
+    }
+
+    #[test]
+    fn parse_decimal_rejects_other_text() {
+        let format = CsvFormat::default();
+        assert_eq!(format.parse_decimal(""), None);
+        assert_eq!(format.parse_decimal("n/a"), None);
+        assert_eq!(format.parse_decimal("12a34"), None);
+        assert_eq!(format.parse_decimal("1.2.3"), None);
+    }
+}
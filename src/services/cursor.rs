@@ -0,0 +1,172 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::NaiveDate;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::config;
+use crate::config::errors::AppError;
+use crate::models::pagination::{Cursor, CursorDirection, SortOrder};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Serialize and sign a cursor as `<payload>.<signature>`, both base64url encoded
+pub fn encode(cursor: &Cursor) -> String {
+    let direction = match cursor.direction {
+        CursorDirection::Next => "next",
+        CursorDirection::Prev => "prev",
+    };
+    let order = match cursor.order {
+        SortOrder::Asc => "asc",
+        SortOrder::Desc => "desc",
+    };
+    let payload = format!("{}|{}|{}|{}", cursor.date, cursor.id, direction, order);
+
+    let mut mac = signer();
+    mac.update(payload.as_bytes());
+    let signature = mac.finalize().into_bytes();
+
+    format!("{}.{}", URL_SAFE_NO_PAD.encode(payload), URL_SAFE_NO_PAD.encode(signature))
+}
+
+/// Verify a cursor's signature and parse it back
+pub fn decode(token: &str) -> Result<Cursor, AppError> {
+    let invalid = || AppError::BadRequest("Invalid pagination cursor".to_string());
+
+    let (payload, signature) = token.split_once('.').ok_or_else(invalid)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| invalid())?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+
+    let mut mac = signer();
+    mac.update(&payload);
+    mac.verify_slice(&signature).map_err(|_| invalid())?;
+
+    let payload = String::from_utf8(payload).map_err(|_| invalid())?;
+    let mut parts = payload.split('|');
+    let date = parts
+        .next()
+        .and_then(|date| date.parse::<NaiveDate>().ok())
+        .ok_or_else(invalid)?;
+    let id = parts
+        .next()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(invalid)?;
+    let direction = match parts.next() {
+        Some("next") => CursorDirection::Next,
+        Some("prev") => CursorDirection::Prev,
+        _ => return Err(invalid()),
+    };
+    let order = match parts.next() {
+        Some("asc") => SortOrder::Asc,
+        Some("desc") => SortOrder::Desc,
+        _ => return Err(invalid()),
+    };
+
+    Ok(Cursor { date, id, direction, order })
+}
+
+/// Trim a keyset page fetched with one extra row and work out the cursors around it.
+///
+/// `items` must be in the order they were fetched, which is reversed for `Prev` cursors,
+/// and is left in the listing's sort order.
+pub fn finish_page<T>(
+    items: &mut Vec<T>,
+    limit: i64,
+    cursor: Option<&Cursor>,
+    order: SortOrder,
+    key: impl Fn(&T) -> (NaiveDate, Uuid),
+) -> (Option<String>, Option<String>) {
+    let has_more = items.len() as i64 > limit;
+    items.truncate(limit as usize);
+
+    let backwards = cursor.is_some_and(|cursor| cursor.direction == CursorDirection::Prev);
+    if backwards {
+        items.reverse();
+    }
+
+    let make = |item: &T, direction| {
+        let (date, id) = key(item);
+        encode(&Cursor { date, id, direction, order })
+    };
+    let first = items.first();
+    let last = items.last();
+
+    if backwards {
+        (
+            last.map(|item| make(item, CursorDirection::Next)),
+            first.filter(|_| has_more).map(|item| make(item, CursorDirection::Prev)),
+        )
+    } else {
+        (
+            last.filter(|_| has_more).map(|item| make(item, CursorDirection::Next)),
+            first.filter(|_| cursor.is_some()).map(|item| make(item, CursorDirection::Prev)),
+        )
+    }
+}
+
+fn signer() -> HmacSha256 {
+    // Derive a separate key so cursor signatures can never be confused with tokens
+    let mut key = HmacSha256::new_from_slice(config::get_jwt_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    key.update(b"pagination-cursor");
+    HmacSha256::new_from_slice(&key.finalize().into_bytes())
+        .expect("HMAC accepts keys of any length")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token() -> String {
+        std::env::set_var("JWT_SECRET", "cursor-tests-secret-of-at-least-32-chars");
+        encode(&Cursor {
+            date: NaiveDate::from_ymd_opt(2024, 3, 20).unwrap(),
+            id: Uuid::parse_str("123e4567-e89b-12d3-a456-426614174000").unwrap(),
+            direction: CursorDirection::Prev,
+            order: SortOrder::Asc,
+        })
+    }
+
+    fn is_invalid(token: &str) -> bool {
+        matches!(decode(token), Err(AppError::BadRequest(message)) if message == "Invalid pagination cursor")
+    }
+
+    #[test]
+    fn decode_reads_back_an_encoded_cursor() {
+        let cursor = decode(&token()).unwrap();
+        assert_eq!(cursor.date, NaiveDate::from_ymd_opt(2024, 3, 20).unwrap());
+        assert_eq!(cursor.id.to_string(), "123e4567-e89b-12d3-a456-426614174000");
+        assert_eq!(cursor.direction, CursorDirection::Prev);
+        assert_eq!(cursor.order, SortOrder::Asc);
+    }
+
+    #[test]
+    fn decode_rejects_a_changed_payload() {
+        let token = token();
+        let (payload, signature) = token.split_once('.').unwrap();
+        let mut bytes = URL_SAFE_NO_PAD.decode(payload).unwrap();
+        bytes[3] ^= 1;
+        assert!(is_invalid(&format!("{}.{}", URL_SAFE_NO_PAD.encode(bytes), signature)));
+    }
+
+    #[test]
+    fn decode_rejects_another_cursors_signature() {
+        let token = token();
+        let other = encode(&Cursor {
+            date: NaiveDate::from_ymd_opt(2024, 3, 21).unwrap(),
+            id: Uuid::nil(),
+            direction: CursorDirection::Next,
+            order: SortOrder::Desc,
+        });
+        let (payload, _) = token.split_once('.').unwrap();
+        let (_, signature) = other.split_once('.').unwrap();
+        assert!(is_invalid(&format!("{}.{}", payload, signature)));
+    }
+
+    #[test]
+    fn decode_needs_the_separator() {
+        assert!(is_invalid(&token().replace('.', "")));
+        assert!(is_invalid(""));
+    }
+}
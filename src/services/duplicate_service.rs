@@ -0,0 +1,228 @@
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::BigInt;
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::duplicate::{
+    DuplicateCandidate, DuplicateCandidateId, DuplicateGroup, DuplicateQuery, MergeDuplicatesRequest,
+    MergeDuplicatesResult,
+};
+use crate::models::expense::Expense;
+use crate::models::income::Income;
+use crate::models::schema::{attachments, expenses, incomes};
+use crate::models::transaction::{Transaction, TransactionKind};
+use crate::database::db_connection::DbConnection;
+use crate::services::expense_service;
+use crate::services::payee_service::normalize_name;
+
+/// id, name, amount, date, description and created_at of a possible duplicate
+type CandidateRow = (Uuid, String, Decimal, NaiveDate, Option<String>, NaiveDateTime);
+
+const DEFAULT_WINDOW_DAYS: i64 = 3;
+const MAX_WINDOW_DAYS: i64 = 31;
+
+/// Rows of `{table}` sharing user and amount with another row at most `$2` days apart.
+/// `{table}` is one of the fixed table names below, text similarity is checked afterwards.
+const CANDIDATES_SQL: &str = "
+    SELECT DISTINCT a.id
+    FROM {table} a
+    JOIN {table} b ON b.user_id = a.user_id AND b.amount = a.amount AND b.id <> a.id
+                  AND abs(a.date - b.date) <= $2
+    WHERE a.user_id = $1";
+
+/// Groups of likely duplicate incomes and expenses, most recent first
+pub fn find_duplicates(connection: &mut DbConnection, user_id: Uuid, query: &DuplicateQuery) -> Result<Vec<DuplicateGroup>, AppError> {
+    let window_days = query.window_days.unwrap_or(DEFAULT_WINDOW_DAYS);
+    if !(0..=MAX_WINDOW_DAYS).contains(&window_days) {
+        return Err(AppError::BadRequest(format!("'window_days' must be between 0 and {}", MAX_WINDOW_DAYS)));
+    }
+
+    let mut groups = Vec::new();
+    if query.kind != Some(TransactionKind::Expense) {
+        let ids = candidate_ids(connection, "incomes", user_id, window_days)?;
+        let candidates = incomes::table
+            .filter(incomes::id.eq_any(ids))
+            .select((incomes::id, incomes::source, incomes::amount, incomes::date, incomes::description, incomes::created_at))
+            .load::<CandidateRow>(connection)?;
+        groups.extend(group_candidates(TransactionKind::Income, candidates, window_days));
+    }
+    if query.kind != Some(TransactionKind::Income) {
+        let ids = candidate_ids(connection, "expenses", user_id, window_days)?;
+        let candidates = expenses::table
+            .filter(expenses::id.eq_any(ids))
+            .select((expenses::id, expenses::item_name, expenses::amount, expenses::date, expenses::description, expenses::created_at))
+            .load::<CandidateRow>(connection)?;
+        groups.extend(group_candidates(TransactionKind::Expense, candidates, window_days));
+    }
+
+    groups.sort_by_key(|group| {
+        std::cmp::Reverse(group.transactions.iter().map(|candidate| candidate.date).max())
+    });
+    Ok(groups)
+}
+
+fn candidate_ids(connection: &mut DbConnection, table: &str, user_id: Uuid, window_days: i64) -> Result<Vec<Uuid>, diesel::result::Error> {
+    Ok(diesel::sql_query(CANDIDATES_SQL.replace("{table}", table))
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<BigInt, _>(window_days)
+        .load::<DuplicateCandidateId>(connection)?
+        .into_iter()
+        .map(|row| row.id)
+        .collect())
+}
+
+/// Cluster rows of the same amount whose dates are within the window and whose names are similar
+fn group_candidates(
+    kind: TransactionKind,
+    rows: Vec<CandidateRow>,
+    window_days: i64,
+) -> Vec<DuplicateGroup> {
+    let mut by_amount: HashMap<Decimal, Vec<DuplicateCandidate>> = HashMap::new();
+    for (id, name, amount, date, description, created_at) in rows {
+        by_amount
+            .entry(amount)
+            .or_default()
+            .push(DuplicateCandidate { id, name, date, description, created_at });
+    }
+
+    let mut groups = Vec::new();
+    for (amount, mut candidates) in by_amount {
+        candidates.sort_by_key(|candidate| (candidate.date, candidate.created_at));
+
+        let mut clusters: Vec<Vec<DuplicateCandidate>> = Vec::new();
+        for candidate in candidates {
+            let cluster = clusters.iter_mut().find(|cluster| {
+                cluster.iter().any(|member| {
+                    (member.date - candidate.date).num_days().abs() <= window_days
+                        && similar_names(&member.name, &candidate.name)
+                })
+            });
+            match cluster {
+                Some(cluster) => cluster.push(candidate),
+                None => clusters.push(vec![candidate]),
+            }
+        }
+
+        for mut transactions in clusters.into_iter().filter(|cluster| cluster.len() > 1) {
+            transactions.sort_by_key(|candidate| candidate.created_at);
+            groups.push(DuplicateGroup {
+                kind,
+                amount,
+                suggested_keep_id: transactions[0].id,
+                transactions,
+            });
+        }
+    }
+    groups
+}
+
+/// Names match when their normalized words are equal, one starts with the other, or they share most words
+fn similar_names(a: &str, b: &str) -> bool {
+    let a = normalize_name(a);
+    let b = normalize_name(b);
+    let a_words: Vec<&str> = a.split(' ').filter(|word| !word.is_empty()).collect();
+    let b_words: Vec<&str> = b.split(' ').filter(|word| !word.is_empty()).collect();
+    if a_words.is_empty() || b_words.is_empty() {
+        return false;
+    }
+    if a_words.starts_with(&b_words) || b_words.starts_with(&a_words) {
+        return true;
+    }
+
+    let a_set: HashSet<&str> = a_words.into_iter().collect();
+    let b_set: HashSet<&str> = b_words.into_iter().collect();
+    let shared = a_set.intersection(&b_set).count();
+    let total = a_set.union(&b_set).count();
+    shared * 2 >= total
+}
+
+/// Keep one transaction, fold the descriptions and attachments of the others into it and delete them
+pub fn merge_duplicates(connection: &mut DbConnection, user_id: Uuid, request: MergeDuplicatesRequest) -> Result<MergeDuplicatesResult, AppError> {
+    let mut merge_ids = request.merge_ids;
+    merge_ids.sort();
+    merge_ids.dedup();
+    if merge_ids.is_empty() {
+        return Err(AppError::Validation("'merge_ids' must not be empty".to_string()));
+    }
+    if merge_ids.contains(&request.keep_id) {
+        return Err(AppError::Validation("'merge_ids' must not contain 'keep_id'".to_string()));
+    }
+
+    connection.transaction(|connection| {
+        let now = Utc::now().naive_utc();
+        let kept = match request.kind {
+            TransactionKind::Income => {
+                let keep = incomes::table
+                    .filter(incomes::id.eq(request.keep_id))
+                    .filter(incomes::user_id.eq(user_id))
+                    .select(Income::as_select())
+                    .first::<Income>(connection)?;
+                let others = incomes::table
+                    .filter(incomes::id.eq_any(&merge_ids))
+                    .filter(incomes::user_id.eq(user_id))
+                    .select(Income::as_select())
+                    .load::<Income>(connection)?;
+                if others.len() != merge_ids.len() {
+                    return Err(AppError::NotFound("Some incomes to merge were not found".to_string()));
+                }
+
+                let description = fold_descriptions(keep.description, others.into_iter().map(|income| income.description));
+                diesel::update(attachments::table.filter(attachments::income_id.eq_any(&merge_ids)))
+                    .set(attachments::income_id.eq(request.keep_id))
+                    .execute(connection)?;
+                diesel::delete(incomes::table.filter(incomes::id.eq_any(&merge_ids)))
+                    .execute(connection)?;
+                let income = diesel::update(incomes::table.find(request.keep_id))
+                    .set((incomes::description.eq(description), incomes::updated_at.eq(now)))
+                    .get_result::<Income>(connection)?;
+                Transaction::Income(income)
+            }
+            TransactionKind::Expense => {
+                let keep = expenses::table
+                    .filter(expenses::id.eq(request.keep_id))
+                    .filter(expenses::user_id.eq(user_id))
+                    .select(Expense::as_select())
+                    .first::<Expense>(connection)?;
+                let others = expenses::table
+                    .filter(expenses::id.eq_any(&merge_ids))
+                    .filter(expenses::user_id.eq(user_id))
+                    .select(Expense::as_select())
+                    .load::<Expense>(connection)?;
+                if others.len() != merge_ids.len() {
+                    return Err(AppError::NotFound("Some expenses to merge were not found".to_string()));
+                }
+
+                let description = fold_descriptions(keep.description, others.into_iter().map(|expense| expense.description));
+                diesel::update(attachments::table.filter(attachments::expense_id.eq_any(&merge_ids)))
+                    .set(attachments::expense_id.eq(request.keep_id))
+                    .execute(connection)?;
+                diesel::delete(expenses::table.filter(expenses::id.eq_any(&merge_ids)))
+                    .execute(connection)?;
+                let expense = diesel::update(expenses::table.find(request.keep_id))
+                    .set((expenses::description.eq(description), expenses::updated_at.eq(now)))
+                    .get_result::<Expense>(connection)?;
+                let expense = expense_service::attach_details(connection, vec![expense])?
+                    .pop()
+                    .ok_or_else(|| AppError::InternalServer("Merged expense disappeared".to_string()))?;
+                Transaction::Expense(expense)
+            }
+        };
+
+        Ok(MergeDuplicatesResult { kept, merged_count: merge_ids.len() })
+    })
+}
+
+/// Append the other descriptions that add something, one per line
+fn fold_descriptions(kept: Option<String>, others: impl Iterator<Item = Option<String>>) -> Option<String> {
+    let mut lines: Vec<String> = kept.into_iter().filter(|text| !text.trim().is_empty()).collect();
+    for text in others.flatten() {
+        let text = text.trim();
+        if !text.is_empty() && !lines.iter().any(|line| line.contains(text)) {
+            lines.push(text.to_string());
+        }
+    }
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
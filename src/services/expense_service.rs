@@ -1,54 +1,402 @@
 use diesel::prelude::*;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
+use rust_decimal::Decimal;
 
-use crate::models::expense::{Expense, NewExpense, UpdateExpense};
-use crate::models::schema::expenses;
+use crate::config::errors::AppError;
+use crate::models::expense::{BulkExpenseRequest, BulkExpenseResult, Expense, ExpenseBulkItem, ExpenseBulkOperation, ExpenseQuery, ExpenseSortField, ExpenseSplit, ExpenseWithSplits, NewExpense, NewExpenseSplit, UpdateExpense};
+use crate::models::insight::AnomalyKind;
+use crate::models::pagination::{Cursor, CursorDirection, Paginated, SortOrder};
+use crate::models::import::ApiBatch;
+use crate::models::schema::{expenses, expense_splits};
 use crate::database::db_connection::DbConnection;
+use crate::models::transaction::TransactionKind;
+use crate::services::{anomaly_service, attachment_service, import_service, payee_service};
+use crate::services::bulk_service::{self, BulkSuccess};
+use crate::services::cursor;
+use crate::services::rule_service::{RuleSet, RuleSetCache};
+use crate::services::export_service::EXPORT_BATCH_SIZE;
+use crate::services::query_filters::{contains_pattern, exact_pattern, fetch_order, keyset_condition, keyset_params, validate_list_params};
+use crate::services::validation::validate_transaction_date;
 
-pub fn get_all_expenses(connection: &mut DbConnection) -> Result<Vec<Expense>, diesel::result::Error> {
-    expenses::table
-        .select(Expense::as_select())
-        .load::<Expense>(connection)
+pub fn get_all_expenses(connection: &mut DbConnection, query: &ExpenseQuery) -> Result<Paginated<ExpenseWithSplits>, AppError> {
+    list_expenses(connection, None, query)
+}
+
+pub fn get_expenses_by_user_id(connection: &mut DbConnection, user_id: Uuid, query: &ExpenseQuery) -> Result<Paginated<ExpenseWithSplits>, AppError> {
+    list_expenses(connection, Some(user_id), query)
 }
 
-pub fn get_expenses_by_user_id(connection: &mut DbConnection, user_id: Uuid) -> Result<Vec<Expense>, diesel::result::Error> {
-    expenses::table
-        .filter(expenses::user_id.eq(user_id))
+fn list_expenses(connection: &mut DbConnection, user_id: Option<Uuid>, query: &ExpenseQuery) -> Result<Paginated<ExpenseWithSplits>, AppError> {
+    validate_list_params(query.from, query.to, query.min_amount, query.max_amount, query.limit, query.offset)?;
+
+    let total_count = filtered_expenses(user_id, query)
+        .count()
+        .get_result::<i64>(connection)?;
+
+    let order = query.order.unwrap_or_default();
+    let sort = query.sort.unwrap_or_default();
+    let keyset = keyset_params(query.cursor.as_deref(), matches!(sort, ExpenseSortField::Date), order, query.limit, query.offset)?;
+    if let Some((cursor, limit)) = keyset {
+        let mut statement = filtered_expenses(user_id, query);
+        if let Some(cursor) = &cursor {
+            statement = statement.filter(keyset_condition("expenses", cursor, order));
+        }
+        statement = match fetch_order(cursor.as_ref(), order) {
+            SortOrder::Asc => statement.order((expenses::date.asc(), expenses::id.asc())),
+            SortOrder::Desc => statement.order((expenses::date.desc(), expenses::id.desc())),
+        };
+
+        let mut expenses = statement
+            .limit(limit + 1)
+            .select(Expense::as_select())
+            .load::<Expense>(connection)?;
+        let (next_cursor, prev_cursor) = cursor::finish_page(&mut expenses, limit, cursor.as_ref(), order, |expense| (expense.date, expense.id));
+        let items = attach_details(connection, expenses)?;
+        return Ok(Paginated { items, total_count, next_cursor, prev_cursor });
+    }
+
+    let mut statement = match (sort, order) {
+        (ExpenseSortField::Date, SortOrder::Asc) => filtered_expenses(user_id, query).order(expenses::date.asc()),
+        (ExpenseSortField::Date, SortOrder::Desc) => filtered_expenses(user_id, query).order(expenses::date.desc()),
+        (ExpenseSortField::Amount, SortOrder::Asc) => filtered_expenses(user_id, query).order(expenses::amount.asc()),
+        (ExpenseSortField::Amount, SortOrder::Desc) => filtered_expenses(user_id, query).order(expenses::amount.desc()),
+        (ExpenseSortField::ItemName, SortOrder::Asc) => filtered_expenses(user_id, query).order(expenses::item_name.asc()),
+        (ExpenseSortField::ItemName, SortOrder::Desc) => filtered_expenses(user_id, query).order(expenses::item_name.desc()),
+        (ExpenseSortField::CreatedAt, SortOrder::Asc) => filtered_expenses(user_id, query).order(expenses::created_at.asc()),
+        (ExpenseSortField::CreatedAt, SortOrder::Desc) => filtered_expenses(user_id, query).order(expenses::created_at.desc()),
+    };
+    // Tie-break on the primary key so pages are stable
+    statement = match order {
+        SortOrder::Asc => statement.then_order_by(expenses::id.asc()),
+        SortOrder::Desc => statement.then_order_by(expenses::id.desc()),
+    };
+
+    if let Some(limit) = query.limit {
+        statement = statement.limit(limit);
+    }
+    if let Some(offset) = query.offset {
+        statement = statement.offset(offset);
+    }
+
+    let expenses = statement
         .select(Expense::as_select())
-        .load::<Expense>(connection)
+        .load::<Expense>(connection)?;
+    let items = attach_details(connection, expenses)?;
+
+    Ok(Paginated { items, total_count, next_cursor: None, prev_cursor: None })
 }
 
-pub fn create_expense(connection: &mut DbConnection, new_expense: NewExpense) -> Result<Expense, diesel::result::Error> {
-    let now = Utc::now().naive_utc();
-    let expense = diesel::insert_into(expenses::table)
-        .values((
-            expenses::id.eq(Uuid::new_v4()),
-            expenses::user_id.eq(new_expense.user_id),
-            expenses::item_name.eq(new_expense.item_name),
-            expenses::amount.eq(new_expense.amount),
-            expenses::date.eq(now.date()),
-            expenses::description.eq(new_expense.description),
-            expenses::created_at.eq(now),
-            expenses::updated_at.eq(now),
-        ))
-        .get_result::<Expense>(connection)?;
+/// Create an expense, running the given rules on its name and description first.
+/// Pass `None` when the caller already ran the user's rules on it.
+pub fn create_expense_with(connection: &mut DbConnection, mut new_expense: NewExpense, rules: Option<&RuleSet>) -> Result<ExpenseWithSplits, AppError> {
+    let date = new_expense.date.unwrap_or_else(|| Utc::now().date_naive());
+    validate_transaction_date(date)?;
+    validate_splits(new_expense.amount, &new_expense.splits)?;
+    if let Some(rules) = rules {
+        rules.apply(TransactionKind::Expense, &mut new_expense.item_name, new_expense.amount, &mut new_expense.description);
+    }
+
+    let result = connection.transaction(|connection| {
+        let payee_id = match new_expense.payee_id {
+            Some(payee_id) => {
+                payee_service::ensure_user_payee(connection, new_expense.user_id, payee_id)?;
+                Some(payee_id)
+            }
+            None => payee_service::resolve_payee(connection, new_expense.user_id, &new_expense.item_name)?,
+        };
+
+        let now = Utc::now().naive_utc();
+        let expense = diesel::insert_into(expenses::table)
+            .values((
+                expenses::id.eq(Uuid::new_v4()),
+                expenses::user_id.eq(new_expense.user_id),
+                expenses::item_name.eq(new_expense.item_name),
+                expenses::amount.eq(new_expense.amount),
+                expenses::date.eq(date),
+                expenses::description.eq(new_expense.description),
+                expenses::created_at.eq(now),
+                expenses::updated_at.eq(now),
+                expenses::payee_id.eq(payee_id),
+                expenses::import_batch_id.eq(new_expense.import_batch_id),
+                expenses::external_account.eq(new_expense.external_account),
+                expenses::external_id.eq(new_expense.external_id),
+            ))
+            .get_result::<Expense>(connection)?;
+
+        let splits = insert_splits(connection, expense.id, new_expense.splits)?;
+        let anomalies = anomaly_flags(connection, expense.id)?;
+        Ok::<_, AppError>(ExpenseWithSplits { expense, splits, anomalies })
+    })?;
 
-    Ok(expense)
+    Ok(result)
 }
 
-pub fn update_expense(connection: &mut DbConnection, expense_id: Uuid, mut update_expense: UpdateExpense) -> Result<Expense, diesel::result::Error> {
+/// Create an expense as part of the client's batch from the `X-Batch-Id` header, if any
+pub fn create_expense_in_batch(connection: &mut DbConnection, new_expense: NewExpense, batch: Option<ApiBatch>) -> Result<ExpenseWithSplits, AppError> {
+    let rules = RuleSet::load(connection, new_expense.user_id)?;
+    create_expense_in_batch_with(connection, new_expense, batch, &rules)
+}
+
+fn create_expense_in_batch_with(
+    connection: &mut DbConnection,
+    mut new_expense: NewExpense,
+    batch: Option<ApiBatch>,
+    rules: &RuleSet,
+) -> Result<ExpenseWithSplits, AppError> {
+    let Some(batch) = batch else {
+        return create_expense_with(connection, new_expense, Some(rules));
+    };
+
+    connection.transaction(|connection| {
+        import_service::join_api_batch(connection, batch, new_expense.user_id)?;
+        new_expense.import_batch_id = Some(batch.id);
+        create_expense_with(connection, new_expense, Some(rules))
+    })
+}
+
+pub fn update_expense(connection: &mut DbConnection, expense_id: Uuid, mut update_expense: UpdateExpense) -> Result<ExpenseWithSplits, AppError> {
     connection.transaction(|connection| {
+        let current = expenses::table
+            .find(expense_id)
+            .select(Expense::as_select())
+            .first::<Expense>(connection)?;
+        let amount = update_expense.amount.unwrap_or(current.amount);
+        if let Some(date) = update_expense.date {
+            validate_transaction_date(date)?;
+        }
+        if let Some(payee_id) = update_expense.payee_id {
+            payee_service::ensure_user_payee(connection, current.user_id, payee_id)?;
+        }
+
+        let splits = match update_expense.splits.take() {
+            Some(new_splits) => {
+                validate_splits(amount, &new_splits)?;
+                diesel::delete(expense_splits::table.filter(expense_splits::expense_id.eq(expense_id)))
+                    .execute(connection)?;
+                insert_splits(connection, expense_id, new_splits)?
+            }
+            None => {
+                let existing = ExpenseSplit::belonging_to(&current)
+                    .select(ExpenseSplit::as_select())
+                    .load::<ExpenseSplit>(connection)?;
+                if !existing.is_empty() {
+                    let total: Decimal = existing.iter().map(|split| split.amount).sum();
+                    if total != amount {
+                        return Err(AppError::Validation(format!(
+                            "Existing line items add up to {} but the expense amount is {}, update the splits as well",
+                            total, amount
+                        )));
+                    }
+                }
+                existing
+            }
+        };
+
         update_expense.updated_at = Some(Utc::now().naive_utc());
-        diesel::update(expenses::table.find(expense_id))
+        let expense = diesel::update(expenses::table.find(expense_id))
             .set(update_expense)
-            .get_result(connection)
+            .get_result::<Expense>(connection)?;
+        let anomalies = anomaly_flags(connection, expense.id)?;
+
+        Ok(ExpenseWithSplits { expense, splits, anomalies })
     })
 }
 
-pub fn delete_expense(connection: &mut DbConnection, expense_id: Uuid) -> Result<Expense, diesel::result::Error> {
+/// Delete an expense, also returning the storage keys of its attachments for the caller to remove
+pub fn delete_expense(connection: &mut DbConnection, expense_id: Uuid) -> Result<(Expense, Vec<String>), diesel::result::Error> {
     connection.transaction(|connection| {
-        diesel::delete(expenses::table.find(expense_id))
-            .get_result(connection)
+        // Locking the expense keeps attachments from being added until it is gone
+        expenses::table.find(expense_id).select(expenses::id).for_update().first::<Uuid>(connection)?;
+        let storage_keys = attachment_service::get_storage_keys(connection, &[expense_id], &[])?;
+        let expense = diesel::delete(expenses::table.find(expense_id))
+            .get_result(connection)?;
+        Ok((expense, storage_keys))
     })
-}
\ No newline at end of file
+}
+
+/// The next batch of a user's filtered expenses in `(date, id)` order, starting after `after`.
+/// Sorting and paging parameters of the query are ignored.
+pub fn export_expenses(connection: &mut DbConnection, user_id: Uuid, query: &ExpenseQuery, after: Option<(NaiveDate, Uuid)>) -> Result<Vec<Expense>, AppError> {
+    validate_list_params(query.from, query.to, query.min_amount, query.max_amount, None, None)?;
+
+    let order = query.order.unwrap_or_default();
+    let mut statement = filtered_expenses(Some(user_id), query);
+    if let Some((date, id)) = after {
+        let cursor = Cursor { date, id, direction: CursorDirection::Next, order };
+        statement = statement.filter(keyset_condition("expenses", &cursor, order));
+    }
+    statement = match order {
+        SortOrder::Asc => statement.order((expenses::date.asc(), expenses::id.asc())),
+        SortOrder::Desc => statement.order((expenses::date.desc(), expenses::id.desc())),
+    };
+
+    let rows = statement
+        .limit(EXPORT_BATCH_SIZE)
+        .select(Expense::as_select())
+        .load::<Expense>(connection)?;
+    Ok(rows)
+}
+
+/// Run a batch of creates, updates and deletes, creates joining the `X-Batch-Id` batch if given.
+/// Also returns the storage keys of the attachments of deleted expenses for the caller to remove.
+pub fn bulk_expenses(connection: &mut DbConnection, request: BulkExpenseRequest, batch: Option<ApiBatch>) -> Result<(BulkExpenseResult, Vec<String>), AppError> {
+    let mut rule_sets = RuleSetCache::default();
+    let outcomes = bulk_service::run_bulk(connection, request.mode, request.operations, |connection, operation| {
+        match operation {
+            ExpenseBulkOperation::Create { data } => {
+                let rules = rule_sets.get(connection, data.user_id)?;
+                let expense = create_expense_in_batch_with(connection, data, batch, rules)?;
+                Ok(BulkSuccess { status: 201, id: expense.expense.id, record: Some(expense), storage_keys: Vec::new() })
+            }
+            ExpenseBulkOperation::Update { id, data } => {
+                let expense = update_expense(connection, id, data).map_err(|e| expense_not_found(e, id))?;
+                Ok(BulkSuccess { status: 200, id, record: Some(expense), storage_keys: Vec::new() })
+            }
+            ExpenseBulkOperation::Delete { id } => {
+                let (_, storage_keys) = delete_expense(connection, id).map_err(|e| expense_not_found(e.into(), id))?;
+                Ok(BulkSuccess { status: 200, id, record: None, storage_keys })
+            }
+        }
+    })?;
+
+    let mut storage_keys = Vec::new();
+    let results: Vec<ExpenseBulkItem> = outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(index, outcome)| match outcome {
+            Ok(success) => {
+                storage_keys.extend(success.storage_keys);
+                ExpenseBulkItem { index, status: success.status, id: Some(success.id), expense: success.record, error: None }
+            }
+            Err(error) => {
+                let (status, message) = bulk_service::failure(&error);
+                ExpenseBulkItem { index, status, id: None, expense: None, error: Some(message) }
+            }
+        })
+        .collect();
+    let failed = results.iter().filter(|item| item.error.is_some()).count();
+
+    Ok((BulkExpenseResult { succeeded: results.len() - failed, failed, results }, storage_keys))
+}
+
+fn expense_not_found(error: AppError, expense_id: Uuid) -> AppError {
+    match error {
+        AppError::NotFound(_) => AppError::NotFound(format!("Expense {} not found", expense_id)),
+        error => error,
+    }
+}
+
+fn filtered_expenses(user_id: Option<Uuid>, query: &ExpenseQuery) -> expenses::BoxedQuery<'static, diesel::pg::Pg> {
+    let mut statement = expenses::table.into_boxed();
+
+    if let Some(user_id) = user_id {
+        statement = statement.filter(expenses::user_id.eq(user_id));
+    }
+    if let Some(from) = query.from {
+        statement = statement.filter(expenses::date.ge(from));
+    }
+    if let Some(to) = query.to {
+        statement = statement.filter(expenses::date.le(to));
+    }
+    if let Some(min_amount) = query.min_amount {
+        statement = statement.filter(expenses::amount.ge(min_amount));
+    }
+    if let Some(max_amount) = query.max_amount {
+        statement = statement.filter(expenses::amount.le(max_amount));
+    }
+    if let Some(search) = query.search.as_deref().filter(|search| !search.trim().is_empty()) {
+        let pattern = contains_pattern(search);
+        statement = statement.filter(
+            expenses::item_name.ilike(pattern.clone())
+                .or(expenses::description.ilike(pattern)),
+        );
+    }
+    if let Some(label) = &query.split_label {
+        statement = statement.filter(
+            expenses::id.eq_any(
+                expense_splits::table
+                    .filter(expense_splits::label.ilike(exact_pattern(label)))
+                    .select(expense_splits::expense_id),
+            ),
+        );
+    }
+
+    statement
+}
+
+/// Load the line items and anomaly flags of the given expenses and pair them up
+pub fn attach_details(connection: &mut DbConnection, expenses: Vec<Expense>) -> Result<Vec<ExpenseWithSplits>, diesel::result::Error> {
+    let splits = ExpenseSplit::belonging_to(&expenses)
+        .select(ExpenseSplit::as_select())
+        .load::<ExpenseSplit>(connection)?;
+    let ids: Vec<Uuid> = expenses.iter().map(|expense| expense.id).collect();
+    let mut anomalies = anomaly_service::get_anomaly_flags(connection, &ids)?;
+
+    Ok(splits
+        .grouped_by(&expenses)
+        .into_iter()
+        .zip(expenses)
+        .map(|(splits, expense)| ExpenseWithSplits {
+            anomalies: anomalies.remove(&expense.id).unwrap_or_default(),
+            expense,
+            splits,
+        })
+        .collect())
+}
+
+fn anomaly_flags(connection: &mut DbConnection, expense_id: Uuid) -> Result<Vec<AnomalyKind>, diesel::result::Error> {
+    Ok(anomaly_service::get_anomaly_flags(connection, &[expense_id])?
+        .remove(&expense_id)
+        .unwrap_or_default())
+}
+
+fn insert_splits(connection: &mut DbConnection, expense_id: Uuid, splits: Vec<NewExpenseSplit>) -> Result<Vec<ExpenseSplit>, diesel::result::Error> {
+    if splits.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let now = Utc::now().naive_utc();
+    let rows: Vec<_> = splits
+        .into_iter()
+        .map(|split| (
+            expense_splits::id.eq(Uuid::new_v4()),
+            expense_splits::expense_id.eq(expense_id),
+            expense_splits::label.eq(split.label.trim().to_string()),
+            expense_splits::amount.eq(split.amount),
+            expense_splits::note.eq(split.note),
+            expense_splits::created_at.eq(now),
+            expense_splits::updated_at.eq(now),
+        ))
+        .collect();
+
+    diesel::insert_into(expense_splits::table)
+        .values(&rows)
+        .get_results(connection)
+}
+
+/// Line items are optional, but when present their amounts must add up to the expense amount
+fn validate_splits(amount: Decimal, splits: &[NewExpenseSplit]) -> Result<(), AppError> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+
+    for split in splits {
+        if split.label.trim().is_empty() {
+            return Err(AppError::Validation("Split label must not be empty".to_string()));
+        }
+        if split.amount <= Decimal::ZERO {
+            return Err(AppError::Validation(format!("Split '{}' must have an amount greater than zero", split.label)));
+        }
+    }
+
+    let total: Decimal = splits.iter().map(|split| split.amount).sum();
+    if total != amount {
+        return Err(AppError::Validation(format!(
+            "Split amounts add up to {} but the expense amount is {}",
+            total, amount
+        )));
+    }
+
+    Ok(())
+}
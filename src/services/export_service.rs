@@ -0,0 +1,77 @@
+use std::borrow::Cow;
+
+use crate::models::expense::Expense;
+use crate::models::income::Income;
+use crate::services::csv_format::CsvFormat;
+
+/// Number of rows loaded per query while streaming an export
+pub const EXPORT_BATCH_SIZE: i64 = 500;
+
+const INCOME_COLUMNS: [&str; 8] = ["id", "date", "source", "amount", "description", "payer_id", "created_at", "updated_at"];
+const EXPENSE_COLUMNS: [&str; 8] = ["id", "date", "item_name", "amount", "description", "payee_id", "created_at", "updated_at"];
+
+pub fn income_csv(format: &CsvFormat, incomes: &[Income], with_header: bool) -> String {
+    let mut out = String::new();
+    if with_header {
+        format.write_record(&mut out, INCOME_COLUMNS);
+    }
+    for income in incomes {
+        format.write_record(&mut out, [
+            income.id.to_string().as_str(),
+            &income.date.to_string(),
+            &text_cell(&income.source),
+            &format.decimal(income.amount),
+            &text_cell(income.description.as_deref().unwrap_or_default()),
+            &income.payer_id.map(|id| id.to_string()).unwrap_or_default(),
+            &income.created_at.to_string(),
+            &income.updated_at.to_string(),
+        ]);
+    }
+    out
+}
+
+pub fn expense_csv(format: &CsvFormat, expenses: &[Expense], with_header: bool) -> String {
+    let mut out = String::new();
+    if with_header {
+        format.write_record(&mut out, EXPENSE_COLUMNS);
+    }
+    for expense in expenses {
+        format.write_record(&mut out, [
+            expense.id.to_string().as_str(),
+            &expense.date.to_string(),
+            &text_cell(&expense.item_name),
+            &format.decimal(expense.amount),
+            &text_cell(expense.description.as_deref().unwrap_or_default()),
+            &expense.payee_id.map(|id| id.to_string()).unwrap_or_default(),
+            &expense.created_at.to_string(),
+            &expense.updated_at.to_string(),
+        ]);
+    }
+    out
+}
+
+/// Free text for a CSV cell, with a `'` in front of text a spreadsheet would run as a formula
+fn text_cell(value: &str) -> Cow<'_, str> {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", value))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_cell_defuses_formulas() {
+        assert_eq!(text_cell("=HYPERLINK(\"http://x\")"), "'=HYPERLINK(\"http://x\")");
+        assert_eq!(text_cell("+49 30 1234"), "'+49 30 1234");
+        assert_eq!(text_cell("-refund"), "'-refund");
+        assert_eq!(text_cell("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(text_cell("\t=1+2"), "'\t=1+2");
+        assert_eq!(text_cell("\r=1+2"), "'\r=1+2");
+        assert_eq!(text_cell("Groceries = food"), "Groceries = food");
+        assert_eq!(text_cell(""), "");
+    }
+}
@@ -0,0 +1,404 @@
+use chrono::{Duration, Months, NaiveDate, Utc};
+use diesel::dsl::sum;
+use diesel::prelude::*;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::forecast::{
+    Cadence, CashFlowForecast, ForecastDay, ForecastQuery, RecurringTransaction, VariableSpending,
+};
+use crate::models::schema::{expenses, incomes};
+use crate::models::transaction::TransactionKind;
+use crate::database::db_connection::DbConnection;
+use crate::services::payee_service::normalize_name;
+
+const DEFAULT_FORECAST_DAYS: i64 = 30;
+const MAX_FORECAST_DAYS: i64 = 365;
+/// How far back to look for repeating transactions, long enough to see a yearly one twice
+const RECURRENCE_LOOKBACK_DAYS: i64 = 730;
+/// Window for the average of variable spending
+const VARIABLE_SPENDING_DAYS: i64 = 90;
+/// Latest occurrences whose median is the projected amount of a recurring transaction
+const RECURRING_AMOUNT_SAMPLE: usize = 3;
+
+struct Entry {
+    kind: TransactionKind,
+    name: String,
+    date: NaiveDate,
+    amount: Decimal,
+}
+
+impl Cadence {
+    fn from_interval(days: i64) -> Option<Self> {
+        match days {
+            6..=8 => Some(Cadence::Weekly),
+            13..=16 => Some(Cadence::Biweekly),
+            27..=33 => Some(Cadence::Monthly),
+            350..=380 => Some(Cadence::Yearly),
+            _ => None,
+        }
+    }
+
+    fn min_occurrences(&self) -> usize {
+        match self {
+            Cadence::Yearly => 2,
+            _ => 3,
+        }
+    }
+
+    /// The `n`th occurrence after `anchor`, months are added from the anchor so the day of month does not drift
+    fn occurrence(&self, anchor: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self {
+            Cadence::Weekly => anchor.checked_add_signed(Duration::weeks(n as i64)),
+            Cadence::Biweekly => anchor.checked_add_signed(Duration::weeks(2 * n as i64)),
+            Cadence::Monthly => anchor.checked_add_months(Months::new(n)),
+            Cadence::Yearly => anchor.checked_add_months(Months::new(12 * n)),
+        }
+    }
+
+    fn approximate_days(&self) -> i64 {
+        match self {
+            Cadence::Weekly => 7,
+            Cadence::Biweekly => 14,
+            Cadence::Monthly => 30,
+            Cadence::Yearly => 365,
+        }
+    }
+}
+
+/// Project the user's balance day by day from recurring transactions, transactions already
+/// entered for future dates and the trailing average of everything else they spend
+pub fn get_forecast(connection: &mut DbConnection, user_id: Uuid, query: &ForecastQuery) -> Result<CashFlowForecast, AppError> {
+    let days = query.days.unwrap_or(DEFAULT_FORECAST_DAYS);
+    if !(1..=MAX_FORECAST_DAYS).contains(&days) {
+        return Err(AppError::BadRequest(format!("'days' must be between 1 and {}", MAX_FORECAST_DAYS)));
+    }
+
+    let today = Utc::now().date_naive();
+    let horizon = today + Duration::days(days);
+    let starting_balance = balance_on(connection, user_id, today)?;
+    let entries = load_entries(connection, user_id, today - Duration::days(RECURRENCE_LOOKBACK_DAYS), horizon)?;
+
+    // Patterns come from history only, transactions entered for future dates are scheduled as they are
+    let mut groups: HashMap<(TransactionKind, String), Vec<&Entry>> = HashMap::new();
+    let mut scheduled: HashMap<NaiveDate, (Decimal, Decimal)> = HashMap::new();
+    let mut entered_until: HashMap<(TransactionKind, String), NaiveDate> = HashMap::new();
+    for entry in &entries {
+        let key = (entry.kind, normalize_name(&entry.name));
+        if entry.date > today {
+            add_flow(&mut scheduled, entry.kind, entry.date, entry.amount);
+            entered_until.insert(key, entry.date);
+        } else if !key.1.is_empty() {
+            groups.entry(key).or_default().push(entry);
+        }
+    }
+
+    let mut recurring = Vec::new();
+    let mut recurring_expense_names = Vec::new();
+    for (key, group) in &groups {
+        if let Some(transaction) = detect_recurring(key.0, group, today) {
+            // Occurrences up to the last one already entered are in the schedule
+            let after = entered_until.get(key).copied().unwrap_or(today);
+            project_recurring(&mut scheduled, &transaction, after, horizon);
+            if key.0 == TransactionKind::Expense {
+                recurring_expense_names.push(key.1.clone());
+            }
+            recurring.push(transaction);
+        }
+    }
+    recurring.sort_by_key(|transaction| transaction.next_date);
+
+    let variable_spending = variable_spending(&groups, &recurring_expense_names, today);
+    let daily_variable_spending: Decimal = variable_spending.iter().map(|item| item.daily_average).sum();
+
+    let mut balance = starting_balance;
+    let series = (1..=days)
+        .map(|offset| {
+            let date = today + Duration::days(offset);
+            let (income, expenses) = scheduled.get(&date).copied().unwrap_or_default();
+            let expenses = expenses + daily_variable_spending;
+            balance += income - expenses;
+            ForecastDay {
+                date,
+                income: income.round_dp(2),
+                expenses: expenses.round_dp(2),
+                balance: balance.round_dp(2),
+            }
+        })
+        .collect();
+
+    Ok(CashFlowForecast {
+        as_of: today,
+        starting_balance,
+        daily_variable_spending: daily_variable_spending.round_dp(2),
+        recurring,
+        variable_spending: variable_spending
+            .into_iter()
+            .map(|item| VariableSpending { daily_average: item.daily_average.round_dp(2), ..item })
+            .collect(),
+        series,
+    })
+}
+
+fn balance_on(connection: &mut DbConnection, user_id: Uuid, date: NaiveDate) -> Result<Decimal, diesel::result::Error> {
+    let income = incomes::table
+        .filter(incomes::user_id.eq(user_id))
+        .filter(incomes::date.le(date))
+        .select(sum(incomes::amount))
+        .first::<Option<Decimal>>(connection)?
+        .unwrap_or(Decimal::ZERO);
+    let spent = expenses::table
+        .filter(expenses::user_id.eq(user_id))
+        .filter(expenses::date.le(date))
+        .select(sum(expenses::amount))
+        .first::<Option<Decimal>>(connection)?
+        .unwrap_or(Decimal::ZERO);
+
+    Ok(income - spent)
+}
+
+fn load_entries(connection: &mut DbConnection, user_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<Entry>, diesel::result::Error> {
+    let income_rows = incomes::table
+        .filter(incomes::user_id.eq(user_id))
+        .filter(incomes::date.between(from, to))
+        .order(incomes::date.asc())
+        .select((incomes::source, incomes::date, incomes::amount))
+        .load::<(String, NaiveDate, Decimal)>(connection)?;
+    let expense_rows = expenses::table
+        .filter(expenses::user_id.eq(user_id))
+        .filter(expenses::date.between(from, to))
+        .order(expenses::date.asc())
+        .select((expenses::item_name, expenses::date, expenses::amount))
+        .load::<(String, NaiveDate, Decimal)>(connection)?;
+
+    Ok(income_rows
+        .into_iter()
+        .map(|(name, date, amount)| Entry { kind: TransactionKind::Income, name, date, amount })
+        .chain(
+            expense_rows
+                .into_iter()
+                .map(|(name, date, amount)| Entry { kind: TransactionKind::Expense, name, date, amount }),
+        )
+        .collect())
+}
+
+/// A group repeats when most gaps between its dates match one cadence and it has not stopped.
+/// The group holds the entries up to `today` in date order.
+fn detect_recurring(kind: TransactionKind, group: &[&Entry], today: NaiveDate) -> Option<RecurringTransaction> {
+    let intervals: Vec<i64> = group
+        .windows(2)
+        .map(|pair| (pair[1].date - pair[0].date).num_days())
+        .collect();
+    if intervals.is_empty() {
+        return None;
+    }
+
+    let mut sorted_intervals = intervals.clone();
+    sorted_intervals.sort_unstable();
+    let cadence = Cadence::from_interval(sorted_intervals[sorted_intervals.len() / 2])?;
+    if group.len() < cadence.min_occurrences() {
+        return None;
+    }
+    let regular = intervals
+        .iter()
+        .filter(|interval| Cadence::from_interval(**interval) == Some(cadence))
+        .count();
+    if regular * 3 < intervals.len() * 2 {
+        return None;
+    }
+
+    let last = group.last()?;
+    // Missing two occurrences in a row means it stopped
+    if (today - last.date).num_days() > 2 * cadence.approximate_days() {
+        return None;
+    }
+
+    let mut amounts: Vec<Decimal> = group
+        .iter()
+        .rev()
+        .take(RECURRING_AMOUNT_SAMPLE)
+        .map(|entry| entry.amount)
+        .collect();
+    amounts.sort_unstable();
+
+    Some(RecurringTransaction {
+        kind,
+        name: last.name.clone(),
+        cadence,
+        amount: amounts[amounts.len() / 2],
+        occurrences: group.len(),
+        last_date: last.date,
+        next_date: cadence.occurrence(last.date, 1)?,
+    })
+}
+
+/// Average daily spending per item over the trailing window, leaving out recurring expenses
+fn variable_spending(
+    groups: &HashMap<(TransactionKind, String), Vec<&Entry>>,
+    recurring_expense_names: &[String],
+    today: NaiveDate,
+) -> Vec<VariableSpending> {
+    let window_start = today - Duration::days(VARIABLE_SPENDING_DAYS);
+    let mut spending: Vec<VariableSpending> = groups
+        .iter()
+        .filter(|((kind, key), _)| *kind == TransactionKind::Expense && !recurring_expense_names.contains(key))
+        .filter_map(|((_, key), group)| {
+            let total: Decimal = group
+                .iter()
+                .filter(|entry| entry.date > window_start && entry.date <= today)
+                .map(|entry| entry.amount)
+                .sum();
+            (total > Decimal::ZERO).then(|| VariableSpending {
+                item_name: key.clone(),
+                daily_average: total / Decimal::from(VARIABLE_SPENDING_DAYS),
+            })
+        })
+        .collect();
+    spending.sort_by_key(|item| std::cmp::Reverse(item.daily_average));
+    spending
+}
+
+/// Add the occurrences of a recurring transaction after `after` and up to `horizon` to the schedule
+fn project_recurring(scheduled: &mut HashMap<NaiveDate, (Decimal, Decimal)>, transaction: &RecurringTransaction, after: NaiveDate, horizon: NaiveDate) {
+    let mut n = 0;
+    let mut date = transaction.next_date;
+    while date <= horizon {
+        if date > after {
+            add_flow(scheduled, transaction.kind, date, transaction.amount);
+        }
+        n += 1;
+        match transaction.cadence.occurrence(transaction.next_date, n) {
+            Some(next) => date = next,
+            None => break,
+        }
+    }
+}
+
+fn add_flow(scheduled: &mut HashMap<NaiveDate, (Decimal, Decimal)>, kind: TransactionKind, date: NaiveDate, amount: Decimal) {
+    let (income, expenses) = scheduled.entry(date).or_default();
+    match kind {
+        TransactionKind::Income => *income += amount,
+        TransactionKind::Expense => *expenses += amount,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn entry(kind: TransactionKind, name: &str, date: NaiveDate, amount: &str) -> Entry {
+        Entry { kind, name: name.to_string(), date, amount: amount.parse().unwrap() }
+    }
+
+    fn monthly(name: &str, amounts: &[&str]) -> Vec<Entry> {
+        amounts
+            .iter()
+            .enumerate()
+            .map(|(index, amount)| entry(TransactionKind::Expense, name, day(2024, 1 + index as u32, 1), amount))
+            .collect()
+    }
+
+    #[test]
+    fn cadence_from_interval() {
+        assert_eq!(Cadence::from_interval(5), None);
+        assert_eq!(Cadence::from_interval(7), Some(Cadence::Weekly));
+        assert_eq!(Cadence::from_interval(14), Some(Cadence::Biweekly));
+        assert_eq!(Cadence::from_interval(20), None);
+        assert_eq!(Cadence::from_interval(28), Some(Cadence::Monthly));
+        assert_eq!(Cadence::from_interval(31), Some(Cadence::Monthly));
+        assert_eq!(Cadence::from_interval(90), None);
+        assert_eq!(Cadence::from_interval(366), Some(Cadence::Yearly));
+        assert_eq!(Cadence::from_interval(-30), None);
+    }
+
+    #[test]
+    fn detect_recurring_finds_a_monthly_expense() {
+        let entries = monthly("Rent", &["900.00", "900.00", "950.00", "950.00"]);
+        let group: Vec<&Entry> = entries.iter().collect();
+        let transaction = detect_recurring(TransactionKind::Expense, &group, day(2024, 4, 20)).unwrap();
+
+        assert_eq!(transaction.cadence, Cadence::Monthly);
+        assert_eq!(transaction.amount, "950.00".parse().unwrap());
+        assert_eq!(transaction.occurrences, 4);
+        assert_eq!(transaction.last_date, day(2024, 4, 1));
+        assert_eq!(transaction.next_date, day(2024, 5, 1));
+    }
+
+    #[test]
+    fn detect_recurring_needs_enough_regular_occurrences() {
+        let entries = monthly("Gym", &["30.00", "30.00"]);
+        let group: Vec<&Entry> = entries.iter().collect();
+        assert!(detect_recurring(TransactionKind::Expense, &group, day(2024, 2, 10)).is_none());
+
+        let entries: Vec<Entry> = [day(2024, 1, 1), day(2024, 1, 3), day(2024, 2, 20), day(2024, 3, 1)]
+            .into_iter()
+            .map(|date| entry(TransactionKind::Expense, "Cafe", date, "4.00"))
+            .collect();
+        let group: Vec<&Entry> = entries.iter().collect();
+        assert!(detect_recurring(TransactionKind::Expense, &group, day(2024, 3, 5)).is_none());
+    }
+
+    #[test]
+    fn detect_recurring_finds_a_yearly_income_from_two_occurrences() {
+        let entries = [
+            entry(TransactionKind::Income, "Bonus", day(2023, 3, 15), "1000.00"),
+            entry(TransactionKind::Income, "Bonus", day(2024, 3, 15), "1200.00"),
+        ];
+        let group: Vec<&Entry> = entries.iter().collect();
+        let transaction = detect_recurring(TransactionKind::Income, &group, day(2024, 6, 1)).unwrap();
+
+        assert_eq!(transaction.cadence, Cadence::Yearly);
+        assert_eq!(transaction.next_date, day(2025, 3, 15));
+    }
+
+    #[test]
+    fn detect_recurring_drops_a_stopped_series() {
+        let entries = monthly("Streaming", &["9.99", "9.99", "9.99"]);
+        let group: Vec<&Entry> = entries.iter().collect();
+
+        // Two missed months after the last one on March 1st
+        assert!(detect_recurring(TransactionKind::Expense, &group, day(2024, 4, 25)).is_some());
+        assert!(detect_recurring(TransactionKind::Expense, &group, day(2024, 5, 10)).is_none());
+    }
+
+    #[test]
+    fn variable_spending_averages_the_trailing_window() {
+        let today = day(2024, 4, 30);
+        let entries = [
+            entry(TransactionKind::Expense, "groceries", day(2024, 4, 10), "60.00"),
+            entry(TransactionKind::Expense, "groceries", day(2024, 3, 1), "30.00"),
+            // Before the window
+            entry(TransactionKind::Expense, "groceries", day(2024, 1, 1), "500.00"),
+            entry(TransactionKind::Expense, "cinema", day(2024, 4, 20), "18.00"),
+            entry(TransactionKind::Expense, "rent", day(2024, 4, 1), "900.00"),
+            entry(TransactionKind::Income, "salary", day(2024, 4, 1), "3000.00"),
+        ];
+        let mut groups: HashMap<(TransactionKind, String), Vec<&Entry>> = HashMap::new();
+        for entry in &entries {
+            groups.entry((entry.kind, entry.name.clone())).or_default().push(entry);
+        }
+
+        let spending = variable_spending(&groups, &["rent".to_string()], today);
+        let averages: Vec<(&str, Decimal)> = spending.iter().map(|item| (item.item_name.as_str(), item.daily_average)).collect();
+        assert_eq!(averages, vec![("groceries", Decimal::from(1)), ("cinema", "0.2".parse().unwrap())]);
+    }
+
+    #[test]
+    fn project_recurring_skips_occurrences_already_entered() {
+        let entries = monthly("Rent", &["900.00", "900.00", "900.00"]);
+        let group: Vec<&Entry> = entries.iter().collect();
+        let transaction = detect_recurring(TransactionKind::Expense, &group, day(2024, 3, 20)).unwrap();
+
+        let mut scheduled = HashMap::new();
+        project_recurring(&mut scheduled, &transaction, day(2024, 4, 2), day(2024, 6, 30));
+        let mut dates: Vec<NaiveDate> = scheduled.keys().copied().collect();
+        dates.sort();
+        assert_eq!(dates, vec![day(2024, 5, 1), day(2024, 6, 1)]);
+    }
+}
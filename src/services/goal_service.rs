@@ -0,0 +1,223 @@
+use diesel::prelude::*;
+use uuid::Uuid;
+use chrono::{Datelike, NaiveDate, Utc};
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::goal::{Goal, GoalContribution, GoalProgress, NewGoal, NewGoalContribution, UpdateGoal};
+use crate::models::income::Income;
+use crate::models::schema::{goals, goal_contributions};
+use crate::database::db_connection::DbConnection;
+
+pub fn get_goals_by_user_id(connection: &mut DbConnection, user_id: Uuid) -> Result<Vec<Goal>, diesel::result::Error> {
+    goals::table
+        .filter(goals::user_id.eq(user_id))
+        .order(goals::target_date.asc())
+        .select(Goal::as_select())
+        .load(connection)
+}
+
+pub fn create_goal(connection: &mut DbConnection, new_goal: NewGoal) -> Result<Goal, AppError> {
+    validate_target_amount(new_goal.target_amount)?;
+    if let Some(percent) = new_goal.match_percent {
+        validate_match_percent(percent)?;
+    }
+
+    let now = Utc::now().naive_utc();
+    let goal = diesel::insert_into(goals::table)
+        .values((
+            goals::id.eq(Uuid::new_v4()),
+            goals::user_id.eq(new_goal.user_id),
+            goals::name.eq(new_goal.name),
+            goals::target_amount.eq(new_goal.target_amount),
+            goals::target_date.eq(new_goal.target_date),
+            goals::match_source.eq(new_goal.match_source),
+            goals::match_percent.eq(new_goal.match_percent),
+            goals::description.eq(new_goal.description),
+            goals::created_at.eq(now),
+            goals::updated_at.eq(now),
+        ))
+        .get_result::<Goal>(connection)?;
+
+    Ok(goal)
+}
+
+pub fn update_goal(connection: &mut DbConnection, goal_id: Uuid, mut update_goal: UpdateGoal) -> Result<Goal, AppError> {
+    if let Some(amount) = update_goal.target_amount {
+        validate_target_amount(amount)?;
+    }
+    if let Some(percent) = update_goal.match_percent {
+        validate_match_percent(percent)?;
+    }
+
+    update_goal.updated_at = Some(Utc::now().naive_utc());
+    let goal = diesel::update(goals::table.find(goal_id))
+        .set(update_goal)
+        .get_result(connection)?;
+
+    Ok(goal)
+}
+
+pub fn delete_goal(connection: &mut DbConnection, goal_id: Uuid) -> Result<Goal, diesel::result::Error> {
+    diesel::delete(goals::table.find(goal_id))
+        .get_result(connection)
+}
+
+pub fn get_contributions(connection: &mut DbConnection, goal_id: Uuid) -> Result<Vec<GoalContribution>, diesel::result::Error> {
+    goal_contributions::table
+        .filter(goal_contributions::goal_id.eq(goal_id))
+        .order(goal_contributions::date.desc())
+        .select(GoalContribution::as_select())
+        .load(connection)
+}
+
+/// Record a manual contribution towards a goal
+pub fn add_contribution(connection: &mut DbConnection, goal_id: Uuid, new_contribution: NewGoalContribution) -> Result<GoalContribution, AppError> {
+    if new_contribution.amount <= Decimal::ZERO {
+        return Err(AppError::Validation("Contribution amount must be greater than zero".to_string()));
+    }
+
+    // Make sure the goal exists so we return 404 instead of a foreign key error
+    goals::table.find(goal_id).select(goals::id).first::<Uuid>(connection)?;
+
+    let now = Utc::now().naive_utc();
+    let contribution = diesel::insert_into(goal_contributions::table)
+        .values((
+            goal_contributions::id.eq(Uuid::new_v4()),
+            goal_contributions::goal_id.eq(goal_id),
+            goal_contributions::amount.eq(new_contribution.amount),
+            goal_contributions::date.eq(new_contribution.date),
+            goal_contributions::note.eq(new_contribution.note),
+            goal_contributions::created_at.eq(now),
+            goal_contributions::updated_at.eq(now),
+        ))
+        .get_result::<GoalContribution>(connection)?;
+
+    Ok(contribution)
+}
+
+/// Record contributions for every goal of the income's owner whose matching rule
+/// applies to the income source
+pub fn record_income_contributions(connection: &mut DbConnection, income: &Income) -> Result<Vec<GoalContribution>, diesel::result::Error> {
+    let candidates = goals::table
+        .filter(goals::user_id.eq(income.user_id))
+        .filter(goals::match_source.is_not_null())
+        .select(Goal::as_select())
+        .load::<Goal>(connection)?;
+
+    let source = income.source.to_lowercase();
+    let now = Utc::now().naive_utc();
+    let mut contributions = Vec::new();
+
+    for goal in candidates {
+        let matches = goal
+            .match_source
+            .as_ref()
+            .is_some_and(|pattern| source.contains(&pattern.to_lowercase()));
+        if !matches {
+            continue;
+        }
+
+        let percent = goal.match_percent.unwrap_or(Decimal::ONE_HUNDRED);
+        let amount = (income.amount * percent / Decimal::ONE_HUNDRED).round_dp(2);
+        if amount <= Decimal::ZERO {
+            continue;
+        }
+
+        let contribution = diesel::insert_into(goal_contributions::table)
+            .values((
+                goal_contributions::id.eq(Uuid::new_v4()),
+                goal_contributions::goal_id.eq(goal.id),
+                goal_contributions::income_id.eq(Some(income.id)),
+                goal_contributions::amount.eq(amount),
+                goal_contributions::date.eq(income.date),
+                goal_contributions::created_at.eq(now),
+                goal_contributions::updated_at.eq(now),
+            ))
+            .get_result::<GoalContribution>(connection)?;
+        contributions.push(contribution);
+    }
+
+    Ok(contributions)
+}
+
+/// Replace the contributions recorded for an income after its source, amount or date changed
+pub fn rerecord_income_contributions(connection: &mut DbConnection, income: &Income) -> Result<Vec<GoalContribution>, diesel::result::Error> {
+    diesel::delete(goal_contributions::table.filter(goal_contributions::income_id.eq(income.id))).execute(connection)?;
+    record_income_contributions(connection, income)
+}
+
+pub fn get_goal_progress(connection: &mut DbConnection, goal_id: Uuid) -> Result<GoalProgress, diesel::result::Error> {
+    let goal = goals::table
+        .find(goal_id)
+        .select(Goal::as_select())
+        .first::<Goal>(connection)?;
+
+    let saved_amount = goal_contributions::table
+        .filter(goal_contributions::goal_id.eq(goal_id))
+        .select(diesel::dsl::sum(goal_contributions::amount))
+        .first::<Option<Decimal>>(connection)?
+        .unwrap_or(Decimal::ZERO);
+
+    Ok(calculate_progress(&goal, saved_amount, Utc::now().date_naive()))
+}
+
+fn calculate_progress(goal: &Goal, saved_amount: Decimal, today: NaiveDate) -> GoalProgress {
+    let remaining_amount = (goal.target_amount - saved_amount).max(Decimal::ZERO);
+    let percent_complete = if goal.target_amount > Decimal::ZERO {
+        (saved_amount * Decimal::ONE_HUNDRED / goal.target_amount)
+            .min(Decimal::ONE_HUNDRED)
+            .round_dp(2)
+    } else {
+        Decimal::ONE_HUNDRED
+    };
+
+    let months_remaining = months_until(today, goal.target_date);
+    // Once the target date has passed everything still missing is due immediately
+    let required_monthly_contribution = if months_remaining > 0 {
+        (remaining_amount / Decimal::from(months_remaining)).round_dp(2)
+    } else {
+        remaining_amount
+    };
+
+    GoalProgress {
+        goal_id: goal.id,
+        target_amount: goal.target_amount,
+        saved_amount,
+        remaining_amount,
+        percent_complete,
+        target_date: goal.target_date,
+        months_remaining,
+        required_monthly_contribution,
+    }
+}
+
+/// Number of monthly contributions left before `target`, counting a partial month as a full one
+fn months_until(today: NaiveDate, target: NaiveDate) -> i64 {
+    if target <= today {
+        return 0;
+    }
+
+    let months = (target.year() - today.year()) as i64 * 12
+        + target.month() as i64
+        - today.month() as i64;
+    if target.day() > today.day() {
+        months + 1
+    } else {
+        months.max(1)
+    }
+}
+
+fn validate_target_amount(amount: Decimal) -> Result<(), AppError> {
+    if amount <= Decimal::ZERO {
+        return Err(AppError::Validation("Target amount must be greater than zero".to_string()));
+    }
+    Ok(())
+}
+
+fn validate_match_percent(percent: Decimal) -> Result<(), AppError> {
+    if percent <= Decimal::ZERO || percent > Decimal::ONE_HUNDRED {
+        return Err(AppError::Validation("Match percent must be between 0 and 100".to_string()));
+    }
+    Ok(())
+}
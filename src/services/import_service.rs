@@ -0,0 +1,570 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{NaiveDate, Utc};
+use csv::{ReaderBuilder, StringRecord};
+use diesel::dsl::count;
+use diesel::prelude::*;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::expense::NewExpense;
+use crate::models::export::CsvOptions;
+use crate::models::import::{
+    AmountSign, ApiBatch, CsvColumn, CsvImportMapping, ImportBatch, ImportBatchQuery, ImportBatchWithCounts, ImportResult, ImportRow,
+};
+use crate::models::income::NewIncome;
+use crate::models::schema::{expenses, import_batches, incomes, users};
+use crate::models::transaction::TransactionKind;
+use crate::database::db_connection::DbConnection;
+use crate::services::csv_format::CsvFormat;
+use crate::services::validation::validate_transaction_date;
+use crate::services::rule_service::RuleSet;
+use crate::services::{attachment_service, expense_service, income_service};
+
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d";
+/// Source of batches clients build up with the `X-Batch-Id` header
+pub const API_BATCH_SOURCE: &str = "api";
+
+/// Column positions resolved against the file's header
+struct CsvLayout {
+    date: usize,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    description: usize,
+    memo: Option<usize>,
+}
+
+/// Read a CSV statement into rows, checking each one without touching the database.
+/// Problems with the mapping itself fail the whole file, problems with a row are
+/// reported on that row.
+pub fn parse_csv(data: &[u8], mapping: &CsvImportMapping) -> Result<Vec<ImportRow>, AppError> {
+    let format = CsvFormat::from_options(&CsvOptions {
+        delimiter: mapping.delimiter.clone(),
+        decimal_separator: mapping.decimal_separator.clone(),
+    })?;
+    if !format.delimiter().is_ascii() {
+        return Err(AppError::Validation("Import delimiter must be an ASCII character".to_string()));
+    }
+    let has_amount = mapping.amount_column.is_some();
+    let has_debit_credit = mapping.debit_column.is_some() || mapping.credit_column.is_some();
+    if has_amount == has_debit_credit {
+        return Err(AppError::Validation("Map either amount_column or debit_column and credit_column".to_string()));
+    }
+
+    let text = decode_text(data);
+    let mut reader = ReaderBuilder::new()
+        .delimiter(format.delimiter() as u8)
+        .has_headers(mapping.has_header)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let headers: Option<Vec<String>> = if mapping.has_header {
+        let headers = reader
+            .headers()
+            .map_err(|e| AppError::Validation(format!("Invalid CSV header: {}", e)))?;
+        Some(headers.iter().map(|name| name.trim().to_string()).collect())
+    } else {
+        None
+    };
+    let resolve = |column: &CsvColumn| resolve_column(column, headers.as_deref());
+    let layout = CsvLayout {
+        date: resolve(&mapping.date_column)?,
+        amount: mapping.amount_column.as_ref().map(resolve).transpose()?,
+        debit: mapping.debit_column.as_ref().map(resolve).transpose()?,
+        credit: mapping.credit_column.as_ref().map(resolve).transpose()?,
+        description: resolve(&mapping.description_column)?,
+        memo: mapping.memo_column.as_ref().map(resolve).transpose()?,
+    };
+    let date_format = mapping.date_format.as_deref().unwrap_or(DEFAULT_DATE_FORMAT);
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |position| position.line() as usize);
+                let mut row = ImportRow::new(line);
+                row.errors.push(format!("Malformed CSV: {}", e));
+                rows.push(row);
+                continue;
+            }
+        };
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        rows.push(parse_record(&record, &layout, &format, date_format, mapping.amount_sign));
+    }
+
+    Ok(rows)
+}
+
+fn parse_record(record: &StringRecord, layout: &CsvLayout, format: &CsvFormat, date_format: &str, sign: AmountSign) -> ImportRow {
+    let line = record.position().map_or(0, |position| position.line() as usize);
+    let mut row = ImportRow::new(line);
+    let field = |index: usize| record.get(index).map(str::trim).filter(|value| !value.is_empty());
+
+    match field(layout.date) {
+        Some(value) => match NaiveDate::parse_from_str(value, date_format) {
+            Ok(date) => set_date(&mut row, date),
+            Err(_) => row.errors.push(format!("Invalid date '{}', expected the format {}", value, date_format)),
+        },
+        None => row.errors.push("Missing date".to_string()),
+    }
+
+    let mut amount_errors = Vec::new();
+    let mut amount = |index: Option<usize>, column: &str| -> Option<Decimal> {
+        let value = field(index?)?;
+        let parsed = format.parse_decimal(value);
+        if parsed.is_none() {
+            amount_errors.push(format!("Invalid {} '{}'", column, value));
+        }
+        parsed.filter(|amount| !amount.is_zero())
+    };
+    let signed = match layout.amount {
+        Some(index) => amount(Some(index), "amount").map(|value| match sign {
+            AmountSign::NegativeIsExpense => value,
+            AmountSign::PositiveIsExpense => -value,
+        }),
+        None => match (amount(layout.debit, "debit"), amount(layout.credit, "credit")) {
+            (Some(_), Some(_)) => {
+                amount_errors.push("Row has both a debit and a credit amount".to_string());
+                None
+            }
+            (Some(debit), None) => Some(-debit.abs()),
+            (None, Some(credit)) => Some(credit.abs()),
+            (None, None) => None,
+        },
+    };
+    match signed {
+        Some(value) => set_amount(&mut row, value),
+        None if amount_errors.is_empty() => row.errors.push("Missing or zero amount".to_string()),
+        None => row.errors.append(&mut amount_errors),
+    }
+
+    match field(layout.description) {
+        Some(name) => row.name = Some(name.to_string()),
+        None => row.errors.push("Missing description".to_string()),
+    }
+    row.description = layout.memo.and_then(field).map(str::to_string);
+
+    row
+}
+
+/// Set the row's date, flagging dates too far in the future
+pub fn set_date(row: &mut ImportRow, date: NaiveDate) {
+    if let Err(AppError::Validation(message)) = validate_transaction_date(date) {
+        row.errors.push(message);
+    }
+    row.date = Some(date);
+}
+
+/// Set the row's kind and amount from a signed amount, negative amounts are expenses
+pub fn set_amount(row: &mut ImportRow, signed: Decimal) {
+    if signed.is_zero() {
+        row.errors.push("Amount is zero".to_string());
+        return;
+    }
+    row.kind = Some(if signed.is_sign_negative() { TransactionKind::Expense } else { TransactionKind::Income });
+    row.amount = Some(signed.abs());
+}
+
+fn resolve_column(column: &CsvColumn, headers: Option<&[String]>) -> Result<usize, AppError> {
+    match (column, headers) {
+        (CsvColumn::Index(index), _) => Ok(*index),
+        (CsvColumn::Name(name), Some(headers)) => headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| AppError::Validation(format!("Column '{}' not found in the CSV header", name))),
+        (CsvColumn::Name(_), None) => Err(AppError::Validation(
+            "Columns must be given by position when the file has no header".to_string(),
+        )),
+    }
+}
+
+/// Statements are usually UTF-8, older bank exports are often Latin-1
+pub fn decode_text(data: &[u8]) -> String {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    match std::str::from_utf8(data) {
+        Ok(text) => text.to_string(),
+        Err(_) => data.iter().map(|&byte| byte as char).collect(),
+    }
+}
+
+/// Save the valid rows as incomes and expenses under a new import batch, all or nothing.
+/// Dry runs, and files without any valid rows, only return the checked rows.
+pub fn import_rows(
+    connection: &mut DbConnection,
+    user_id: Uuid,
+    source: &str,
+    file_name: Option<String>,
+    mut rows: Vec<ImportRow>,
+    dry_run: bool,
+) -> Result<ImportResult, AppError> {
+    // Rules run once here, so the preview shows their result and the rows are created as shown
+    let rules = RuleSet::load(connection, user_id)?;
+    for row in rows.iter_mut().filter(|row| row.is_valid()) {
+        if let (Some(kind), Some(name), Some(amount)) = (row.kind, row.name.as_mut(), row.amount) {
+            rules.apply(kind, name, amount, &mut row.description);
+        }
+    }
+
+    let batch_id = if dry_run {
+        mark_duplicates(connection, user_id, &mut rows)?;
+        None
+    } else {
+        connection.transaction(|connection| {
+            // Imports of the same user wait for each other, so a statement uploaded twice
+            // at once sees the rows of the first upload when checking for duplicates
+            users::table.find(user_id).select(users::id).for_no_key_update().first::<Uuid>(connection)?;
+            mark_duplicates(connection, user_id, &mut rows)?;
+            if !rows.iter().any(ImportRow::is_importable) {
+                return Ok(None);
+            }
+
+            let batch = diesel::insert_into(import_batches::table)
+                .values((
+                    import_batches::id.eq(Uuid::new_v4()),
+                    import_batches::user_id.eq(user_id),
+                    import_batches::source.eq(source),
+                    import_batches::file_name.eq(file_name),
+                    import_batches::created_at.eq(Utc::now().naive_utc()),
+                ))
+                .get_result::<ImportBatch>(connection)?;
+
+            for row in rows.iter().filter(|row| row.is_importable()) {
+                let (Some(kind), Some(name), Some(amount)) = (row.kind, row.name.clone(), row.amount) else {
+                    continue;
+                };
+                match kind {
+                    TransactionKind::Income => {
+                        income_service::create_income_with(connection, NewIncome {
+                            user_id,
+                            source: name,
+                            amount,
+                            date: row.date,
+                            description: row.description.clone(),
+                            payer_id: None,
+                            import_batch_id: Some(batch.id),
+                            external_account: row.external_account.clone(),
+                            external_id: row.external_id.clone(),
+                        }, None)?;
+                    }
+                    TransactionKind::Expense => {
+                        expense_service::create_expense_with(connection, NewExpense {
+                            user_id,
+                            item_name: name,
+                            amount,
+                            date: row.date,
+                            description: row.description.clone(),
+                            payee_id: None,
+                            import_batch_id: Some(batch.id),
+                            external_account: row.external_account.clone(),
+                            external_id: row.external_id.clone(),
+                            splits: row.splits.clone(),
+                        }, None)?;
+                    }
+                }
+            }
+
+            Ok::<_, AppError>(Some(batch.id))
+        })?
+    };
+
+    let importable = |kind: TransactionKind| {
+        rows.iter().filter(|row| row.is_importable() && row.kind == Some(kind)).count()
+    };
+    let income_count = importable(TransactionKind::Income);
+    let expense_count = importable(TransactionKind::Expense);
+    let valid_count = income_count + expense_count;
+    let error_count = rows.iter().filter(|row| !row.is_valid()).count();
+    let duplicate_count = rows.len() - valid_count - error_count;
+
+    Ok(ImportResult {
+        batch_id,
+        dry_run,
+        valid_count,
+        error_count,
+        duplicate_count,
+        income_count,
+        expense_count,
+        rows,
+    })
+}
+
+/// Flag rows whose bank reference was imported before for the same account, or appears
+/// earlier in the same file
+fn mark_duplicates(connection: &mut DbConnection, user_id: Uuid, rows: &mut [ImportRow]) -> Result<(), diesel::result::Error> {
+    let references: Vec<String> = rows.iter().filter_map(|row| row.external_id.clone()).collect();
+    if references.is_empty() {
+        return Ok(());
+    }
+
+    let imported: HashSet<(Option<String>, String)> = incomes::table
+        .filter(incomes::user_id.eq(user_id))
+        .filter(incomes::external_id.eq_any(&references))
+        .select((incomes::external_account, incomes::external_id.assume_not_null()))
+        .load::<(Option<String>, String)>(connection)?
+        .into_iter()
+        .chain(
+            expenses::table
+                .filter(expenses::user_id.eq(user_id))
+                .filter(expenses::external_id.eq_any(&references))
+                .select((expenses::external_account, expenses::external_id.assume_not_null()))
+                .load::<(Option<String>, String)>(connection)?,
+        )
+        .collect();
+
+    flag_duplicates(rows, imported);
+    Ok(())
+}
+
+/// Flag valid rows whose `(external_account, external_id)` is among the `imported` ones
+/// or belongs to an earlier row
+pub fn flag_duplicates(rows: &mut [ImportRow], mut imported: HashSet<(Option<String>, String)>) {
+    for row in rows.iter_mut().filter(|row| row.is_valid()) {
+        if let Some(reference) = &row.external_id {
+            row.duplicate = !imported.insert((row.external_account.clone(), reference.clone()));
+        }
+    }
+}
+
+pub fn get_batches(connection: &mut DbConnection, user_id: Uuid, query: &ImportBatchQuery) -> Result<Vec<ImportBatchWithCounts>, diesel::result::Error> {
+    let mut batches = import_batches::table
+        .filter(import_batches::user_id.eq(user_id))
+        .into_boxed();
+    if let Some(source) = &query.source {
+        batches = batches.filter(import_batches::source.eq(source));
+    }
+
+    let batches = batches
+        .order(import_batches::created_at.desc())
+        .select(ImportBatch::as_select())
+        .load::<ImportBatch>(connection)?;
+    let batch_ids: Vec<Uuid> = batches.iter().map(|batch| batch.id).collect();
+
+    let income_counts: HashMap<Option<Uuid>, i64> = incomes::table
+        .filter(incomes::import_batch_id.eq_any(&batch_ids))
+        .group_by(incomes::import_batch_id)
+        .select((incomes::import_batch_id, count(incomes::id)))
+        .load::<(Option<Uuid>, i64)>(connection)?
+        .into_iter()
+        .collect();
+    let expense_counts: HashMap<Option<Uuid>, i64> = expenses::table
+        .filter(expenses::import_batch_id.eq_any(&batch_ids))
+        .group_by(expenses::import_batch_id)
+        .select((expenses::import_batch_id, count(expenses::id)))
+        .load::<(Option<Uuid>, i64)>(connection)?
+        .into_iter()
+        .collect();
+
+    Ok(batches
+        .into_iter()
+        .map(|batch| ImportBatchWithCounts {
+            income_count: income_counts.get(&Some(batch.id)).copied().unwrap_or(0),
+            expense_count: expense_counts.get(&Some(batch.id)).copied().unwrap_or(0),
+            batch,
+        })
+        .collect())
+}
+
+/// Check that a transaction of `user_id` may go into the client's batch, creating the batch
+/// for the signed-in user the first time its id is used. Batches of imported files can't be added to.
+pub fn join_api_batch(connection: &mut DbConnection, batch: ApiBatch, user_id: Uuid) -> Result<(), AppError> {
+    if user_id != batch.user_id {
+        return Err(AppError::Validation(format!("Only the signed-in user's incomes and expenses can be added to batch {}", batch.id)));
+    }
+
+    diesel::insert_into(import_batches::table)
+        .values((
+            import_batches::id.eq(batch.id),
+            import_batches::user_id.eq(batch.user_id),
+            import_batches::source.eq(API_BATCH_SOURCE),
+            import_batches::created_at.eq(Utc::now().naive_utc()),
+        ))
+        .on_conflict(import_batches::id)
+        .do_nothing()
+        .execute(connection)?;
+
+    let existing = import_batches::table
+        .find(batch.id)
+        .select(ImportBatch::as_select())
+        .first::<ImportBatch>(connection)?;
+    if existing.user_id != batch.user_id || existing.source != API_BATCH_SOURCE {
+        return Err(AppError::Validation(format!("Batch {} is already in use and can't be added to", batch.id)));
+    }
+    Ok(())
+}
+
+/// Delete every income and expense an import or API batch created, along with the batch itself.
+/// Also returns the storage keys of their attachments for the caller to remove.
+pub fn undo_batch(connection: &mut DbConnection, user_id: Uuid, batch_id: Uuid) -> Result<(ImportBatchWithCounts, Vec<String>), AppError> {
+    connection.transaction(|connection| {
+        let batch = import_batches::table
+            .find(batch_id)
+            .filter(import_batches::user_id.eq(user_id))
+            .select(ImportBatch::as_select())
+            .first::<ImportBatch>(connection)?;
+
+        // Locked so no attachments are added to them before they are deleted
+        let income_ids = incomes::table
+            .filter(incomes::import_batch_id.eq(batch.id))
+            .select(incomes::id)
+            .for_update()
+            .load::<Uuid>(connection)?;
+        let expense_ids = expenses::table
+            .filter(expenses::import_batch_id.eq(batch.id))
+            .select(expenses::id)
+            .for_update()
+            .load::<Uuid>(connection)?;
+        let storage_keys = attachment_service::get_storage_keys(connection, &expense_ids, &income_ids)?;
+
+        diesel::delete(incomes::table.filter(incomes::import_batch_id.eq(batch.id))).execute(connection)?;
+        diesel::delete(expenses::table.filter(expenses::import_batch_id.eq(batch.id))).execute(connection)?;
+        diesel::delete(import_batches::table.find(batch.id)).execute(connection)?;
+
+        let batch = ImportBatchWithCounts {
+            batch,
+            income_count: income_ids.len() as i64,
+            expense_count: expense_ids.len() as i64,
+        };
+        Ok((batch, storage_keys))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::test_support::amount;
+
+    fn mapping(date: CsvColumn, description: CsvColumn) -> CsvImportMapping {
+        CsvImportMapping {
+            has_header: true,
+            delimiter: None,
+            decimal_separator: None,
+            date_column: date,
+            date_format: None,
+            amount_column: None,
+            amount_sign: AmountSign::default(),
+            debit_column: None,
+            credit_column: None,
+            description_column: description,
+            memo_column: None,
+        }
+    }
+
+    fn named(name: &str) -> Option<CsvColumn> {
+        Some(CsvColumn::Name(name.to_string()))
+    }
+
+    fn amount_mapping() -> CsvImportMapping {
+        let mut mapping = mapping(CsvColumn::Name("Date".to_string()), CsvColumn::Name("Payee".to_string()));
+        mapping.amount_column = named("Amount");
+        mapping
+    }
+
+    #[test]
+    fn parse_csv_resolves_header_names() {
+        let mut mapping = amount_mapping();
+        mapping.memo_column = named(" reference ");
+        let data = "Reference,Payee,Date,Amount\nCard 1234,REWE Markt,2024-03-20,-23.45\n";
+
+        let rows = parse_csv(data.as_bytes(), &mapping).unwrap();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.line, 2);
+        assert_eq!(row.date, NaiveDate::from_ymd_opt(2024, 3, 20));
+        assert_eq!(row.name.as_deref(), Some("REWE Markt"));
+        assert_eq!(row.description.as_deref(), Some("Card 1234"));
+        assert_eq!((row.kind, row.amount), (Some(TransactionKind::Expense), amount("23.45")));
+        assert!(row.errors.is_empty());
+
+        mapping.memo_column = named("Memo");
+        let error = parse_csv(data.as_bytes(), &mapping).unwrap_err();
+        assert!(matches!(error, AppError::Validation(message) if message.contains("'Memo' not found")));
+    }
+
+    #[test]
+    fn parse_csv_resolves_column_positions() {
+        let mut mapping = mapping(CsvColumn::Index(0), CsvColumn::Index(2));
+        mapping.has_header = false;
+        mapping.delimiter = Some(";".to_string());
+        mapping.decimal_separator = Some(",".to_string());
+        mapping.date_format = Some("%d.%m.%Y".to_string());
+        mapping.amount_column = Some(CsvColumn::Index(1));
+
+        let rows = parse_csv("20.03.2024;1.500,00;Acme Corp\n21.03.2024;-7,00;Bakery\n".as_bytes(), &mapping).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].line, rows[0].kind, rows[0].amount), (1, Some(TransactionKind::Income), amount("1500.00")));
+        assert_eq!((rows[1].line, rows[1].kind, rows[1].amount), (2, Some(TransactionKind::Expense), amount("7.00")));
+
+        mapping.date_column = CsvColumn::Name("Date".to_string());
+        assert!(parse_csv(b"20.03.2024;1,00;Acme Corp\n", &mapping).is_err());
+    }
+
+    #[test]
+    fn parse_csv_needs_either_amount_or_debit_and_credit() {
+        let mut mapping = amount_mapping();
+        mapping.debit_column = named("Debit");
+        assert!(parse_csv(b"Date,Payee,Amount,Debit\n", &mapping).is_err());
+
+        mapping.amount_column = None;
+        mapping.debit_column = None;
+        assert!(parse_csv(b"Date,Payee,Amount,Debit\n", &mapping).is_err());
+    }
+
+    #[test]
+    fn parse_csv_applies_the_amount_sign() {
+        let data = "Date,Payee,Amount\n2024-03-20,Coffee,-3.20\n2024-03-21,Refund,3.20\n";
+
+        let rows = parse_csv(data.as_bytes(), &amount_mapping()).unwrap();
+        assert_eq!(rows.iter().map(|row| row.kind).collect::<Vec<_>>(), vec![Some(TransactionKind::Expense), Some(TransactionKind::Income)]);
+
+        let mut mapping = amount_mapping();
+        mapping.amount_sign = AmountSign::PositiveIsExpense;
+        let rows = parse_csv(data.as_bytes(), &mapping).unwrap();
+        assert_eq!(rows.iter().map(|row| row.kind).collect::<Vec<_>>(), vec![Some(TransactionKind::Income), Some(TransactionKind::Expense)]);
+        assert!(rows.iter().all(|row| row.amount == amount("3.20")));
+    }
+
+    #[test]
+    fn parse_csv_reads_debit_and_credit_columns() {
+        let mut mapping = mapping(CsvColumn::Name("Date".to_string()), CsvColumn::Name("Payee".to_string()));
+        mapping.debit_column = named("Debit");
+        mapping.credit_column = named("Credit");
+        let data = "Date,Payee,Debit,Credit\n\
+                    2024-03-20,Rent,-800.00,\n\
+                    2024-03-21,Salary,,1500.00\n\
+                    2024-03-22,Both,5.00,5.00\n\
+                    2024-03-23,Neither,0.00,\n";
+
+        let rows = parse_csv(data.as_bytes(), &mapping).unwrap();
+        assert_eq!((rows[0].kind, rows[0].amount), (Some(TransactionKind::Expense), amount("800.00")));
+        assert_eq!((rows[1].kind, rows[1].amount), (Some(TransactionKind::Income), amount("1500.00")));
+        assert_eq!(rows[2].errors, vec!["Row has both a debit and a credit amount".to_string()]);
+        assert_eq!(rows[3].errors, vec!["Missing or zero amount".to_string()]);
+    }
+
+    #[test]
+    fn parse_csv_reports_row_errors() {
+        let data = "Date,Payee,Amount\n20/03/2024,,0\n2024-03-21,Shop,abc\n";
+
+        let rows = parse_csv(data.as_bytes(), &amount_mapping()).unwrap();
+        assert_eq!(rows[0].errors, vec![
+            "Invalid date '20/03/2024', expected the format %Y-%m-%d".to_string(),
+            "Missing or zero amount".to_string(),
+            "Missing description".to_string(),
+        ]);
+        assert_eq!(rows[1].errors, vec!["Invalid amount 'abc'".to_string()]);
+    }
+
+    #[test]
+    fn parse_csv_decodes_latin1_and_strips_the_bom() {
+        let rows = parse_csv(b"\xEF\xBB\xBFDate,Payee,Amount\n2024-03-20,Caf\xC3\xA9,-3.20\n", &amount_mapping()).unwrap();
+        assert_eq!(rows[0].name.as_deref(), Some("Café"));
+
+        let rows = parse_csv(b"Date,Payee,Amount\n2024-03-20,B\xE4ckerei M\xFCller,-3.20\n", &amount_mapping()).unwrap();
+        assert_eq!(rows[0].name.as_deref(), Some("Bäckerei Müller"));
+    }
+}
@@ -1,63 +1,318 @@
 use diesel::prelude::*;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 use crate::models::user::User;
-use diesel::result::Error;
+use std::collections::HashMap;
 
-use crate::models::income::{Income, NewIncome, UpdateIncome, IncomeWithUser};
+use crate::models::income::{BulkIncomeRequest, BulkIncomeResult, Income, IncomeBulkItem, IncomeBulkOperation, IncomeQuery, IncomeSortField, NewIncome, UpdateIncome, IncomeWithUser};
+use crate::models::pagination::{Cursor, CursorDirection, Paginated, SortOrder};
+use crate::models::import::ApiBatch;
 use crate::models::schema::{incomes, users};
 use crate::database::db_connection::DbConnection;
+use crate::config::errors::AppError;
+use crate::models::transaction::TransactionKind;
+use crate::services::{attachment_service, goal_service, import_service, payee_service};
+use crate::services::bulk_service::{self, BulkSuccess};
+use crate::services::cursor;
+use crate::services::rule_service::{RuleSet, RuleSetCache};
+use crate::services::export_service::EXPORT_BATCH_SIZE;
+use crate::services::query_filters::{contains_pattern, fetch_order, keyset_condition, keyset_params, validate_list_params};
+use crate::services::validation::validate_transaction_date;
 
-pub fn get_all_incomes(connection: &mut DbConnection) -> Result<Vec<IncomeWithUser>, Error> {
-    incomes::table
-        .inner_join(users::table)
-        .select((incomes::all_columns, users::all_columns))
-        .load::<(Income, User)>(connection)
-        .map(|results| {
-            results
-                .into_iter()
-                .map(|(income, user)| IncomeWithUser {
-                    income,
-                    user,
-                })
-                .collect()
+pub fn get_all_incomes(connection: &mut DbConnection, query: &IncomeQuery) -> Result<Paginated<IncomeWithUser>, AppError> {
+    let page = list_incomes(connection, None, query)?;
+
+    let user_ids: Vec<Uuid> = page.items.iter().map(|income| income.user_id).collect();
+    let owners: HashMap<Uuid, User> = users::table
+        .filter(users::id.eq_any(user_ids))
+        .select(User::as_select())
+        .load::<User>(connection)?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let items = page
+        .items
+        .into_iter()
+        .filter_map(|income| {
+            let user = owners.get(&income.user_id)?.clone();
+            Some(IncomeWithUser { income, user })
         })
+        .collect();
+
+    Ok(Paginated {
+        items,
+        total_count: page.total_count,
+        next_cursor: page.next_cursor,
+        prev_cursor: page.prev_cursor,
+    })
+}
+
+pub fn get_incomes_by_user_id(connection: &mut DbConnection, user_id: Uuid, query: &IncomeQuery) -> Result<Paginated<Income>, AppError> {
+    list_incomes(connection, Some(user_id), query)
+}
+
+fn list_incomes(connection: &mut DbConnection, user_id: Option<Uuid>, query: &IncomeQuery) -> Result<Paginated<Income>, AppError> {
+    validate_list_params(query.from, query.to, query.min_amount, query.max_amount, query.limit, query.offset)?;
+
+    let total_count = filtered_incomes(user_id, query)
+        .count()
+        .get_result::<i64>(connection)?;
+
+    let order = query.order.unwrap_or_default();
+    let sort = query.sort.unwrap_or_default();
+    let keyset = keyset_params(query.cursor.as_deref(), matches!(sort, IncomeSortField::Date), order, query.limit, query.offset)?;
+    if let Some((cursor, limit)) = keyset {
+        let mut statement = filtered_incomes(user_id, query);
+        if let Some(cursor) = &cursor {
+            statement = statement.filter(keyset_condition("incomes", cursor, order));
+        }
+        statement = match fetch_order(cursor.as_ref(), order) {
+            SortOrder::Asc => statement.order((incomes::date.asc(), incomes::id.asc())),
+            SortOrder::Desc => statement.order((incomes::date.desc(), incomes::id.desc())),
+        };
+
+        let mut items = statement
+            .limit(limit + 1)
+            .select(Income::as_select())
+            .load::<Income>(connection)?;
+        let (next_cursor, prev_cursor) = cursor::finish_page(&mut items, limit, cursor.as_ref(), order, |income| (income.date, income.id));
+        return Ok(Paginated { items, total_count, next_cursor, prev_cursor });
+    }
+
+    let mut statement = match (sort, order) {
+        (IncomeSortField::Date, SortOrder::Asc) => filtered_incomes(user_id, query).order(incomes::date.asc()),
+        (IncomeSortField::Date, SortOrder::Desc) => filtered_incomes(user_id, query).order(incomes::date.desc()),
+        (IncomeSortField::Amount, SortOrder::Asc) => filtered_incomes(user_id, query).order(incomes::amount.asc()),
+        (IncomeSortField::Amount, SortOrder::Desc) => filtered_incomes(user_id, query).order(incomes::amount.desc()),
+        (IncomeSortField::Source, SortOrder::Asc) => filtered_incomes(user_id, query).order(incomes::source.asc()),
+        (IncomeSortField::Source, SortOrder::Desc) => filtered_incomes(user_id, query).order(incomes::source.desc()),
+        (IncomeSortField::CreatedAt, SortOrder::Asc) => filtered_incomes(user_id, query).order(incomes::created_at.asc()),
+        (IncomeSortField::CreatedAt, SortOrder::Desc) => filtered_incomes(user_id, query).order(incomes::created_at.desc()),
+    };
+    // Tie-break on the primary key so pages are stable
+    statement = match order {
+        SortOrder::Asc => statement.then_order_by(incomes::id.asc()),
+        SortOrder::Desc => statement.then_order_by(incomes::id.desc()),
+    };
+
+    if let Some(limit) = query.limit {
+        statement = statement.limit(limit);
+    }
+    if let Some(offset) = query.offset {
+        statement = statement.offset(offset);
+    }
+
+    let items = statement
+        .select(Income::as_select())
+        .load::<Income>(connection)?;
+
+    Ok(Paginated { items, total_count, next_cursor: None, prev_cursor: None })
 }
 
-pub fn get_incomes_by_user_id(connection: &mut DbConnection, user_id: Uuid) -> Result<Vec<Income>, diesel::result::Error> {
-    incomes::table
-        .filter(incomes::user_id.eq(user_id))
+/// The next batch of a user's filtered incomes in `(date, id)` order, starting after `after`.
+/// Sorting and paging parameters of the query are ignored.
+pub fn export_incomes(connection: &mut DbConnection, user_id: Uuid, query: &IncomeQuery, after: Option<(NaiveDate, Uuid)>) -> Result<Vec<Income>, AppError> {
+    validate_list_params(query.from, query.to, query.min_amount, query.max_amount, None, None)?;
+
+    let order = query.order.unwrap_or_default();
+    let mut statement = filtered_incomes(Some(user_id), query);
+    if let Some((date, id)) = after {
+        let cursor = Cursor { date, id, direction: CursorDirection::Next, order };
+        statement = statement.filter(keyset_condition("incomes", &cursor, order));
+    }
+    statement = match order {
+        SortOrder::Asc => statement.order((incomes::date.asc(), incomes::id.asc())),
+        SortOrder::Desc => statement.order((incomes::date.desc(), incomes::id.desc())),
+    };
+
+    let rows = statement
+        .limit(EXPORT_BATCH_SIZE)
         .select(Income::as_select())
-        .load(connection)
-}
-
-pub fn create_income(connection: &mut DbConnection, new_income: NewIncome) -> Result<Income, diesel::result::Error> {
-    let now = Utc::now().naive_utc();
-    let income = diesel::insert_into(incomes::table)
-        .values((
-            incomes::id.eq(Uuid::new_v4()),
-            incomes::user_id.eq(new_income.user_id),
-            incomes::source.eq(new_income.source),
-            incomes::amount.eq(new_income.amount),
-            incomes::date.eq(new_income.date),
-            incomes::description.eq(new_income.description),
-            incomes::created_at.eq(now),
-            incomes::updated_at.eq(now),
-        ))
-        .get_result::<Income>(connection)?;
-
-    Ok(income)
-}
-
-pub fn update_income(connection: &mut DbConnection, income_id: Uuid, update_income: UpdateIncome) -> Result<Income, diesel::result::Error> {
-    diesel::update(incomes::table)
-        .filter(incomes::id.eq(income_id))
-        .set(update_income)
-        .get_result(connection)
-}
-
-pub fn delete_income(connection: &mut DbConnection, income_id: Uuid) -> Result<Income, diesel::result::Error> {
-    diesel::delete(incomes::table)
-        .filter(incomes::id.eq(income_id))
-        .get_result(connection)
-}
\ No newline at end of file
+        .load::<Income>(connection)?;
+    Ok(rows)
+}
+
+fn filtered_incomes(user_id: Option<Uuid>, query: &IncomeQuery) -> incomes::BoxedQuery<'static, diesel::pg::Pg> {
+    let mut statement = incomes::table.into_boxed();
+
+    if let Some(user_id) = user_id {
+        statement = statement.filter(incomes::user_id.eq(user_id));
+    }
+    if let Some(from) = query.from {
+        statement = statement.filter(incomes::date.ge(from));
+    }
+    if let Some(to) = query.to {
+        statement = statement.filter(incomes::date.le(to));
+    }
+    if let Some(min_amount) = query.min_amount {
+        statement = statement.filter(incomes::amount.ge(min_amount));
+    }
+    if let Some(max_amount) = query.max_amount {
+        statement = statement.filter(incomes::amount.le(max_amount));
+    }
+    if let Some(search) = query.search.as_deref().filter(|search| !search.trim().is_empty()) {
+        let pattern = contains_pattern(search);
+        statement = statement.filter(
+            incomes::source.ilike(pattern.clone())
+                .or(incomes::description.ilike(pattern)),
+        );
+    }
+
+    statement
+}
+
+/// Create an income, running the given rules on its source and description first.
+/// Pass `None` when the caller already ran the user's rules on it.
+pub fn create_income_with(connection: &mut DbConnection, mut new_income: NewIncome, rules: Option<&RuleSet>) -> Result<Income, AppError> {
+    let date = new_income.date.unwrap_or_else(|| Utc::now().date_naive());
+    validate_transaction_date(date)?;
+    if let Some(rules) = rules {
+        rules.apply(TransactionKind::Income, &mut new_income.source, new_income.amount, &mut new_income.description);
+    }
+
+    connection.transaction(|connection| {
+        let payer_id = match new_income.payer_id {
+            Some(payer_id) => {
+                payee_service::ensure_user_payee(connection, new_income.user_id, payer_id)?;
+                Some(payer_id)
+            }
+            None => payee_service::resolve_payee(connection, new_income.user_id, &new_income.source)?,
+        };
+
+        let now = Utc::now().naive_utc();
+        let income = diesel::insert_into(incomes::table)
+            .values((
+                incomes::id.eq(Uuid::new_v4()),
+                incomes::user_id.eq(new_income.user_id),
+                incomes::source.eq(new_income.source),
+                incomes::amount.eq(new_income.amount),
+                incomes::date.eq(date),
+                incomes::description.eq(new_income.description),
+                incomes::created_at.eq(now),
+                incomes::updated_at.eq(now),
+                incomes::payer_id.eq(payer_id),
+                incomes::import_batch_id.eq(new_income.import_batch_id),
+                incomes::external_account.eq(new_income.external_account),
+                incomes::external_id.eq(new_income.external_id),
+            ))
+            .get_result::<Income>(connection)?;
+
+        goal_service::record_income_contributions(connection, &income)?;
+
+        Ok(income)
+    })
+}
+
+/// Create an income as part of the client's batch from the `X-Batch-Id` header, if any
+pub fn create_income_in_batch(connection: &mut DbConnection, new_income: NewIncome, batch: Option<ApiBatch>) -> Result<Income, AppError> {
+    let rules = RuleSet::load(connection, new_income.user_id)?;
+    create_income_in_batch_with(connection, new_income, batch, &rules)
+}
+
+fn create_income_in_batch_with(
+    connection: &mut DbConnection,
+    mut new_income: NewIncome,
+    batch: Option<ApiBatch>,
+    rules: &RuleSet,
+) -> Result<Income, AppError> {
+    let Some(batch) = batch else {
+        return create_income_with(connection, new_income, Some(rules));
+    };
+
+    connection.transaction(|connection| {
+        import_service::join_api_batch(connection, batch, new_income.user_id)?;
+        new_income.import_batch_id = Some(batch.id);
+        create_income_with(connection, new_income, Some(rules))
+    })
+}
+
+pub fn update_income(connection: &mut DbConnection, income_id: Uuid, update_income: UpdateIncome) -> Result<Income, AppError> {
+    if let Some(date) = update_income.date {
+        validate_transaction_date(date)?;
+    }
+
+    connection.transaction(|connection| {
+        if let Some(payer_id) = update_income.payer_id {
+            let user_id = incomes::table
+                .find(income_id)
+                .select(incomes::user_id)
+                .for_update()
+                .first::<Uuid>(connection)?;
+            payee_service::ensure_user_payee(connection, user_id, payer_id)?;
+        }
+
+        let contributions_change = update_income.source.is_some() || update_income.amount.is_some() || update_income.date.is_some();
+        let income = diesel::update(incomes::table)
+            .filter(incomes::id.eq(income_id))
+            .set(update_income)
+            .get_result::<Income>(connection)?;
+        if contributions_change {
+            goal_service::rerecord_income_contributions(connection, &income)?;
+        }
+
+        Ok(income)
+    })
+}
+
+/// Delete an income, also returning the storage keys of its attachments for the caller to remove
+pub fn delete_income(connection: &mut DbConnection, income_id: Uuid) -> Result<(Income, Vec<String>), diesel::result::Error> {
+    connection.transaction(|connection| {
+        // Locking the income keeps attachments from being added until it is gone
+        incomes::table.find(income_id).select(incomes::id).for_update().first::<Uuid>(connection)?;
+        let storage_keys = attachment_service::get_storage_keys(connection, &[], &[income_id])?;
+        let income = diesel::delete(incomes::table)
+            .filter(incomes::id.eq(income_id))
+            .get_result(connection)?;
+        Ok((income, storage_keys))
+    })
+}
+
+/// Run a batch of creates, updates and deletes, creates joining the `X-Batch-Id` batch if given.
+/// Also returns the storage keys of the attachments of deleted incomes for the caller to remove.
+pub fn bulk_incomes(connection: &mut DbConnection, request: BulkIncomeRequest, batch: Option<ApiBatch>) -> Result<(BulkIncomeResult, Vec<String>), AppError> {
+    let mut rule_sets = RuleSetCache::default();
+    let outcomes = bulk_service::run_bulk(connection, request.mode, request.operations, |connection, operation| {
+        match operation {
+            IncomeBulkOperation::Create { data } => {
+                let rules = rule_sets.get(connection, data.user_id)?;
+                let income = create_income_in_batch_with(connection, data, batch, rules)?;
+                Ok(BulkSuccess { status: 201, id: income.id, record: Some(income), storage_keys: Vec::new() })
+            }
+            IncomeBulkOperation::Update { id, data } => {
+                let income = update_income(connection, id, data).map_err(|e| income_not_found(e, id))?;
+                Ok(BulkSuccess { status: 200, id, record: Some(income), storage_keys: Vec::new() })
+            }
+            IncomeBulkOperation::Delete { id } => {
+                let (_, storage_keys) = delete_income(connection, id).map_err(|e| income_not_found(e.into(), id))?;
+                Ok(BulkSuccess { status: 200, id, record: None, storage_keys })
+            }
+        }
+    })?;
+
+    let mut storage_keys = Vec::new();
+    let results: Vec<IncomeBulkItem> = outcomes
+        .into_iter()
+        .enumerate()
+        .map(|(index, outcome)| match outcome {
+            Ok(success) => {
+                storage_keys.extend(success.storage_keys);
+                IncomeBulkItem { index, status: success.status, id: Some(success.id), income: success.record, error: None }
+            }
+            Err(error) => {
+                let (status, message) = bulk_service::failure(&error);
+                IncomeBulkItem { index, status, id: None, income: None, error: Some(message) }
+            }
+        })
+        .collect();
+    let failed = results.iter().filter(|item| item.error.is_some()).count();
+
+    Ok((BulkIncomeResult { succeeded: results.len() - failed, failed, results }, storage_keys))
+}
+
+fn income_not_found(error: AppError, income_id: Uuid) -> AppError {
+    match error {
+        AppError::NotFound(_) => AppError::NotFound(format!("Income {} not found", income_id)),
+        error => error,
+    }
+}
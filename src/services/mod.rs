@@ -1,3 +1,29 @@
 pub mod income_service;
 pub mod expense_service;
-pub mod auth_service;
\ No newline at end of file
+pub mod auth_service;
+pub mod goal_service;
+pub mod attachment_service;
+pub mod attachment_storage;
+pub mod payee_service;
+pub mod validation;
+pub mod query_filters;
+pub mod cursor;
+pub mod search_service;
+pub mod transaction_service;
+pub mod report_service;
+pub mod forecast_service;
+pub mod anomaly_service;
+pub mod duplicate_service;
+pub mod net_worth_service;
+pub mod csv_format;
+pub mod export_service;
+pub mod import_service;
+pub mod ofx_parser;
+pub mod qif_format;
+pub mod camt_parser;
+pub mod mt940_parser;
+pub mod archive_service;
+pub mod rule_service;
+pub mod bulk_service;
+pub mod statement_pdf;#[cfg(test)]
+pub mod test_support;
@@ -0,0 +1,316 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::import::ImportRow;
+use crate::services::import_service::{decode_text, set_amount, set_date};
+
+/// A `:tag:` field of the statement with any continuation lines joined by newlines
+struct Field {
+    line: usize,
+    tag: String,
+    value: String,
+}
+
+/// The parts of an `:86:` information field we keep
+#[derive(Default)]
+struct Information {
+    counterparty: Option<String>,
+    remittance: Option<String>,
+}
+
+/// Read the `:61:` statement lines of a SWIFT MT940 file, each with the `:86:` field after it.
+///
+/// Transactions are dated by their value date. The bank reference after `//` identifies
+/// a transaction in later imports, lines without one are not checked for duplicates.
+pub fn parse_mt940(data: &[u8]) -> Result<Vec<ImportRow>, AppError> {
+    let text = decode_text(data);
+    let fields = read_fields(&text);
+    if !fields.iter().any(|field| field.tag == "20") || !fields.iter().any(|field| field.tag == "61") {
+        return Err(AppError::Validation("Not an MT940 statement, no :20: and :61: fields found".to_string()));
+    }
+
+    let mut rows: Vec<ImportRow> = Vec::new();
+    let mut account: Option<String> = None;
+    // Whether the last :61: still waits for its :86:
+    let mut open_row = false;
+
+    for field in fields {
+        match field.tag.as_str() {
+            "25" => account = Some(field.value.trim().to_string()),
+            "61" => {
+                rows.push(statement_line(field.line, &field.value, account.clone()));
+                open_row = true;
+            }
+            "86" if open_row => {
+                if let Some(row) = rows.last_mut() {
+                    apply_information(row, &field.value);
+                }
+                open_row = false;
+            }
+            _ => open_row = false,
+        }
+    }
+
+    for row in rows.iter_mut().filter(|row| row.name.is_none()) {
+        row.errors.push("Missing transaction details in :86:".to_string());
+    }
+    Ok(rows)
+}
+
+fn read_fields(text: &str) -> Vec<Field> {
+    let mut fields: Vec<Field> = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        // SWIFT envelope blocks such as `{1:F01...}{4:`, and the `-` or `-}` ending a message.
+        // Other lines starting with `-` are field text, e.g. a negative amount in :86:
+        if line.is_empty() || line.starts_with('{') || line == "-" || line == "-}" {
+            continue;
+        }
+
+        let tag = line
+            .strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(tag, _)| (2..=3).contains(&tag.len()) && tag.chars().all(|c| c.is_ascii_alphanumeric()));
+        match tag {
+            Some((tag, value)) => fields.push(Field { line: index + 1, tag: tag.to_string(), value: value.to_string() }),
+            None => {
+                if let Some(field) = fields.last_mut() {
+                    field.value.push('\n');
+                    field.value.push_str(line);
+                }
+            }
+        }
+    }
+
+    fields
+}
+
+/// `YYMMDD[MMDD](C|D|RC|RD)[funds code]amount type code customer reference[//bank reference]`
+fn statement_line(line: usize, value: &str, account: Option<String>) -> ImportRow {
+    let mut row = ImportRow::new(line);
+    row.external_account = account;
+    let (value, supplementary) = value.split_once('\n').unwrap_or((value, ""));
+
+    let date = value.get(..6).and_then(parse_date);
+    match date {
+        Some(date) => set_date(&mut row, date),
+        None => row.errors.push(format!("Invalid value date in :61:{}", value)),
+    }
+
+    // The optional entry date is four digits, the debit/credit mark starts with a letter
+    let rest = value.get(6..).unwrap_or_default();
+    let rest = if rest.get(..4).is_some_and(|entry_date| entry_date.chars().all(|c| c.is_ascii_digit())) { &rest[4..] } else { rest };
+    // A reversal of a credit takes money out again, and a reversal of a debit puts it back
+    let (sign, rest) = if let Some(rest) = rest.strip_prefix("RC") {
+        (-1, rest)
+    } else if let Some(rest) = rest.strip_prefix("RD") {
+        (1, rest)
+    } else if let Some(rest) = rest.strip_prefix('C') {
+        (1, rest)
+    } else if let Some(rest) = rest.strip_prefix('D') {
+        (-1, rest)
+    } else {
+        row.errors.push(format!("Invalid debit/credit mark in :61:{}", value));
+        return row;
+    };
+
+    // Skip the funds code, the third letter of the currency
+    let rest = rest.strip_prefix(|c: char| c.is_ascii_alphabetic()).unwrap_or(rest);
+    let amount_end = rest.find(|c: char| !c.is_ascii_digit() && c != ',').unwrap_or(rest.len());
+    let amount = &rest[..amount_end];
+    match amount.replace(',', ".").parse::<Decimal>() {
+        Ok(amount) => set_amount(&mut row, if sign < 0 { -amount } else { amount }),
+        Err(_) => row.errors.push(format!("Invalid amount '{}'", amount)),
+    }
+
+    // Transaction type, one letter and a three character code such as NTRF, then the customer's
+    // reference. Only the bank's reference after `//` identifies the entry, customers reuse theirs.
+    let references = rest[amount_end..].get(4..).unwrap_or_default();
+    row.external_id = references
+        .split_once("//")
+        .map(|(_, bank_reference)| bank_reference.trim())
+        .filter(|reference| !reference.is_empty() && *reference != "NONREF")
+        .map(str::to_string);
+
+    let supplementary = supplementary.trim();
+    if !supplementary.is_empty() {
+        row.description = Some(supplementary.to_string());
+    }
+    row
+}
+
+/// Fill in the counterparty and remittance text from an `:86:` field
+fn apply_information(row: &mut ImportRow, value: &str) {
+    let information = parse_information(value);
+    match (information.counterparty, information.remittance) {
+        (Some(counterparty), remittance) => {
+            row.name = Some(counterparty);
+            row.description = remittance.or(row.description.take());
+        }
+        // Without a counterparty the remittance text names the transaction and the
+        // :61: supplementary details stay in the description
+        (None, Some(remittance)) => row.name = Some(remittance),
+        (None, None) => row.name = row.description.take(),
+    }
+}
+
+/// `:86:` content is free text, German banks' `?20`-style subfields or `/NAME/`-style codes
+fn parse_information(value: &str) -> Information {
+    let joined: String = value.lines().collect();
+
+    if joined.get(3..4) == Some("?") && joined.get(..3).is_some_and(|code| code.chars().all(|c| c.is_ascii_digit())) {
+        return subfield_information(&joined);
+    }
+    if joined.starts_with('/') {
+        return coded_information(&joined);
+    }
+
+    let text = value.lines().map(str::trim).collect::<Vec<_>>().join(" ");
+    Information { counterparty: None, remittance: Some(text).filter(|text| !text.is_empty()) }
+}
+
+/// `?20`-`?29` and `?60`-`?63` hold the remittance text, `?32` and `?33` the counterparty
+fn subfield_information(value: &str) -> Information {
+    let mut remittance = String::new();
+    let mut counterparty = String::new();
+    let mut booking_text = None;
+
+    for subfield in value.split('?').skip(1) {
+        let (Some(code), Some(text)) = (subfield.get(..2).and_then(|code| code.parse::<u8>().ok()), subfield.get(2..)) else { continue };
+        match code {
+            0 => booking_text = Some(text.trim().to_string()),
+            20..=29 | 60..=63 => remittance.push_str(text),
+            32 | 33 => counterparty.push_str(text),
+            _ => {}
+        }
+    }
+
+    let remittance = Some(remittance.trim().to_string()).filter(|text| !text.is_empty());
+    Information {
+        counterparty: Some(counterparty.trim().to_string()).filter(|text| !text.is_empty()),
+        remittance: remittance.or(booking_text.filter(|text| !text.is_empty())),
+    }
+}
+
+/// `/NAME/Jane Doe/REMI/Invoice 42` style, as used by Dutch and other banks
+fn coded_information(value: &str) -> Information {
+    let parts: Vec<&str> = value.split('/').collect();
+    let mut information = Information::default();
+
+    // Codes and values alternate, `/REMI/USTD//text` nests one more code
+    let mut index = 1;
+    while index + 1 < parts.len() {
+        let code = parts[index];
+        let text = parts[index + 1].trim();
+        match code {
+            "NAME" if !text.is_empty() => information.counterparty = Some(text.to_string()),
+            "REMI" => {
+                let remittance = if matches!(text, "USTD" | "STRD") { parts.get(index + 3).map_or("", |text| text.trim()) } else { text };
+                if !remittance.is_empty() {
+                    information.remittance = Some(remittance.to_string());
+                }
+            }
+            _ => {}
+        }
+        index += 2;
+    }
+
+    information
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let year: i32 = value.get(..2)?.parse().ok()?;
+    let year = if year < 70 { 2000 + year } else { 1900 + year };
+    NaiveDate::from_ymd_opt(year, value.get(2..4)?.parse().ok()?, value.get(4..6)?.parse().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transaction::TransactionKind;
+    use crate::services::test_support::amount;
+
+    const STATEMENT: &str = "{1:F01BANKDEFFXXXX0000000000}{2:O9401200240320BANKDEFFXXXX00000000002403201200N}{4:
+:20:STARTUMS
+:25:10020030/1234567
+:28C:1/1
+:60F:C240301EUR1000,00
+:61:2403200320DR12,50NTRFNONREF//B24032000001
+Card payment
+:86:106?00KARTENZAHLUNG?20Groceries?21 week 12?32REWE Markt
+:61:240321C1500,00NTRFREF123
+:86:/NAME/Acme Corp/REMI/USTD//Salary March/
+:61:240322RC20,00NMSCNONREF
+:86:Chargeback
+-20,00 EUR returned
+:61:240323RD7,00NMSCNONREF
+:62F:C240323EUR2474,50
+-}
+";
+
+    #[test]
+    fn parse_mt940_reads_statement_lines() {
+        let rows = parse_mt940(STATEMENT.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|row| row.external_account.as_deref() == Some("10020030/1234567")));
+
+        // Entry date, funds code and bank reference
+        let card = &rows[0];
+        assert_eq!(card.date, NaiveDate::from_ymd_opt(2024, 3, 20));
+        assert_eq!((card.kind, card.amount), (Some(TransactionKind::Expense), amount("12.50")));
+        assert_eq!(card.external_id.as_deref(), Some("B24032000001"));
+        assert_eq!(card.name.as_deref(), Some("REWE Markt"));
+        assert_eq!(card.description.as_deref(), Some("Groceries week 12"));
+
+        // No entry date, customer reference only, which is not kept
+        let salary = &rows[1];
+        assert_eq!(salary.date, NaiveDate::from_ymd_opt(2024, 3, 21));
+        assert_eq!((salary.kind, salary.amount), (Some(TransactionKind::Income), amount("1500.00")));
+        assert_eq!(salary.external_id, None);
+        assert_eq!(salary.name.as_deref(), Some("Acme Corp"));
+        assert_eq!(salary.description.as_deref(), Some("Salary March"));
+    }
+
+    #[test]
+    fn parse_mt940_reverses_reversals() {
+        let rows = parse_mt940(STATEMENT.as_bytes()).unwrap();
+
+        assert_eq!((rows[2].kind, rows[2].amount), (Some(TransactionKind::Expense), amount("20.00")));
+        assert_eq!(rows[2].external_id, None);
+        assert_eq!((rows[3].kind, rows[3].amount), (Some(TransactionKind::Income), amount("7.00")));
+    }
+
+    #[test]
+    fn parse_mt940_keeps_field_lines_starting_with_a_dash() {
+        let rows = parse_mt940(STATEMENT.as_bytes()).unwrap();
+
+        assert_eq!(rows[2].name.as_deref(), Some("Chargeback -20,00 EUR returned"));
+        // The closing `-}` ends the statement instead of continuing a field
+        assert_eq!(rows[3].errors, vec!["Missing transaction details in :86:".to_string()]);
+    }
+
+    #[test]
+    fn parse_information_reads_subfields() {
+        let information = parse_information("166?00GUTSCHRIFT?20Invoice?2142?32Jane?33 Doe");
+        assert_eq!(information.counterparty.as_deref(), Some("Jane Doe"));
+        assert_eq!(information.remittance.as_deref(), Some("Invoice42"));
+
+        let information = parse_information("805?00ABSCHLUSS");
+        assert_eq!(information.counterparty, None);
+        assert_eq!(information.remittance.as_deref(), Some("ABSCHLUSS"));
+    }
+
+    #[test]
+    fn parse_information_reads_codes() {
+        let information = parse_information("/TRTP/SEPA OVERBOEKING/NAME/Jane Doe/REMI/Invoice 42/EREF/NOTPROVIDED");
+        assert_eq!(information.counterparty.as_deref(), Some("Jane Doe"));
+        assert_eq!(information.remittance.as_deref(), Some("Invoice 42"));
+    }
+
+    #[test]
+    fn parse_mt940_requires_statement_fields() {
+        assert!(parse_mt940(b":25:10020030/1234567\n:28C:1/1\n").is_err());
+    }
+}
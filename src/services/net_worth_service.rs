@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+
+use diesel::prelude::*;
+use uuid::Uuid;
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::net_worth::{
+    NetWorthItem, NetWorthItemWithSnapshots, NetWorthPoint, NetWorthQuery, NetWorthSnapshot, NewNetWorthItem,
+    NewNetWorthSnapshot, UpdateNetWorthItem,
+};
+use crate::models::report::ReportPeriod;
+use crate::models::schema::{expenses, incomes, net_worth_items, net_worth_snapshots};
+use crate::database::db_connection::DbConnection;
+use crate::services::query_filters::validate_date_range;
+
+const ITEM_KINDS: &[&str] = &["asset", "liability"];
+
+pub fn get_items_by_user_id(connection: &mut DbConnection, user_id: Uuid) -> Result<Vec<NetWorthItemWithSnapshots>, diesel::result::Error> {
+    let items = net_worth_items::table
+        .filter(net_worth_items::user_id.eq(user_id))
+        .order((net_worth_items::kind.asc(), net_worth_items::name.asc()))
+        .select(NetWorthItem::as_select())
+        .load::<NetWorthItem>(connection)?;
+
+    let snapshots = NetWorthSnapshot::belonging_to(&items)
+        .order(net_worth_snapshots::date.desc())
+        .select(NetWorthSnapshot::as_select())
+        .load::<NetWorthSnapshot>(connection)?;
+
+    Ok(snapshots
+        .grouped_by(&items)
+        .into_iter()
+        .zip(items)
+        .map(|(snapshots, item)| NetWorthItemWithSnapshots { item, snapshots })
+        .collect())
+}
+
+pub fn create_item(connection: &mut DbConnection, new_item: NewNetWorthItem) -> Result<NetWorthItem, AppError> {
+    validate_kind(&new_item.kind)?;
+
+    let now = Utc::now().naive_utc();
+    let item = diesel::insert_into(net_worth_items::table)
+        .values((
+            net_worth_items::id.eq(Uuid::new_v4()),
+            net_worth_items::user_id.eq(new_item.user_id),
+            net_worth_items::name.eq(new_item.name),
+            net_worth_items::kind.eq(new_item.kind),
+            net_worth_items::category.eq(new_item.category),
+            net_worth_items::created_at.eq(now),
+            net_worth_items::updated_at.eq(now),
+        ))
+        .get_result::<NetWorthItem>(connection)?;
+
+    Ok(item)
+}
+
+pub fn update_item(connection: &mut DbConnection, item_id: Uuid, mut update_item: UpdateNetWorthItem) -> Result<NetWorthItem, AppError> {
+    if let Some(kind) = &update_item.kind {
+        validate_kind(kind)?;
+    }
+
+    update_item.updated_at = Some(Utc::now().naive_utc());
+    let item = diesel::update(net_worth_items::table.find(item_id))
+        .set(update_item)
+        .get_result(connection)?;
+
+    Ok(item)
+}
+
+/// Delete an item together with its snapshots
+pub fn delete_item(connection: &mut DbConnection, item_id: Uuid) -> Result<NetWorthItem, diesel::result::Error> {
+    diesel::delete(net_worth_items::table.find(item_id))
+        .get_result(connection)
+}
+
+/// Record the value of an item on a date, replacing any snapshot already taken that day
+pub fn record_snapshot(connection: &mut DbConnection, item_id: Uuid, new_snapshot: NewNetWorthSnapshot) -> Result<NetWorthSnapshot, AppError> {
+    if new_snapshot.value < Decimal::ZERO {
+        return Err(AppError::Validation("Snapshot value must not be negative, use a liability item for debts".to_string()));
+    }
+
+    // Make sure the item exists so we return 404 instead of a foreign key error
+    net_worth_items::table.find(item_id).select(net_worth_items::id).first::<Uuid>(connection)?;
+
+    let now = Utc::now().naive_utc();
+    let snapshot = diesel::insert_into(net_worth_snapshots::table)
+        .values((
+            net_worth_snapshots::id.eq(Uuid::new_v4()),
+            net_worth_snapshots::item_id.eq(item_id),
+            net_worth_snapshots::date.eq(new_snapshot.date),
+            net_worth_snapshots::value.eq(new_snapshot.value),
+            net_worth_snapshots::note.eq(&new_snapshot.note),
+            net_worth_snapshots::created_at.eq(now),
+            net_worth_snapshots::updated_at.eq(now),
+        ))
+        .on_conflict((net_worth_snapshots::item_id, net_worth_snapshots::date))
+        .do_update()
+        .set((
+            net_worth_snapshots::value.eq(new_snapshot.value),
+            net_worth_snapshots::note.eq(&new_snapshot.note),
+            net_worth_snapshots::updated_at.eq(now),
+        ))
+        .get_result::<NetWorthSnapshot>(connection)?;
+
+    Ok(snapshot)
+}
+
+pub fn delete_snapshot(connection: &mut DbConnection, snapshot_id: Uuid) -> Result<NetWorthSnapshot, diesel::result::Error> {
+    diesel::delete(net_worth_snapshots::table.find(snapshot_id))
+        .get_result(connection)
+}
+
+/// Net worth at the end of every period in the range.
+///
+/// Each point starts from the item values on the nearest snapshot date, the latest
+/// one up to the point or, before the first snapshot, the earliest one after it.
+/// Incomes and expenses between that date and the point are then added or taken
+/// away, so months without snapshots follow the recorded cash flow. Without any
+/// snapshots the net worth is simply everything earned minus everything spent.
+pub fn get_net_worth(connection: &mut DbConnection, user_id: Uuid, query: &NetWorthQuery) -> Result<Vec<NetWorthPoint>, AppError> {
+    validate_date_range(query.from, query.to)?;
+    let period = query.period.unwrap_or_default();
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = query.from.unwrap_or_else(|| default_from(period, to));
+
+    let items = net_worth_items::table
+        .filter(net_worth_items::user_id.eq(user_id))
+        .select(NetWorthItem::as_select())
+        .load::<NetWorthItem>(connection)?;
+    let snapshots = NetWorthSnapshot::belonging_to(&items)
+        .order(net_worth_snapshots::date.asc())
+        .select(NetWorthSnapshot::as_select())
+        .load::<NetWorthSnapshot>(connection)?;
+    let liabilities: Vec<Uuid> = items
+        .iter()
+        .filter(|item| item.kind == "liability")
+        .map(|item| item.id)
+        .collect();
+
+    let cash_flow = cumulative_cash_flow(connection, user_id)?;
+    let snapshot_dates: Vec<NaiveDate> = {
+        let mut dates: Vec<NaiveDate> = snapshots.iter().map(|snapshot| snapshot.date).collect();
+        dates.dedup();
+        dates
+    };
+
+    let points = period_ends(period, from, to)
+        .into_iter()
+        .map(|date| {
+            let anchor = snapshot_dates
+                .iter()
+                .rev()
+                .find(|anchor| **anchor <= date)
+                .or_else(|| snapshot_dates.first())
+                .copied();
+
+            let (assets, liability_total) = match anchor {
+                Some(anchor) => values_on(&snapshots, &liabilities, anchor),
+                None => (Decimal::ZERO, Decimal::ZERO),
+            };
+            let cash_flow_adjustment = match anchor {
+                Some(anchor) => flow_until(&cash_flow, date) - flow_until(&cash_flow, anchor),
+                None => flow_until(&cash_flow, date),
+            };
+
+            NetWorthPoint {
+                date,
+                assets,
+                liabilities: liability_total,
+                anchor_date: anchor,
+                cash_flow_adjustment,
+                net_worth: assets - liability_total + cash_flow_adjustment,
+                reconstructed: anchor != Some(date),
+            }
+        })
+        .collect();
+
+    Ok(points)
+}
+
+/// Running total of incomes minus expenses at the end of every day with transactions
+fn cumulative_cash_flow(connection: &mut DbConnection, user_id: Uuid) -> Result<Vec<(NaiveDate, Decimal)>, diesel::result::Error> {
+    let income_days = incomes::table
+        .filter(incomes::user_id.eq(user_id))
+        .group_by(incomes::date)
+        .select((incomes::date, diesel::dsl::sum(incomes::amount)))
+        .load::<(NaiveDate, Option<Decimal>)>(connection)?;
+    let expense_days = expenses::table
+        .filter(expenses::user_id.eq(user_id))
+        .group_by(expenses::date)
+        .select((expenses::date, diesel::dsl::sum(expenses::amount)))
+        .load::<(NaiveDate, Option<Decimal>)>(connection)?;
+
+    let mut daily: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+    for (date, amount) in income_days {
+        *daily.entry(date).or_default() += amount.unwrap_or_default();
+    }
+    for (date, amount) in expense_days {
+        *daily.entry(date).or_default() -= amount.unwrap_or_default();
+    }
+
+    let mut total = Decimal::ZERO;
+    Ok(daily
+        .into_iter()
+        .map(|(date, amount)| {
+            total += amount;
+            (date, total)
+        })
+        .collect())
+}
+
+fn flow_until(cash_flow: &[(NaiveDate, Decimal)], date: NaiveDate) -> Decimal {
+    let index = cash_flow.partition_point(|(day, _)| *day <= date);
+    index.checked_sub(1).map_or(Decimal::ZERO, |last| cash_flow[last].1)
+}
+
+/// Asset and liability totals using the latest value of every item up to `date`
+fn values_on(snapshots: &[NetWorthSnapshot], liabilities: &[Uuid], date: NaiveDate) -> (Decimal, Decimal) {
+    let mut latest: BTreeMap<Uuid, Decimal> = BTreeMap::new();
+    // Snapshots are sorted by date, so later values overwrite earlier ones
+    for snapshot in snapshots.iter().take_while(|snapshot| snapshot.date <= date) {
+        latest.insert(snapshot.item_id, snapshot.value);
+    }
+
+    latest.into_iter().fold((Decimal::ZERO, Decimal::ZERO), |(assets, debts), (item_id, value)| {
+        if liabilities.contains(&item_id) {
+            (assets, debts + value)
+        } else {
+            (assets + value, debts)
+        }
+    })
+}
+
+fn default_from(period: ReportPeriod, to: NaiveDate) -> NaiveDate {
+    match period {
+        ReportPeriod::Month => period_start(period, to) - Months::new(11),
+        ReportPeriod::Year => period_start(period, to) - Months::new(48),
+    }
+}
+
+fn period_start(period: ReportPeriod, date: NaiveDate) -> NaiveDate {
+    match period {
+        ReportPeriod::Month => date.with_day(1).unwrap_or(date),
+        ReportPeriod::Year => date.with_ordinal(1).unwrap_or(date),
+    }
+}
+
+fn validate_kind(kind: &str) -> Result<(), AppError> {
+    if !ITEM_KINDS.contains(&kind) {
+        return Err(AppError::Validation(format!(
+            "Kind must be one of: {}",
+            ITEM_KINDS.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Last day of every period overlapping the range, with the final one cut off at `to`
+fn period_ends(period: ReportPeriod, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+    let step = match period {
+        ReportPeriod::Month => Months::new(1),
+        ReportPeriod::Year => Months::new(12),
+    };
+
+    let mut ends = Vec::new();
+    let mut start = period_start(period, from);
+    while start <= to {
+        let next = start + step;
+        ends.push((next - chrono::Days::new(1)).min(to));
+        start = next;
+    }
+    ends
+}
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::import::ImportRow;
+use crate::services::import_service::{decode_text, set_amount, set_date};
+
+/// A piece of an OFX document, tags are matched case-insensitively
+enum Token<'a> {
+    Open(String),
+    Close(String),
+    Text(&'a str),
+}
+
+/// Read the `STMTTRN` entries of an OFX statement, both SGML (1.x) and XML (2.x).
+/// QFX files are OFX with a few extra Quicken tags and parse the same way.
+///
+/// SGML leaves such as `<TRNAMT>-12.50` have no closing tag, so a leaf's value is
+/// simply the text up to the next tag; closing tags are only needed for `STMTTRN`.
+pub fn parse_ofx(data: &[u8]) -> Result<Vec<ImportRow>, AppError> {
+    let text = decode_text(data);
+    let start = text
+        .to_ascii_uppercase()
+        .find("<OFX>")
+        .ok_or_else(|| AppError::Validation("Not an OFX file, the <OFX> element is missing".to_string()))?;
+
+    let mut rows = Vec::new();
+    let mut account: Option<String> = None;
+    let mut transaction: Option<HashMap<String, String>> = None;
+    let mut leaf: Option<String> = None;
+
+    for token in tokenize(&text[start..]) {
+        match token {
+            Token::Open(name) => {
+                if name == "STMTTRN" {
+                    transaction = Some(HashMap::new());
+                }
+                leaf = Some(name);
+            }
+            Token::Close(name) => {
+                if name == "STMTTRN" {
+                    if let Some(fields) = transaction.take() {
+                        rows.push(transaction_row(rows.len() + 1, &fields, account.clone()));
+                    }
+                }
+                leaf = None;
+            }
+            Token::Text(value) => {
+                let Some(name) = leaf.take() else { continue };
+                let value = decode_entities(value);
+                match transaction.as_mut() {
+                    // The first NAME wins over one nested in a PAYEE aggregate
+                    Some(fields) => {
+                        fields.entry(name).or_insert(value);
+                    }
+                    // BANKACCTFROM and CCACCTFROM come before each statement's transactions
+                    None if name == "ACCTID" => account = Some(value),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        return Err(AppError::Validation("The OFX file contains no transactions".to_string()));
+    }
+    Ok(rows)
+}
+
+fn transaction_row(position: usize, fields: &HashMap<String, String>, account: Option<String>) -> ImportRow {
+    let mut row = ImportRow::new(position);
+    row.external_account = account;
+    row.external_id = fields.get("FITID").cloned();
+
+    match fields.get("DTPOSTED") {
+        // YYYYMMDD, optionally followed by a time and time zone
+        Some(value) => match value.get(..8).and_then(|day| NaiveDate::parse_from_str(day, "%Y%m%d").ok()) {
+            Some(date) => set_date(&mut row, date),
+            None => row.errors.push(format!("Invalid DTPOSTED '{}'", value)),
+        },
+        None => row.errors.push("Missing DTPOSTED".to_string()),
+    }
+
+    match fields.get("TRNAMT") {
+        // Some banks use a decimal comma, OFX allows both
+        Some(value) => match value.replace(',', ".").parse::<Decimal>() {
+            Ok(amount) => set_amount(&mut row, amount),
+            Err(_) => row.errors.push(format!("Invalid TRNAMT '{}'", value)),
+        },
+        None => row.errors.push("Missing TRNAMT".to_string()),
+    }
+
+    let memo = fields.get("MEMO").cloned();
+    match fields.get("NAME").cloned() {
+        Some(name) => {
+            row.description = memo.filter(|memo| *memo != name);
+            row.name = Some(name);
+        }
+        None => match memo {
+            Some(memo) => row.name = Some(memo),
+            None => row.errors.push("Missing NAME and MEMO".to_string()),
+        },
+    }
+
+    row
+}
+
+fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('<') {
+        let value = rest[..open].trim();
+        if !value.is_empty() {
+            tokens.push(Token::Text(value));
+        }
+        let Some(close) = rest[open..].find('>') else { break };
+        let tag = rest[open + 1..open + close].trim();
+        rest = &rest[open + close + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::Close(name.trim().to_ascii_uppercase()));
+        } else if !tag.starts_with('?') && !tag.starts_with('!') {
+            let name = tag.split_whitespace().next().unwrap_or_default().trim_end_matches('/');
+            tokens.push(Token::Open(name.to_ascii_uppercase()));
+        }
+    }
+
+    tokens
+}
+
+fn decode_entities(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::models::transaction::TransactionKind;
+    use crate::services::import_service::flag_duplicates;
+    use crate::services::test_support::amount;
+
+    const SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+ENCODING:USASCII
+
+<OFX>
+<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS><DTSERVER>20240331120000</SONRS></SIGNONMSGSRSV1>
+<BANKMSGSRSV1>
+<STMTTRNRS><STMTRS><CURDEF>USD
+<BANKACCTFROM><BANKID>121000248<ACCTID>111111<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST><DTSTART>20240301<DTEND>20240331
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240320120000.000[-5:EST]<TRNAMT>-12,50<FITID>A1<NAME>Joe&apos;s Diner<MEMO>Lunch &amp; coffee</STMTTRN>
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240321<TRNAMT>1500.00<FITID>A2<MEMO>Payroll</STMTTRN>
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240321<TRNAMT>1500.00<FITID>A2<MEMO>Payroll</STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS>
+<STMTTRNRS><STMTRS><CURDEF>USD
+<BANKACCTFROM><BANKID>121000248<ACCTID>222222<ACCTTYPE>SAVINGS</BANKACCTFROM>
+<BANKTRANLIST>
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20240322<TRNAMT>-5.00<FITID>A1<NAME>Kiosk</STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS>
+</BANKMSGSRSV1>
+</OFX>
+";
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>
+<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE"?>
+<OFX>
+  <CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+    <CCACCTFROM><ACCTID>4111000011112222</ACCTID></CCACCTFROM>
+    <BANKTRANLIST>
+      <stmttrn>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20240320093000[+1:CET]</DTPOSTED>
+        <TRNAMT>-9.99</TRNAMT>
+        <FITID>X1</FITID>
+        <NAME>Streaming &lt;Premium&gt;</NAME>
+        <PAYEE><NAME>Streaming Inc</NAME></PAYEE>
+        <MEMO/>
+      </stmttrn>
+    </BANKTRANLIST>
+  </CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>"#;
+
+    fn describe(tokens: &[Token]) -> Vec<String> {
+        tokens
+            .iter()
+            .map(|token| match token {
+                Token::Open(name) => format!("<{}>", name),
+                Token::Close(name) => format!("</{}>", name),
+                Token::Text(text) => text.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_reads_sgml_leaves() {
+        let tokens = tokenize("<STMTTRN><TRNAMT>-12.50\n<name>Shop</STMTTRN>");
+        assert_eq!(describe(&tokens), vec!["<STMTTRN>", "<TRNAMT>", "-12.50", "<NAME>", "Shop", "</STMTTRN>"]);
+    }
+
+    #[test]
+    fn tokenize_skips_xml_declarations_and_attributes() {
+        let tokens = tokenize("<?xml version=\"1.0\"?><!DOCTYPE ofx><OFX lang=\"en\"><MEMO/></OFX>");
+        assert_eq!(describe(&tokens), vec!["<OFX>", "<MEMO>", "</OFX>"]);
+    }
+
+    #[test]
+    fn decode_entities_decodes_ampersand_last() {
+        assert_eq!(decode_entities("Joe&apos;s &amp; Co &lt;b&gt; &quot;x&quot;"), "Joe's & Co <b> \"x\"");
+        assert_eq!(decode_entities("&amp;lt;"), "&lt;");
+        assert_eq!(decode_entities("A&nbsp;B"), "A B");
+    }
+
+    #[test]
+    fn parse_ofx_reads_sgml_statements() {
+        let rows = parse_ofx(SGML.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 4);
+
+        let diner = &rows[0];
+        assert_eq!(diner.date, NaiveDate::from_ymd_opt(2024, 3, 20));
+        assert_eq!((diner.kind, diner.amount), (Some(TransactionKind::Expense), amount("12.50")));
+        assert_eq!(diner.name.as_deref(), Some("Joe's Diner"));
+        assert_eq!(diner.description.as_deref(), Some("Lunch & coffee"));
+        assert_eq!(diner.external_id.as_deref(), Some("A1"));
+
+        let payroll = &rows[1];
+        assert_eq!((payroll.kind, payroll.amount), (Some(TransactionKind::Income), amount("1500.00")));
+        assert_eq!(payroll.name.as_deref(), Some("Payroll"));
+        assert_eq!(payroll.description, None);
+    }
+
+    #[test]
+    fn parse_ofx_keeps_each_statements_account() {
+        let rows = parse_ofx(SGML.as_bytes()).unwrap();
+        let accounts: Vec<_> = rows.iter().map(|row| row.external_account.as_deref()).collect();
+        assert_eq!(accounts, vec![Some("111111"), Some("111111"), Some("111111"), Some("222222")]);
+    }
+
+    #[test]
+    fn parse_ofx_reads_xml_statements() {
+        let rows = parse_ofx(XML.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 1);
+
+        let row = &rows[0];
+        assert_eq!(row.external_account.as_deref(), Some("4111000011112222"));
+        assert_eq!(row.date, NaiveDate::from_ymd_opt(2024, 3, 20));
+        assert_eq!(row.amount, amount("9.99"));
+        assert_eq!(row.name.as_deref(), Some("Streaming <Premium>"));
+        assert_eq!(row.description, None);
+        assert!(row.errors.is_empty());
+    }
+
+    #[test]
+    fn parse_ofx_reports_invalid_fields() {
+        let data = "<OFX><STMTTRN><DTPOSTED>2024-03<TRNAMT>abc</STMTTRN></OFX>";
+        let rows = parse_ofx(data.as_bytes()).unwrap();
+        assert_eq!(rows[0].errors, vec![
+            "Invalid DTPOSTED '2024-03'".to_string(),
+            "Invalid TRNAMT 'abc'".to_string(),
+            "Missing NAME and MEMO".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn parse_ofx_rejects_files_without_transactions() {
+        assert!(parse_ofx(b"<html></html>").is_err());
+        assert!(parse_ofx(b"<OFX><BANKACCTFROM><ACCTID>1</BANKACCTFROM></OFX>").is_err());
+    }
+
+    #[test]
+    fn repeated_fitids_in_one_file_are_duplicates_per_account() {
+        let mut rows = parse_ofx(SGML.as_bytes()).unwrap();
+        flag_duplicates(&mut rows, HashSet::new());
+
+        let duplicates: Vec<bool> = rows.iter().map(|row| row.duplicate).collect();
+        assert_eq!(duplicates, vec![false, false, true, false]);
+    }
+
+    #[test]
+    fn fitids_imported_before_are_duplicates() {
+        let mut rows = parse_ofx(SGML.as_bytes()).unwrap();
+        let imported = HashSet::from([(Some("222222".to_string()), "A1".to_string()), (Some("333333".to_string()), "A2".to_string())]);
+        flag_duplicates(&mut rows, imported);
+
+        let duplicates: Vec<bool> = rows.iter().map(|row| row.duplicate).collect();
+        assert_eq!(duplicates, vec![false, false, true, true]);
+    }
+}
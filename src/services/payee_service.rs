@@ -0,0 +1,267 @@
+use diesel::prelude::*;
+use diesel::dsl::{count, sum};
+use uuid::Uuid;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+
+use crate::config::errors::AppError;
+use crate::models::payee::{
+    NewPayee, NewPayeeRule, Payee, PayeeNormalizationResult, PayeeRule, PayeeSpending,
+    PayeeSpendingQuery, PayeeWithRules, UpdatePayee,
+};
+use crate::models::schema::{expenses, incomes, payee_rules, payees};
+use crate::database::db_connection::DbConnection;
+
+const MATCH_TYPES: &[&str] = &["exact", "starts_with", "contains"];
+
+/// Lowercase a raw merchant name and reduce it to words separated by single spaces,
+/// so "AMZN Mktp*2K4", "Amazon.com" and "amazon" become comparable
+pub fn normalize_name(raw: &str) -> String {
+    raw.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The payees and rules of one user, ready to match raw names against
+pub struct PayeeMatcher {
+    rules: Vec<PayeeRule>,
+    names: HashMap<String, Uuid>,
+}
+
+impl PayeeMatcher {
+    pub fn load(connection: &mut DbConnection, user_id: Uuid) -> Result<Self, diesel::result::Error> {
+        let user_payees = payees::table
+            .filter(payees::user_id.eq(user_id))
+            .select(Payee::as_select())
+            .load::<Payee>(connection)?;
+        let mut rules = PayeeRule::belonging_to(&user_payees)
+            .select(PayeeRule::as_select())
+            .load::<PayeeRule>(connection)?;
+
+        // Most specific rules first: exact before prefix before substring, longer patterns first
+        rules.sort_by(|a, b| {
+            match_type_rank(&a.match_type)
+                .cmp(&match_type_rank(&b.match_type))
+                .then(b.pattern.len().cmp(&a.pattern.len()))
+        });
+
+        let names = user_payees
+            .into_iter()
+            .map(|payee| (normalize_name(&payee.name), payee.id))
+            .collect();
+
+        Ok(Self { rules, names })
+    }
+
+    /// Find the payee a raw name belongs to, falling back to a payee with the same normalized name
+    pub fn resolve(&self, raw_name: &str) -> Option<Uuid> {
+        let normalized = normalize_name(raw_name);
+        if normalized.is_empty() {
+            return None;
+        }
+
+        self.rules
+            .iter()
+            .find(|rule| match rule.match_type.as_str() {
+                "exact" => normalized == rule.pattern,
+                "starts_with" => normalized.starts_with(&rule.pattern),
+                _ => normalized.contains(&rule.pattern),
+            })
+            .map(|rule| rule.payee_id)
+            .or_else(|| self.names.get(&normalized).copied())
+    }
+}
+
+fn match_type_rank(match_type: &str) -> usize {
+    MATCH_TYPES
+        .iter()
+        .position(|candidate| *candidate == match_type)
+        .unwrap_or(MATCH_TYPES.len())
+}
+
+pub fn resolve_payee(connection: &mut DbConnection, user_id: Uuid, raw_name: &str) -> Result<Option<Uuid>, diesel::result::Error> {
+    Ok(PayeeMatcher::load(connection, user_id)?.resolve(raw_name))
+}
+
+/// Check that a payee chosen for one of the user's transactions is one of their own
+pub fn ensure_user_payee(connection: &mut DbConnection, user_id: Uuid, payee_id: Uuid) -> Result<(), AppError> {
+    payees::table
+        .filter(payees::id.eq(payee_id))
+        .filter(payees::user_id.eq(user_id))
+        .select(payees::id)
+        .first::<Uuid>(connection)
+        .optional()?
+        .map(|_| ())
+        .ok_or_else(|| AppError::Validation(format!("Payee {} not found", payee_id)))
+}
+
+pub fn get_payees_by_user_id(connection: &mut DbConnection, user_id: Uuid) -> Result<Vec<PayeeWithRules>, diesel::result::Error> {
+    let user_payees = payees::table
+        .filter(payees::user_id.eq(user_id))
+        .order(payees::name.asc())
+        .select(Payee::as_select())
+        .load::<Payee>(connection)?;
+    let rules = PayeeRule::belonging_to(&user_payees)
+        .select(PayeeRule::as_select())
+        .load::<PayeeRule>(connection)?;
+
+    Ok(rules
+        .grouped_by(&user_payees)
+        .into_iter()
+        .zip(user_payees)
+        .map(|(rules, payee)| PayeeWithRules { payee, rules })
+        .collect())
+}
+
+pub fn create_payee(connection: &mut DbConnection, new_payee: NewPayee) -> Result<Payee, AppError> {
+    let name = new_payee.name.trim().to_string();
+    if normalize_name(&name).is_empty() {
+        return Err(AppError::Validation("Payee name must not be empty".to_string()));
+    }
+
+    let now = Utc::now().naive_utc();
+    let payee = diesel::insert_into(payees::table)
+        .values((
+            payees::id.eq(Uuid::new_v4()),
+            payees::user_id.eq(new_payee.user_id),
+            payees::name.eq(name),
+            payees::created_at.eq(now),
+            payees::updated_at.eq(now),
+        ))
+        .get_result::<Payee>(connection)?;
+
+    Ok(payee)
+}
+
+pub fn update_payee(connection: &mut DbConnection, payee_id: Uuid, mut update_payee: UpdatePayee) -> Result<Payee, AppError> {
+    if let Some(name) = &update_payee.name {
+        if normalize_name(name).is_empty() {
+            return Err(AppError::Validation("Payee name must not be empty".to_string()));
+        }
+        update_payee.name = Some(name.trim().to_string());
+    }
+
+    update_payee.updated_at = Some(Utc::now().naive_utc());
+    let payee = diesel::update(payees::table.find(payee_id))
+        .set(update_payee)
+        .get_result(connection)?;
+
+    Ok(payee)
+}
+
+pub fn delete_payee(connection: &mut DbConnection, payee_id: Uuid) -> Result<Payee, diesel::result::Error> {
+    diesel::delete(payees::table.find(payee_id))
+        .get_result(connection)
+}
+
+pub fn add_rule(connection: &mut DbConnection, payee_id: Uuid, new_rule: NewPayeeRule) -> Result<PayeeRule, AppError> {
+    if !MATCH_TYPES.contains(&new_rule.match_type.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Match type must be one of: {}",
+            MATCH_TYPES.join(", ")
+        )));
+    }
+
+    let pattern = normalize_name(&new_rule.pattern);
+    if pattern.is_empty() {
+        return Err(AppError::Validation("Rule pattern must contain letters or digits".to_string()));
+    }
+
+    payees::table.find(payee_id).select(payees::id).first::<Uuid>(connection)?;
+
+    let now = Utc::now().naive_utc();
+    let rule = diesel::insert_into(payee_rules::table)
+        .values((
+            payee_rules::id.eq(Uuid::new_v4()),
+            payee_rules::payee_id.eq(payee_id),
+            payee_rules::match_type.eq(new_rule.match_type),
+            payee_rules::pattern.eq(pattern),
+            payee_rules::created_at.eq(now),
+            payee_rules::updated_at.eq(now),
+        ))
+        .get_result::<PayeeRule>(connection)?;
+
+    Ok(rule)
+}
+
+pub fn delete_rule(connection: &mut DbConnection, rule_id: Uuid) -> Result<PayeeRule, diesel::result::Error> {
+    diesel::delete(payee_rules::table.find(rule_id))
+        .get_result(connection)
+}
+
+/// Assign payees to the user's existing expenses and incomes that don't have one yet
+pub fn normalize_history(connection: &mut DbConnection, user_id: Uuid) -> Result<PayeeNormalizationResult, diesel::result::Error> {
+    connection.transaction(|connection| {
+        let matcher = PayeeMatcher::load(connection, user_id)?;
+
+        let unassigned_expenses = expenses::table
+            .filter(expenses::user_id.eq(user_id))
+            .filter(expenses::payee_id.is_null())
+            .select((expenses::id, expenses::item_name))
+            .load::<(Uuid, String)>(connection)?;
+        let mut expenses_updated = 0;
+        for (expense_id, item_name) in unassigned_expenses {
+            if let Some(payee_id) = matcher.resolve(&item_name) {
+                expenses_updated += diesel::update(expenses::table.find(expense_id))
+                    .set(expenses::payee_id.eq(payee_id))
+                    .execute(connection)?;
+            }
+        }
+
+        let unassigned_incomes = incomes::table
+            .filter(incomes::user_id.eq(user_id))
+            .filter(incomes::payer_id.is_null())
+            .select((incomes::id, incomes::source))
+            .load::<(Uuid, String)>(connection)?;
+        let mut incomes_updated = 0;
+        for (income_id, source) in unassigned_incomes {
+            if let Some(payer_id) = matcher.resolve(&source) {
+                incomes_updated += diesel::update(incomes::table.find(income_id))
+                    .set(incomes::payer_id.eq(payer_id))
+                    .execute(connection)?;
+            }
+        }
+
+        Ok(PayeeNormalizationResult { expenses_updated, incomes_updated })
+    })
+}
+
+/// Total expenses per payee, largest first
+pub fn get_spending_by_payee(connection: &mut DbConnection, user_id: Uuid, query: &PayeeSpendingQuery) -> Result<Vec<PayeeSpending>, diesel::result::Error> {
+    let mut statement = expenses::table
+        .group_by(expenses::payee_id)
+        .select((expenses::payee_id, sum(expenses::amount), count(expenses::id)))
+        .filter(expenses::user_id.eq(user_id))
+        .into_boxed();
+    if let Some(from) = query.from {
+        statement = statement.filter(expenses::date.ge(from));
+    }
+    if let Some(to) = query.to {
+        statement = statement.filter(expenses::date.le(to));
+    }
+
+    let totals = statement.load::<(Option<Uuid>, Option<Decimal>, i64)>(connection)?;
+
+    let names: HashMap<Uuid, String> = payees::table
+        .filter(payees::user_id.eq(user_id))
+        .select((payees::id, payees::name))
+        .load::<(Uuid, String)>(connection)?
+        .into_iter()
+        .collect();
+
+    let mut spending: Vec<PayeeSpending> = totals
+        .into_iter()
+        .map(|(payee_id, total, expense_count)| PayeeSpending {
+            payee_id,
+            payee_name: payee_id.and_then(|id| names.get(&id).cloned()),
+            total_amount: total.unwrap_or(Decimal::ZERO),
+            expense_count,
+        })
+        .collect();
+    spending.sort_by_key(|entry| std::cmp::Reverse(entry.total_amount));
+
+    Ok(spending)
+}
@@ -0,0 +1,378 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::expense::NewExpenseSplit;
+use crate::models::export::QifDateOrder;
+use crate::models::import::ImportRow;
+use crate::models::transaction::Transaction;
+use crate::services::csv_format::CsvFormat;
+use crate::services::import_service::{decode_text, set_amount, set_date};
+
+/// The part of a QIF file the current lines belong to
+#[derive(PartialEq)]
+enum Section {
+    Transactions,
+    Account,
+    Other,
+}
+
+/// One field of a record: the line it is on, its code letter and its value
+type Field = (usize, char, String);
+
+#[derive(Default)]
+struct Split {
+    category: Option<String>,
+    memo: Option<String>,
+    amount: Option<String>,
+}
+
+/// Read the `!Type:Bank` and `!Type:CCard` sections of a QIF file.
+///
+/// Other sections (investments, category lists, memorized transactions) are skipped.
+/// Account names from `!Account` blocks are kept as the rows' external account.
+pub fn parse_qif(data: &[u8], date_order: QifDateOrder) -> Result<Vec<ImportRow>, AppError> {
+    let text = decode_text(data);
+    let mut section = Section::Other;
+    let mut found_transactions = false;
+    let mut account: Option<String> = None;
+    let mut record: Vec<Field> = Vec::new();
+    let mut rows = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('!') {
+            let header = header.trim().to_ascii_lowercase();
+            if let Some(kind) = header.strip_prefix("type:") {
+                section = match kind.trim() {
+                    "bank" | "ccard" => Section::Transactions,
+                    _ => Section::Other,
+                };
+                found_transactions |= section == Section::Transactions;
+            } else if header == "account" {
+                section = Section::Account;
+            }
+            record.clear();
+            continue;
+        }
+
+        if line.starts_with('^') {
+            finish_record(&section, &mut record, &mut account, &mut rows, date_order);
+            continue;
+        }
+
+        let mut chars = line.chars();
+        if let Some(code) = chars.next() {
+            record.push((index + 1, code, chars.as_str().trim().to_string()));
+        }
+    }
+    // Tolerate a missing `^` after the last record
+    finish_record(&section, &mut record, &mut account, &mut rows, date_order);
+
+    if !found_transactions {
+        return Err(AppError::Validation("Not a QIF bank file, no !Type:Bank or !Type:CCard section found".to_string()));
+    }
+    Ok(rows)
+}
+
+fn finish_record(section: &Section, record: &mut Vec<Field>, account: &mut Option<String>, rows: &mut Vec<ImportRow>, date_order: QifDateOrder) {
+    if record.is_empty() {
+        return;
+    }
+    match section {
+        Section::Transactions => rows.extend(transaction_rows(record, account.as_deref(), date_order)),
+        Section::Account => {
+            if let Some((_, _, name)) = record.iter().find(|(_, code, _)| *code == 'N') {
+                *account = Some(name.clone());
+            }
+        }
+        Section::Other => {}
+    }
+    record.clear();
+}
+
+/// Turn a transaction record into rows. Split lines must add up to the total, and become
+/// line items of an expense when they all go the same way, otherwise each is its own transaction.
+fn transaction_rows(record: &[Field], account: Option<&str>, date_order: QifDateOrder) -> Vec<ImportRow> {
+    let field = |code: char| record.iter().find(|(_, c, _)| *c == code).map(|(_, _, value)| value.clone()).filter(|value| !value.is_empty());
+    let mut splits: Vec<Split> = Vec::new();
+    for (_, code, value) in record {
+        let value = Some(value.clone()).filter(|value| !value.is_empty());
+        match code {
+            'S' => splits.push(Split { category: value, ..Split::default() }),
+            'E' | '$' => {
+                if splits.is_empty() {
+                    splits.push(Split::default());
+                }
+                if let Some(split) = splits.last_mut() {
+                    if *code == 'E' {
+                        split.memo = value;
+                    } else {
+                        split.amount = value;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut row = ImportRow::new(record[0].0);
+    row.external_account = account.map(str::to_string);
+    let memo = field('M');
+    let category = field('L');
+    row.name = field('P').or_else(|| memo.clone()).or_else(|| category.clone());
+    row.description = describe(memo.as_deref(), category.as_deref());
+    if row.name.is_none() {
+        row.errors.push("Missing payee".to_string());
+    }
+
+    match field('D') {
+        Some(value) => match parse_date(&value, date_order) {
+            Some(date) => set_date(&mut row, date),
+            None => row.errors.push(format!("Invalid date '{}'", value)),
+        },
+        None => row.errors.push("Missing date".to_string()),
+    }
+
+    let format = CsvFormat::default();
+    let split_amounts: Option<Vec<Decimal>> = splits
+        .iter()
+        .map(|split| split.amount.as_deref().and_then(|amount| format.parse_decimal(amount)))
+        .collect();
+    let total = match field('T').or_else(|| field('U')) {
+        Some(value) => format.parse_decimal(&value).or_else(|| {
+            row.errors.push(format!("Invalid amount '{}'", value));
+            None
+        }),
+        None => split_amounts.as_ref().map(|amounts| amounts.iter().sum()),
+    };
+
+    let Some(total) = total else {
+        if row.errors.is_empty() {
+            row.errors.push("Missing amount".to_string());
+        }
+        return vec![row];
+    };
+    if splits.is_empty() {
+        set_amount(&mut row, total);
+        return vec![row];
+    }
+    let Some(split_amounts) = split_amounts else {
+        row.errors.push("Invalid or missing split amount".to_string());
+        return vec![row];
+    };
+
+    if split_amounts.iter().sum::<Decimal>() != total {
+        row.errors.push("Split amounts do not add up to the total".to_string());
+        return vec![row];
+    }
+
+    let line_items = total.is_sign_negative()
+        && split_amounts.iter().all(|amount| amount.is_sign_negative() && !amount.is_zero());
+    if line_items {
+        row.splits = splits
+            .iter()
+            .zip(&split_amounts)
+            .enumerate()
+            .map(|(index, (split, amount))| NewExpenseSplit {
+                label: split.category.clone().or_else(|| split.memo.clone()).unwrap_or_else(|| format!("Split {}", index + 1)),
+                amount: amount.abs(),
+                note: split.memo.clone().filter(|_| split.category.is_some()),
+            })
+            .collect();
+        set_amount(&mut row, total);
+        return vec![row];
+    }
+
+    splits
+        .iter()
+        .zip(split_amounts)
+        .map(|(split, amount)| {
+            let mut split_row = ImportRow::new(row.line);
+            split_row.external_account = row.external_account.clone();
+            split_row.name = row.name.clone();
+            split_row.date = row.date;
+            split_row.errors = row.errors.clone();
+            split_row.description = describe(split.memo.as_deref().or(memo.as_deref()), split.category.as_deref());
+            set_amount(&mut split_row, amount);
+            split_row
+        })
+        .collect()
+}
+
+/// Memo and category as one description, e.g. `Weekly shop [Food:Groceries]`
+fn describe(memo: Option<&str>, category: Option<&str>) -> Option<String> {
+    match (memo, category) {
+        (Some(memo), Some(category)) => Some(format!("{} [{}]", memo, category)),
+        (Some(memo), None) => Some(memo.to_string()),
+        (None, Some(category)) => Some(format!("[{}]", category)),
+        (None, None) => None,
+    }
+}
+
+/// Dates such as `12/31/2024`, `12/31'24`, ` 1/ 5/98`, `31.12.2024` or `2024-12-31`
+fn parse_date(value: &str, order: QifDateOrder) -> Option<NaiveDate> {
+    let normalized: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if matches!(c, '\'' | '.' | '-') { '/' } else { c })
+        .collect();
+    let parts: Vec<&str> = normalized.split('/').collect();
+    let [first, second, third] = parts.as_slice() else { return None };
+
+    if first.len() == 4 {
+        return NaiveDate::from_ymd_opt(first.parse().ok()?, second.parse().ok()?, third.parse().ok()?);
+    }
+    let (month, day) = match order {
+        QifDateOrder::Mdy => (first.parse().ok()?, second.parse().ok()?),
+        QifDateOrder::Dmy => (second.parse().ok()?, first.parse().ok()?),
+    };
+    let year: i32 = third.parse().ok()?;
+    let year = match (third.len(), year) {
+        (1..=2, year) if year < 70 => 2000 + year,
+        (1..=2, year) => 1900 + year,
+        _ => year,
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Write transactions as QIF bank account entries, expenses negative and their line items as splits.
+/// The account header is only written when `with_header` is set.
+pub fn write_qif(transactions: &[Transaction], date_order: QifDateOrder, with_header: bool) -> String {
+    let mut out = String::new();
+    if with_header {
+        out.push_str("!Type:Bank\n");
+    }
+    let date_format = match date_order {
+        QifDateOrder::Mdy => "%m/%d/%Y",
+        QifDateOrder::Dmy => "%d/%m/%Y",
+    };
+
+    for transaction in transactions {
+        let (date, amount, name, description) = match transaction {
+            Transaction::Income(income) => (income.date, income.amount, &income.source, &income.description),
+            Transaction::Expense(expense) => (expense.expense.date, -expense.expense.amount, &expense.expense.item_name, &expense.expense.description),
+        };
+        out.push_str(&format!("D{}\n", date.format(date_format)));
+        out.push_str(&format!("T{}\n", amount));
+        out.push_str(&format!("P{}\n", single_line(name)));
+        if let Some(description) = description {
+            out.push_str(&format!("M{}\n", single_line(description)));
+        }
+        if let Transaction::Expense(expense) = transaction {
+            for split in &expense.splits {
+                out.push_str(&format!("S{}\n", single_line(&split.label)));
+                if let Some(note) = &split.note {
+                    out.push_str(&format!("E{}\n", single_line(note)));
+                }
+                out.push_str(&format!("${}\n", -split.amount));
+            }
+        }
+        out.push_str("^\n");
+    }
+
+    out
+}
+
+/// QIF fields end at the line break
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::transaction::TransactionKind;
+    use crate::services::test_support::amount;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn parse_date_follows_the_date_order() {
+        assert_eq!(parse_date("03/04/2024", QifDateOrder::Mdy), date(2024, 3, 4));
+        assert_eq!(parse_date("03/04/2024", QifDateOrder::Dmy), date(2024, 4, 3));
+        assert_eq!(parse_date("31.12.2024", QifDateOrder::Dmy), date(2024, 12, 31));
+        assert_eq!(parse_date("31.12.2024", QifDateOrder::Mdy), None);
+        assert_eq!(parse_date("2024-12-31", QifDateOrder::Dmy), date(2024, 12, 31));
+    }
+
+    #[test]
+    fn parse_date_expands_short_years() {
+        assert_eq!(parse_date("12/31'24", QifDateOrder::Mdy), date(2024, 12, 31));
+        assert_eq!(parse_date(" 1/ 5'24", QifDateOrder::Mdy), date(2024, 1, 5));
+        assert_eq!(parse_date(" 1/ 5/98", QifDateOrder::Mdy), date(1998, 1, 5));
+        assert_eq!(parse_date("1/5", QifDateOrder::Mdy), None);
+    }
+
+    #[test]
+    fn parse_qif_reads_bank_records() {
+        let data = b"!Type:Bank\nD03/04'24\nT-12.50\nPBakery\nMBread\nLFood\n^\nD03/05/2024\nT1,000.00\nPSalary\n^\n";
+        let rows = parse_qif(data, QifDateOrder::Mdy).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].kind, Some(TransactionKind::Expense));
+        assert_eq!(rows[0].amount, amount("12.50"));
+        assert_eq!(rows[0].date, date(2024, 3, 4));
+        assert_eq!(rows[0].name.as_deref(), Some("Bakery"));
+        assert_eq!(rows[0].description.as_deref(), Some("Bread [Food]"));
+        assert_eq!(rows[1].kind, Some(TransactionKind::Income));
+        assert_eq!(rows[1].amount, amount("1000.00"));
+        assert!(rows.iter().all(|row| row.errors.is_empty()));
+    }
+
+    #[test]
+    fn parse_qif_reads_dmy_dates() {
+        let rows = parse_qif(b"!Type:Bank\nD03/04/2024\nT-1.00\nPShop\n^\n", QifDateOrder::Dmy).unwrap();
+        assert_eq!(rows[0].date, date(2024, 4, 3));
+    }
+
+    #[test]
+    fn parse_qif_keeps_a_record_missing_the_trailing_caret() {
+        let rows = parse_qif(b"!Type:Bank\nD03/04/2024\nT-1.00\nPShop\n^\nD03/05/2024\nT-2.00\nPKiosk", QifDateOrder::Mdy).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].name.as_deref(), Some("Kiosk"));
+        assert_eq!(rows[1].amount, amount("2.00"));
+    }
+
+    #[test]
+    fn parse_qif_turns_expense_splits_into_line_items() {
+        let data = b"!Type:Bank\nD03/04/2024\nT-30.00\nPMarket\nSFood\nEFruit\n$-20.00\nSHousehold\n$-10.00\n^\n";
+        let rows = parse_qif(data, QifDateOrder::Mdy).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].amount, amount("30.00"));
+        let splits: Vec<_> = rows[0].splits.iter().map(|split| (split.label.as_str(), split.amount, split.note.as_deref())).collect();
+        assert_eq!(splits, vec![("Food", "20.00".parse().unwrap(), Some("Fruit")), ("Household", "10.00".parse().unwrap(), None)]);
+    }
+
+    #[test]
+    fn parse_qif_expands_mixed_splits_into_transactions() {
+        let data = b"!Type:Bank\nD03/04/2024\nT-5.00\nPTransfer\nSRent\n$-15.00\nSRefund\n$10.00\n^\n";
+        let rows = parse_qif(data, QifDateOrder::Mdy).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].kind, rows[0].amount), (Some(TransactionKind::Expense), amount("15.00")));
+        assert_eq!((rows[1].kind, rows[1].amount), (Some(TransactionKind::Income), amount("10.00")));
+        assert_eq!(rows[1].description.as_deref(), Some("[Refund]"));
+    }
+
+    #[test]
+    fn parse_qif_rejects_splits_not_adding_up_to_the_total() {
+        let data = b"!Type:Bank\nD03/04/2024\nT-30.00\nPMarket\nSFood\n$-20.00\nSHousehold\n$-5.00\n^\n";
+        let rows = parse_qif(data, QifDateOrder::Mdy).unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].errors, vec!["Split amounts do not add up to the total".to_string()]);
+    }
+
+    #[test]
+    fn parse_qif_requires_a_bank_section() {
+        assert!(parse_qif(b"!Type:Invst\nD03/04/2024\nT-1.00\n^\n", QifDateOrder::Mdy).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use diesel::dsl::sql;
+use diesel::expression::BoxableExpression;
+use diesel::pg::Pg;
+use diesel::sql_types::{Bool, Date};
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::pagination::{Cursor, CursorDirection, SortOrder, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::services::cursor;
+
+/// Build an ILIKE pattern matching `search` anywhere, with LIKE wildcards in it taken literally
+pub fn contains_pattern(search: &str) -> String {
+    format!("%{}%", escape_like(search.trim()))
+}
+
+/// Build an ILIKE pattern matching the whole of `value`, with LIKE wildcards in it taken literally
+pub fn exact_pattern(value: &str) -> String {
+    escape_like(value.trim())
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Check that a date range is not reversed
+pub fn validate_date_range(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<(), AppError> {
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(AppError::BadRequest("'from' must not be after 'to'".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Check the range and paging parameters shared by the income and expense listings
+pub fn validate_list_params(
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    min_amount: Option<Decimal>,
+    max_amount: Option<Decimal>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<(), AppError> {
+    validate_date_range(from, to)?;
+    if let (Some(min), Some(max)) = (min_amount, max_amount) {
+        if min > max {
+            return Err(AppError::BadRequest("'min_amount' must not be greater than 'max_amount'".to_string()));
+        }
+    }
+    if let Some(limit) = limit {
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(AppError::BadRequest(format!("'limit' must be between 1 and {}", MAX_PAGE_SIZE)));
+        }
+    }
+    if offset.is_some_and(|offset| offset < 0) {
+        return Err(AppError::BadRequest("'offset' must not be negative".to_string()));
+    }
+    Ok(())
+}
+
+/// Decide whether a listing is paged by `(date, id)` keyset, returning the decoded cursor and page size.
+///
+/// A limited, date-sorted listing without `offset` is keyset paged from its first page on,
+/// so that page already comes with a cursor to the next one.
+pub fn keyset_params(
+    token: Option<&str>,
+    sorted_by_date: bool,
+    order: SortOrder,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<Option<(Option<Cursor>, i64)>, AppError> {
+    let Some(token) = token else {
+        return Ok(limit
+            .filter(|_| sorted_by_date && offset.is_none())
+            .map(|limit| (None, limit)));
+    };
+
+    if !sorted_by_date {
+        return Err(AppError::BadRequest("'cursor' can only be used when sorting by date".to_string()));
+    }
+    if offset.is_some() {
+        return Err(AppError::BadRequest("'cursor' and 'offset' cannot be combined".to_string()));
+    }
+    let cursor = cursor::decode(token)?;
+    if cursor.order != order {
+        return Err(AppError::BadRequest("'cursor' was issued for a different sort order".to_string()));
+    }
+
+    Ok(Some((Some(cursor), limit.unwrap_or(DEFAULT_PAGE_SIZE))))
+}
+
+/// Rows of `table` on the far side of `cursor`, as a row comparison so the `(user_id, date, id)` index applies
+pub fn keyset_condition<QS: 'static>(table: &str, cursor: &Cursor, order: SortOrder) -> Box<dyn BoxableExpression<QS, Pg, SqlType = Bool>> {
+    let operator = if (cursor.direction == CursorDirection::Next) == (order == SortOrder::Desc) { "<" } else { ">" };
+    Box::new(
+        sql::<Bool>(&format!("({0}.date, {0}.id) {1} (", table, operator))
+            .bind::<Date, _>(cursor.date)
+            .sql(", ")
+            .bind::<diesel::sql_types::Uuid, _>(cursor.id)
+            .sql(")"),
+    )
+}
+
+/// The direction to fetch rows in, backwards from the listing's order when paging to the previous page
+pub fn fetch_order(cursor: Option<&Cursor>, order: SortOrder) -> SortOrder {
+    match (cursor.map(|cursor| cursor.direction), order) {
+        (Some(CursorDirection::Prev), SortOrder::Asc) => SortOrder::Desc,
+        (Some(CursorDirection::Prev), SortOrder::Desc) => SortOrder::Asc,
+        _ => order,
+    }
+}
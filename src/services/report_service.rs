@@ -0,0 +1,232 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Date, Nullable, Text};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::report::{
+    BreakdownDimension, BreakdownEntry, BreakdownQuery, BreakdownRow, MonthlyStatement, PeriodSummary,
+    SpendingBreakdown, StatementItem, StatementQuery, SummaryQuery,
+};
+use crate::models::expense::Expense;
+use crate::models::income::Income;
+use crate::models::schema::{expenses, incomes, users};
+use crate::database::db_connection::DbConnection;
+use crate::services::query_filters::validate_date_range;
+
+/// Income and expense totals per period. Every period in the range gets a row,
+/// the range defaults to the user's first and last transaction.
+/// Parameters: `$1` user id, `$2` period, `$3` from, `$4` to.
+const SUMMARY_SQL: &str = "
+    WITH entries AS (
+        SELECT date, amount AS income, 0 AS expenses FROM incomes
+        WHERE user_id = $1 AND ($3::date IS NULL OR date >= $3) AND ($4::date IS NULL OR date <= $4)
+        UNION ALL
+        SELECT date, 0, amount FROM expenses
+        WHERE user_id = $1 AND ($3::date IS NULL OR date >= $3) AND ($4::date IS NULL OR date <= $4)
+    ), periods AS (
+        SELECT generate_series(
+            date_trunc($2, COALESCE($3, MIN(date))::timestamp),
+            date_trunc($2, COALESCE($4, MAX(date))::timestamp),
+            ('1 ' || $2)::interval
+        )::date AS period_start
+        FROM entries
+    ), totals AS (
+        SELECT periods.period_start,
+               COALESCE(SUM(entries.income), 0) AS total_income,
+               COALESCE(SUM(entries.expenses), 0) AS total_expenses
+        FROM periods
+        LEFT JOIN entries ON date_trunc($2, entries.date::timestamp)::date = periods.period_start
+        GROUP BY periods.period_start
+    )
+    SELECT period_start, total_income, total_expenses,
+           total_income - total_expenses AS net_savings,
+           CASE WHEN total_income > 0
+                THEN ROUND((total_income - total_expenses) * 100 / total_income, 2)
+           END AS savings_rate
+    FROM totals
+    ORDER BY period_start";
+
+pub fn get_summary(connection: &mut DbConnection, user_id: Uuid, query: &SummaryQuery) -> Result<Vec<PeriodSummary>, AppError> {
+    validate_date_range(query.from, query.to)?;
+
+    let summary = diesel::sql_query(SUMMARY_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<Text, _>(query.period.unwrap_or_default().as_str())
+        .bind::<Nullable<Date>, _>(query.from)
+        .bind::<Nullable<Date>, _>(query.to)
+        .load::<PeriodSummary>(connection)?;
+
+    Ok(summary)
+}
+
+/// Expense totals per group over `$3` to `$4` and the period before it, starting at `$2`.
+/// `{key}` and `{position}` are filled in from `breakdown_expressions`, never from user input.
+const BREAKDOWN_SQL: &str = "
+    WITH spending AS (
+        SELECT {key} AS key, {position} AS position, amount, date >= $3 AS current
+        FROM expenses
+        WHERE user_id = $1 AND date >= $2 AND date <= $4
+    )
+    SELECT key, MIN(position)::int AS position,
+           COALESCE(SUM(amount) FILTER (WHERE current), 0) AS total_amount,
+           COUNT(*) FILTER (WHERE current) AS expense_count,
+           COALESCE(SUM(amount) FILTER (WHERE NOT current), 0) AS previous_total_amount
+    FROM spending
+    GROUP BY key";
+
+/// SQL for the group key of an expense and the position of the group in natural order
+fn breakdown_expressions(dimension: BreakdownDimension) -> (&'static str, &'static str) {
+    match dimension {
+        // Same normalization as payee_service::normalize_name
+        BreakdownDimension::Item => ("trim(regexp_replace(lower(item_name), '[^[:alnum:]]+', ' ', 'g'))", "0"),
+        BreakdownDimension::Weekday => ("trim(to_char(date, 'FMDay'))", "extract(isodow FROM date)"),
+        BreakdownDimension::DayOfMonth => ("extract(day FROM date)::int::text", "extract(day FROM date)"),
+        BreakdownDimension::AmountBand => (
+            "CASE WHEN amount < 10 THEN '0-10' WHEN amount < 50 THEN '10-50' WHEN amount < 100 THEN '50-100' \
+                  WHEN amount < 500 THEN '100-500' WHEN amount < 1000 THEN '500-1000' ELSE '1000+' END",
+            "CASE WHEN amount < 10 THEN 1 WHEN amount < 50 THEN 2 WHEN amount < 100 THEN 3 \
+                  WHEN amount < 500 THEN 4 WHEN amount < 1000 THEN 5 ELSE 6 END",
+        ),
+    }
+}
+
+/// Expense totals grouped by a dimension, compared with the period of the same length just before
+pub fn get_spending_breakdown(connection: &mut DbConnection, user_id: Uuid, query: &BreakdownQuery) -> Result<SpendingBreakdown, AppError> {
+    validate_date_range(Some(query.from), Some(query.to))?;
+
+    let days = (query.to - query.from).num_days() + 1;
+    let previous_to = query.from - Duration::days(1);
+    let previous_from = query.from - Duration::days(days);
+
+    let (key, position) = breakdown_expressions(query.dimension);
+    let rows = diesel::sql_query(BREAKDOWN_SQL.replace("{key}", key).replace("{position}", position))
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<Date, _>(previous_from)
+        .bind::<Date, _>(query.from)
+        .bind::<Date, _>(query.to)
+        .load::<BreakdownRow>(connection)?;
+
+    let total_amount: Decimal = rows.iter().map(|row| row.total_amount).sum();
+    let previous_total_amount: Decimal = rows.iter().map(|row| row.previous_total_amount).sum();
+
+    let mut rows = rows;
+    rows.sort_by_key(|row| (row.position, std::cmp::Reverse(row.total_amount)));
+    let entries = rows
+        .into_iter()
+        .map(|row| BreakdownEntry {
+            percent_of_total: percent(row.total_amount, total_amount).unwrap_or(Decimal::ZERO),
+            change_percent: percent(row.total_amount - row.previous_total_amount, row.previous_total_amount),
+            key: row.key,
+            total_amount: row.total_amount,
+            expense_count: row.expense_count,
+            previous_total_amount: row.previous_total_amount,
+        })
+        .collect();
+
+    Ok(SpendingBreakdown {
+        dimension: query.dimension,
+        from: query.from,
+        to: query.to,
+        previous_from,
+        previous_to,
+        total_amount,
+        previous_total_amount,
+        entries,
+    })
+}
+
+/// `part` as a percentage of `whole` to two decimals, none when `whole` is zero
+fn percent(part: Decimal, whole: Decimal) -> Option<Decimal> {
+    if whole.is_zero() {
+        return None;
+    }
+    Some((part * Decimal::ONE_HUNDRED / whole).round_dp(2))
+}
+
+/// Number of payees or items listed under the top items of a statement
+const STATEMENT_TOP_ITEMS: i64 = 10;
+
+/// Expense totals of `$1` from `$2` up to, not including, `$3`, largest first.
+/// Expenses without a payee are grouped by item name, ignoring case.
+const STATEMENT_TOP_ITEMS_SQL: &str = "
+    SELECT MIN(COALESCE(payees.name, expenses.item_name)) AS name,
+           COUNT(*) AS expense_count,
+           SUM(expenses.amount) AS total_amount
+    FROM expenses
+    LEFT JOIN payees ON payees.id = expenses.payee_id
+    WHERE expenses.user_id = $1 AND expenses.date >= $2 AND expenses.date < $3
+    GROUP BY lower(COALESCE(payees.name, expenses.item_name))
+    ORDER BY total_amount DESC, name
+    LIMIT $4";
+
+/// Gather the figures and transactions of one month for the statement
+pub fn get_monthly_statement(connection: &mut DbConnection, user_id: Uuid, query: &StatementQuery) -> Result<MonthlyStatement, AppError> {
+    let start = match &query.month {
+        Some(month) => NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+            .map_err(|_| AppError::BadRequest("'month' must be given as YYYY-MM".to_string()))?,
+        None => {
+            let today = Utc::now().date_naive();
+            today.with_day(1).unwrap_or(today) - Months::new(1)
+        }
+    };
+    let end = start + Months::new(1);
+
+    let (first_name, last_name, email) = users::table
+        .find(user_id)
+        .select((users::first_name, users::last_name, users::email))
+        .first::<(String, String, String)>(connection)?;
+
+    let income_before = incomes::table
+        .filter(incomes::user_id.eq(user_id))
+        .filter(incomes::date.lt(start))
+        .select(diesel::dsl::sum(incomes::amount))
+        .first::<Option<Decimal>>(connection)?
+        .unwrap_or_default();
+    let expenses_before = expenses::table
+        .filter(expenses::user_id.eq(user_id))
+        .filter(expenses::date.lt(start))
+        .select(diesel::dsl::sum(expenses::amount))
+        .first::<Option<Decimal>>(connection)?
+        .unwrap_or_default();
+
+    let month_incomes = incomes::table
+        .filter(incomes::user_id.eq(user_id))
+        .filter(incomes::date.ge(start))
+        .filter(incomes::date.lt(end))
+        .order((incomes::date.asc(), incomes::created_at.asc()))
+        .select(Income::as_select())
+        .load::<Income>(connection)?;
+    let month_expenses = expenses::table
+        .filter(expenses::user_id.eq(user_id))
+        .filter(expenses::date.ge(start))
+        .filter(expenses::date.lt(end))
+        .order((expenses::date.asc(), expenses::created_at.asc()))
+        .select(Expense::as_select())
+        .load::<Expense>(connection)?;
+
+    let top_items = diesel::sql_query(STATEMENT_TOP_ITEMS_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<Date, _>(start)
+        .bind::<Date, _>(end)
+        .bind::<diesel::sql_types::BigInt, _>(STATEMENT_TOP_ITEMS)
+        .load::<StatementItem>(connection)?;
+
+    let opening_balance = income_before - expenses_before;
+    let total_income: Decimal = month_incomes.iter().map(|income| income.amount).sum();
+    let total_expenses: Decimal = month_expenses.iter().map(|expense| expense.amount).sum();
+
+    Ok(MonthlyStatement {
+        month: start,
+        account_name: format!("{} {}", first_name, last_name).trim().to_string(),
+        email,
+        opening_balance,
+        total_income,
+        total_expenses,
+        closing_balance: opening_balance + total_income - total_expenses,
+        incomes: month_incomes,
+        expenses: month_expenses,
+        top_items,
+    })
+}
@@ -0,0 +1,499 @@
+use std::collections::hash_map::{Entry, HashMap};
+
+use diesel::prelude::*;
+use uuid::Uuid;
+use chrono::Utc;
+use rust_decimal::Decimal;
+
+use crate::config::errors::AppError;
+use crate::models::income::Income;
+use crate::models::rule::{
+    NewTransactionRule, RuleAction, RuleActionKind, RuleApplicationResult, RuleCondition, RuleField, RuleOperator,
+    RuleSample, RuleTestResult, TransactionRule, UpdateTransactionRule,
+};
+use crate::models::schema::{expenses, incomes, transaction_rules};
+use crate::models::transaction::TransactionKind;
+use crate::database::db_connection::DbConnection;
+use crate::services::goal_service;
+
+const RULE_SCOPES: &[&str] = &["all", "income", "expense"];
+
+/// A rule with its conditions and actions read back from JSON
+struct CompiledRule {
+    rule: TransactionRule,
+    conditions: Vec<RuleCondition>,
+    actions: Vec<RuleAction>,
+}
+
+/// The enabled rules of one user, in the order they run
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    pub fn load(connection: &mut DbConnection, user_id: Uuid) -> Result<Self, AppError> {
+        let rules = transaction_rules::table
+            .filter(transaction_rules::user_id.eq(user_id))
+            .filter(transaction_rules::enabled.eq(true))
+            .order((transaction_rules::position.asc(), transaction_rules::created_at.asc()))
+            .select(TransactionRule::as_select())
+            .load::<TransactionRule>(connection)?;
+
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let conditions = serde_json::from_value(rule.conditions.clone())
+                    .map_err(|e| AppError::InternalServer(format!("Invalid conditions in rule {}: {}", rule.id, e)))?;
+                let actions = serde_json::from_value(rule.actions.clone())
+                    .map_err(|e| AppError::InternalServer(format!("Invalid actions in rule {}: {}", rule.id, e)))?;
+                Ok(CompiledRule { rule, conditions, actions })
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+
+        Ok(Self { rules })
+    }
+
+    /// Run the rules on a transaction's name and description, returning the rules that matched.
+    /// Later rules see what earlier ones changed, so a transaction should only go through this once.
+    pub fn apply(&self, kind: TransactionKind, name: &mut String, amount: Decimal, description: &mut Option<String>) -> Vec<&TransactionRule> {
+        let mut matched = Vec::new();
+
+        for compiled in &self.rules {
+            if compiled.rule.applies_to != "all" && compiled.rule.applies_to != kind.as_str() {
+                continue;
+            }
+            if !compiled.conditions.iter().all(|condition| condition_matches(condition, name, amount, description.as_deref())) {
+                continue;
+            }
+
+            for action in &compiled.actions {
+                match action.action {
+                    RuleActionKind::SetName => *name = action.value.clone(),
+                    RuleActionKind::SetDescription => *description = Some(action.value.clone()),
+                    RuleActionKind::AppendDescription => match description {
+                        Some(text) if text.contains(&action.value) => {}
+                        Some(text) if !text.trim().is_empty() => *text = format!("{} {}", text.trim_end(), action.value),
+                        _ => *description = Some(action.value.clone()),
+                    },
+                }
+            }
+
+            matched.push(&compiled.rule);
+            if compiled.rule.stop_processing {
+                break;
+            }
+        }
+
+        matched
+    }
+}
+
+/// Rule sets of the users seen so far, so a request creating many transactions loads each once
+#[derive(Default)]
+pub struct RuleSetCache {
+    sets: HashMap<Uuid, RuleSet>,
+}
+
+impl RuleSetCache {
+    pub fn get(&mut self, connection: &mut DbConnection, user_id: Uuid) -> Result<&RuleSet, AppError> {
+        Ok(match self.sets.entry(user_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(RuleSet::load(connection, user_id)?),
+        })
+    }
+}
+
+fn condition_matches(condition: &RuleCondition, name: &str, amount: Decimal, description: Option<&str>) -> bool {
+    match condition.field {
+        RuleField::Amount => {
+            let Ok(value) = condition.value.trim().parse::<Decimal>() else { return false };
+            match condition.operator {
+                RuleOperator::Equals => amount == value,
+                RuleOperator::Lt => amount < value,
+                RuleOperator::Lte => amount <= value,
+                RuleOperator::Gt => amount > value,
+                RuleOperator::Gte => amount >= value,
+                _ => false,
+            }
+        }
+        RuleField::Name => text_matches(condition.operator, name, &condition.value),
+        RuleField::Description => description.is_some_and(|description| text_matches(condition.operator, description, &condition.value)),
+    }
+}
+
+fn text_matches(operator: RuleOperator, text: &str, value: &str) -> bool {
+    let text = text.to_lowercase();
+    let value = value.to_lowercase();
+    match operator {
+        RuleOperator::Contains => text.contains(&value),
+        RuleOperator::Equals => text.trim() == value.trim(),
+        RuleOperator::StartsWith => text.starts_with(&value),
+        RuleOperator::EndsWith => text.ends_with(&value),
+        _ => false,
+    }
+}
+
+pub fn get_rules_by_user_id(connection: &mut DbConnection, user_id: Uuid) -> Result<Vec<TransactionRule>, diesel::result::Error> {
+    transaction_rules::table
+        .filter(transaction_rules::user_id.eq(user_id))
+        .order((transaction_rules::position.asc(), transaction_rules::created_at.asc()))
+        .select(TransactionRule::as_select())
+        .load(connection)
+}
+
+pub fn create_rule(connection: &mut DbConnection, new_rule: NewTransactionRule) -> Result<TransactionRule, AppError> {
+    let name = validate_name(&new_rule.name)?;
+    let applies_to = new_rule.applies_to.unwrap_or_else(|| "all".to_string());
+    validate_scope(&applies_to)?;
+    validate_conditions(&new_rule.conditions)?;
+    validate_actions(&new_rule.actions)?;
+
+    let position = match new_rule.position {
+        Some(position) => position,
+        None => {
+            let last = transaction_rules::table
+                .filter(transaction_rules::user_id.eq(new_rule.user_id))
+                .select(diesel::dsl::max(transaction_rules::position))
+                .first::<Option<i32>>(connection)?;
+            last.map_or(1, |last| last + 1)
+        }
+    };
+
+    let now = Utc::now().naive_utc();
+    let rule = diesel::insert_into(transaction_rules::table)
+        .values((
+            transaction_rules::id.eq(Uuid::new_v4()),
+            transaction_rules::user_id.eq(new_rule.user_id),
+            transaction_rules::name.eq(name),
+            transaction_rules::position.eq(position),
+            transaction_rules::applies_to.eq(applies_to),
+            transaction_rules::conditions.eq(to_json(&new_rule.conditions)?),
+            transaction_rules::actions.eq(to_json(&new_rule.actions)?),
+            transaction_rules::stop_processing.eq(new_rule.stop_processing.unwrap_or(false)),
+            transaction_rules::enabled.eq(new_rule.enabled.unwrap_or(true)),
+            transaction_rules::created_at.eq(now),
+            transaction_rules::updated_at.eq(now),
+        ))
+        .get_result::<TransactionRule>(connection)?;
+
+    Ok(rule)
+}
+
+pub fn update_rule(connection: &mut DbConnection, rule_id: Uuid, update_rule: UpdateTransactionRule) -> Result<TransactionRule, AppError> {
+    let name = update_rule.name.as_deref().map(validate_name).transpose()?;
+    if let Some(applies_to) = &update_rule.applies_to {
+        validate_scope(applies_to)?;
+    }
+    if let Some(conditions) = &update_rule.conditions {
+        validate_conditions(conditions)?;
+    }
+    if let Some(actions) = &update_rule.actions {
+        validate_actions(actions)?;
+    }
+    let conditions = update_rule.conditions.as_ref().map(to_json).transpose()?;
+    let actions = update_rule.actions.as_ref().map(to_json).transpose()?;
+
+    let rule = diesel::update(transaction_rules::table.find(rule_id))
+        .set((
+            name.map(|name| transaction_rules::name.eq(name)),
+            update_rule.position.map(|position| transaction_rules::position.eq(position)),
+            update_rule.applies_to.map(|applies_to| transaction_rules::applies_to.eq(applies_to)),
+            conditions.map(|conditions| transaction_rules::conditions.eq(conditions)),
+            actions.map(|actions| transaction_rules::actions.eq(actions)),
+            update_rule.stop_processing.map(|stop| transaction_rules::stop_processing.eq(stop)),
+            update_rule.enabled.map(|enabled| transaction_rules::enabled.eq(enabled)),
+            transaction_rules::updated_at.eq(Utc::now().naive_utc()),
+        ))
+        .get_result(connection)?;
+
+    Ok(rule)
+}
+
+pub fn delete_rule(connection: &mut DbConnection, rule_id: Uuid) -> Result<TransactionRule, diesel::result::Error> {
+    diesel::delete(transaction_rules::table.find(rule_id))
+        .get_result(connection)
+}
+
+/// Run the user's rules on a sample transaction without saving anything
+pub fn test_rules(connection: &mut DbConnection, user_id: Uuid, mut sample: RuleSample) -> Result<RuleTestResult, AppError> {
+    let rules = RuleSet::load(connection, user_id)?;
+    let matched_rules = rules
+        .apply(sample.kind, &mut sample.name, sample.amount, &mut sample.description)
+        .into_iter()
+        .cloned()
+        .collect();
+
+    Ok(RuleTestResult { matched_rules, result: sample })
+}
+
+/// Run the current rules over all of the user's existing incomes and expenses
+pub fn apply_to_history(connection: &mut DbConnection, user_id: Uuid) -> Result<RuleApplicationResult, AppError> {
+    connection.transaction(|connection| {
+        let rules = RuleSet::load(connection, user_id)?;
+        let now = Utc::now().naive_utc();
+
+        let user_expenses = expenses::table
+            .filter(expenses::user_id.eq(user_id))
+            .select((expenses::id, expenses::item_name, expenses::amount, expenses::description))
+            .load::<(Uuid, String, Decimal, Option<String>)>(connection)?;
+        let mut expenses_updated = 0;
+        for (expense_id, mut item_name, amount, mut description) in user_expenses {
+            let (old_name, old_description) = (item_name.clone(), description.clone());
+            rules.apply(TransactionKind::Expense, &mut item_name, amount, &mut description);
+            if item_name != old_name || description != old_description {
+                expenses_updated += diesel::update(expenses::table.find(expense_id))
+                    .set((expenses::item_name.eq(item_name), expenses::description.eq(description), expenses::updated_at.eq(now)))
+                    .execute(connection)?;
+            }
+        }
+
+        let user_incomes = incomes::table
+            .filter(incomes::user_id.eq(user_id))
+            .select((incomes::id, incomes::source, incomes::amount, incomes::description))
+            .load::<(Uuid, String, Decimal, Option<String>)>(connection)?;
+        let mut incomes_updated = 0;
+        for (income_id, mut source, amount, mut description) in user_incomes {
+            let (old_source, old_description) = (source.clone(), description.clone());
+            rules.apply(TransactionKind::Income, &mut source, amount, &mut description);
+            if source != old_source || description != old_description {
+                let income = diesel::update(incomes::table.find(income_id))
+                    .set((incomes::source.eq(&source), incomes::description.eq(description), incomes::updated_at.eq(now)))
+                    .get_result::<Income>(connection)?;
+                if source != old_source {
+                    goal_service::rerecord_income_contributions(connection, &income)?;
+                }
+                incomes_updated += 1;
+            }
+        }
+
+        Ok(RuleApplicationResult { expenses_updated, incomes_updated })
+    })
+}
+
+/// Check a rule that was not created through this service, such as one read from an archive
+pub fn validate_rule(rule: &TransactionRule) -> Result<(), AppError> {
+    validate_name(&rule.name)?;
+    validate_scope(&rule.applies_to)?;
+    let conditions: Vec<RuleCondition> = serde_json::from_value(rule.conditions.clone())
+        .map_err(|e| AppError::Validation(format!("Invalid conditions in rule {}: {}", rule.id, e)))?;
+    validate_conditions(&conditions)?;
+    let actions: Vec<RuleAction> = serde_json::from_value(rule.actions.clone())
+        .map_err(|e| AppError::Validation(format!("Invalid actions in rule {}: {}", rule.id, e)))?;
+    validate_actions(&actions)
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("Rule name must not be empty".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+fn validate_scope(applies_to: &str) -> Result<(), AppError> {
+    if !RULE_SCOPES.contains(&applies_to) {
+        return Err(AppError::Validation(format!(
+            "Applies to must be one of: {}",
+            RULE_SCOPES.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+fn validate_conditions(conditions: &[RuleCondition]) -> Result<(), AppError> {
+    if conditions.is_empty() {
+        return Err(AppError::Validation("A rule needs at least one condition".to_string()));
+    }
+
+    for condition in conditions {
+        let numeric = matches!(condition.operator, RuleOperator::Lt | RuleOperator::Lte | RuleOperator::Gt | RuleOperator::Gte);
+        match condition.field {
+            RuleField::Amount => {
+                if !numeric && condition.operator != RuleOperator::Equals {
+                    return Err(AppError::Validation("Amount conditions must use equals, lt, lte, gt or gte".to_string()));
+                }
+                if condition.value.trim().parse::<Decimal>().is_err() {
+                    return Err(AppError::Validation(format!("Amount condition value '{}' is not a number", condition.value)));
+                }
+            }
+            RuleField::Name | RuleField::Description => {
+                if numeric {
+                    return Err(AppError::Validation("Text conditions must use contains, equals, starts_with or ends_with".to_string()));
+                }
+                if condition.value.trim().is_empty() {
+                    return Err(AppError::Validation("Text condition value must not be empty".to_string()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_actions(actions: &[RuleAction]) -> Result<(), AppError> {
+    if actions.is_empty() {
+        return Err(AppError::Validation("A rule needs at least one action".to_string()));
+    }
+    if actions.iter().any(|action| action.action == RuleActionKind::SetName && action.value.trim().is_empty()) {
+        return Err(AppError::Validation("set_name needs a non-empty value".to_string()));
+    }
+    Ok(())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<serde_json::Value, AppError> {
+    serde_json::to_value(value).map_err(|e| AppError::InternalServer(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use serde_json::json;
+
+    fn compiled(position: i32, applies_to: &str, conditions: serde_json::Value, actions: serde_json::Value, stop_processing: bool) -> CompiledRule {
+        let rule = TransactionRule {
+            id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            name: format!("Rule {}", position),
+            position,
+            applies_to: applies_to.to_string(),
+            conditions: conditions.clone(),
+            actions: actions.clone(),
+            stop_processing,
+            enabled: true,
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+        };
+        CompiledRule {
+            rule,
+            conditions: serde_json::from_value(conditions).unwrap(),
+            actions: serde_json::from_value(actions).unwrap(),
+        }
+    }
+
+    fn condition(field: RuleField, operator: RuleOperator, value: &str) -> RuleCondition {
+        RuleCondition { field, operator, value: value.to_string() }
+    }
+
+    fn action(action: RuleActionKind, value: &str) -> RuleAction {
+        RuleAction { action, value: value.to_string() }
+    }
+
+    fn uber_rule(position: i32, stop_processing: bool) -> CompiledRule {
+        compiled(
+            position,
+            "expense",
+            json!([
+                { "field": "item_name", "operator": "contains", "value": "UBER" },
+                { "field": "amount", "operator": "lt", "value": "50" },
+            ]),
+            json!([
+                { "action": "set_name", "value": "Uber ride" },
+                { "action": "append_description", "value": "commute" },
+            ]),
+            stop_processing,
+        )
+    }
+
+    /// Matches on the name the Uber rule sets
+    fn transport_rule(position: i32) -> CompiledRule {
+        compiled(
+            position,
+            "all",
+            json!([{ "field": "name", "operator": "equals", "value": "Uber ride" }]),
+            json!([{ "action": "set_description", "value": "Transport" }]),
+            false,
+        )
+    }
+
+    #[test]
+    fn text_matches_ignores_case() {
+        assert!(text_matches(RuleOperator::Contains, "UBER *TRIP 8XK2", "trip"));
+        assert!(text_matches(RuleOperator::Equals, " Rent ", "rent"));
+        assert!(text_matches(RuleOperator::StartsWith, "Uber Eats", "UBER"));
+        assert!(text_matches(RuleOperator::EndsWith, "Uber Eats", "eats"));
+        assert!(!text_matches(RuleOperator::StartsWith, "Uber Eats", "eats"));
+        assert!(!text_matches(RuleOperator::Lt, "10", "20"));
+    }
+
+    #[test]
+    fn condition_matches_by_field() {
+        let amount = "23.40".parse().unwrap();
+        assert!(condition_matches(&condition(RuleField::Amount, RuleOperator::Lt, "50"), "Uber", amount, None));
+        assert!(condition_matches(&condition(RuleField::Amount, RuleOperator::Equals, "23.4"), "Uber", amount, None));
+        assert!(!condition_matches(&condition(RuleField::Amount, RuleOperator::Gte, "50"), "Uber", amount, None));
+        assert!(!condition_matches(&condition(RuleField::Amount, RuleOperator::Contains, "23"), "Uber", amount, None));
+        assert!(condition_matches(&condition(RuleField::Name, RuleOperator::Contains, "ube"), "Uber", amount, None));
+        assert!(condition_matches(&condition(RuleField::Description, RuleOperator::Contains, "work"), "Uber", amount, Some("To work")));
+        assert!(!condition_matches(&condition(RuleField::Description, RuleOperator::Contains, "work"), "Uber", amount, None));
+    }
+
+    #[test]
+    fn apply_sets_name_and_appends_description() {
+        let rules = RuleSet { rules: vec![uber_rule(1, false)] };
+
+        let mut name = "UBER *TRIP 8XK2".to_string();
+        let mut description = Some("Airport".to_string());
+        let matched = rules.apply(TransactionKind::Expense, &mut name, "23.40".parse().unwrap(), &mut description);
+        assert_eq!(matched.len(), 1);
+        assert_eq!(name, "Uber ride");
+        assert_eq!(description.as_deref(), Some("Airport commute"));
+
+        let mut name = "UBER *TRIP 8XK2".to_string();
+        let mut description = None;
+        assert!(rules.apply(TransactionKind::Expense, &mut name, "64.00".parse().unwrap(), &mut description).is_empty());
+        assert_eq!(name, "UBER *TRIP 8XK2");
+        assert_eq!(description, None);
+
+        let mut name = "UBER *TRIP 8XK2".to_string();
+        assert!(rules.apply(TransactionKind::Income, &mut name, "23.40".parse().unwrap(), &mut description).is_empty());
+    }
+
+    #[test]
+    fn apply_does_not_append_twice() {
+        let rules = RuleSet { rules: vec![uber_rule(1, false)] };
+        let mut name = "UBER *TRIP".to_string();
+        let mut description = Some("Daily commute".to_string());
+        rules.apply(TransactionKind::Expense, &mut name, "12".parse().unwrap(), &mut description);
+        assert_eq!(description.as_deref(), Some("Daily commute"));
+
+        let mut description = None;
+        rules.apply(TransactionKind::Expense, &mut name, "12".parse().unwrap(), &mut description);
+        assert_eq!(description.as_deref(), Some("commute"));
+    }
+
+    #[test]
+    fn apply_runs_in_order_until_stop_processing() {
+        let rules = RuleSet { rules: vec![uber_rule(1, false), transport_rule(2)] };
+        let mut name = "UBER *TRIP".to_string();
+        let mut description = None;
+        let matched = rules.apply(TransactionKind::Expense, &mut name, "12".parse().unwrap(), &mut description);
+        assert_eq!(matched.iter().map(|rule| rule.position).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(description.as_deref(), Some("Transport"));
+
+        let rules = RuleSet { rules: vec![uber_rule(1, true), transport_rule(2)] };
+        let mut name = "UBER *TRIP".to_string();
+        let mut description = None;
+        let matched = rules.apply(TransactionKind::Expense, &mut name, "12".parse().unwrap(), &mut description);
+        assert_eq!(matched.iter().map(|rule| rule.position).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(description.as_deref(), Some("commute"));
+    }
+
+    #[test]
+    fn validate_conditions_checks_operators_against_fields() {
+        assert!(validate_conditions(&[condition(RuleField::Amount, RuleOperator::Lt, "50")]).is_ok());
+        assert!(validate_conditions(&[condition(RuleField::Name, RuleOperator::Contains, "UBER")]).is_ok());
+        assert!(validate_conditions(&[condition(RuleField::Amount, RuleOperator::Contains, "50")]).is_err());
+        assert!(validate_conditions(&[condition(RuleField::Amount, RuleOperator::Gt, "fifty")]).is_err());
+        assert!(validate_conditions(&[condition(RuleField::Name, RuleOperator::Gte, "UBER")]).is_err());
+        assert!(validate_conditions(&[condition(RuleField::Description, RuleOperator::Lt, "10")]).is_err());
+        assert!(validate_conditions(&[condition(RuleField::Name, RuleOperator::Equals, " ")]).is_err());
+        assert!(validate_conditions(&[]).is_err());
+    }
+
+    #[test]
+    fn validate_actions_needs_a_name() {
+        assert!(validate_actions(&[action(RuleActionKind::AppendDescription, "commute")]).is_ok());
+        assert!(validate_actions(&[action(RuleActionKind::SetName, " ")]).is_err());
+        assert!(validate_actions(&[]).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::search::{SearchQuery, SearchResult};
+use crate::database::db_connection::DbConnection;
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+
+/// Ranked matches across the user's incomes and expenses, using the `search_vector` columns.
+///
+/// Text is HTML-escaped before `ts_headline` adds its `<mark>` tags, so snippets are safe to render.
+const SEARCH_SQL: &str = "
+    WITH query AS (SELECT websearch_to_tsquery('english', $2) AS terms)
+    SELECT 'expense' AS kind, e.id, e.item_name AS title, e.amount, e.date,
+           ts_headline('english', escape_html(e.item_name || coalesce(' - ' || e.description, '')), query.terms,
+                       'StartSel=<mark>, StopSel=</mark>, MaxFragments=2') AS snippet,
+           ts_rank(e.search_vector, query.terms) AS rank
+    FROM expenses e, query
+    WHERE e.user_id = $1 AND e.search_vector @@ query.terms
+    UNION ALL
+    SELECT 'income', i.id, i.source, i.amount, i.date,
+           ts_headline('english', escape_html(i.source || coalesce(' - ' || i.description, '')), query.terms,
+                       'StartSel=<mark>, StopSel=</mark>, MaxFragments=2'),
+           ts_rank(i.search_vector, query.terms)
+    FROM incomes i, query
+    WHERE i.user_id = $1 AND i.search_vector @@ query.terms
+    ORDER BY rank DESC, date DESC
+    LIMIT $3";
+
+pub fn search_transactions(connection: &mut DbConnection, user_id: Uuid, query: &SearchQuery) -> Result<Vec<SearchResult>, AppError> {
+    let terms = query.q.trim();
+    if terms.is_empty() {
+        return Err(AppError::BadRequest("'q' must not be empty".to_string()));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+        return Err(AppError::BadRequest(format!("'limit' must be between 1 and {}", MAX_SEARCH_LIMIT)));
+    }
+
+    let results = diesel::sql_query(SEARCH_SQL)
+        .bind::<diesel::sql_types::Uuid, _>(user_id)
+        .bind::<Text, _>(terms)
+        .bind::<BigInt, _>(limit)
+        .load::<SearchResult>(connection)?;
+
+    Ok(results)
+}
@@ -0,0 +1,450 @@
+use chrono::Utc;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use rust_decimal::Decimal;
+
+use crate::models::report::MonthlyStatement;
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+/// Baseline of the page footer, the body stops above it
+const FOOTER_Y: f32 = 30.0;
+const FONT_SIZE: f32 = 9.0;
+const ROW_HEIGHT: f32 = 14.0;
+
+const REGULAR: Name<'static> = Name(b"F1");
+const BOLD: Name<'static> = Name(b"F2");
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // space to /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, // 0 to ?
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, // @ to O
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, // P to _
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, // ` to o
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // p to ~
+];
+
+/// A table column, `right` aligns its text to the right edge
+struct Column {
+    title: &'static str,
+    x: f32,
+    width: f32,
+    right: bool,
+}
+
+const fn column(title: &'static str, x: f32, width: f32, right: bool) -> Column {
+    Column { title, x, width, right }
+}
+
+const TRANSACTION_COLUMNS: [Column; 4] = [
+    column("Date", MARGIN, 60.0, false),
+    column("Name", 115.0, 160.0, false),
+    column("Description", 280.0, 190.0, false),
+    column("Amount", 475.0, 70.0, true),
+];
+
+const TOP_ITEM_COLUMNS: [Column; 4] = [
+    column("Payee or item", MARGIN, 280.0, false),
+    column("Count", 335.0, 60.0, true),
+    column("Share", 400.0, 60.0, true),
+    column("Total", 475.0, 70.0, true),
+];
+
+/// Lays out lines top to bottom, starting a new page when the current one is full
+struct Layout {
+    pages: Vec<Content>,
+    y: f32,
+}
+
+impl Layout {
+    fn new() -> Self {
+        Self { pages: vec![Content::new()], y: PAGE_HEIGHT - MARGIN }
+    }
+
+    fn content(&mut self) -> &mut Content {
+        self.pages.last_mut().expect("a layout always has a page")
+    }
+
+    /// Move down by `height` for the next line, returns true when that needed a new page
+    fn line(&mut self, height: f32) -> bool {
+        if self.y - height < FOOTER_Y + 2.0 * ROW_HEIGHT {
+            self.pages.push(Content::new());
+            self.y = PAGE_HEIGHT - MARGIN - height;
+            return true;
+        }
+        self.y -= height;
+        false
+    }
+
+    /// Start a new page unless `height` more points fit on the current one
+    fn keep_together(&mut self, height: f32) {
+        if self.y - height < FOOTER_Y + 2.0 * ROW_HEIGHT {
+            self.pages.push(Content::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn text(&mut self, font: Name, size: f32, x: f32, text: &str) {
+        let y = self.y;
+        self.content()
+            .begin_text()
+            .set_font(font, size)
+            .next_line(x, y)
+            .show(Str(&encode(text)))
+            .end_text();
+    }
+
+    fn text_right(&mut self, font: Name, size: f32, right: f32, text: &str) {
+        self.text(font, size, right - text_width(text, size), text);
+    }
+
+    /// A horizontal line a little below the current baseline
+    fn rule(&mut self, gray: f32) {
+        let y = self.y - 4.0;
+        self.content()
+            .set_stroke_gray(gray)
+            .set_line_width(0.5)
+            .move_to(MARGIN, y)
+            .line_to(PAGE_WIDTH - MARGIN, y)
+            .stroke();
+    }
+
+    fn cells(&mut self, font: Name, columns: &[Column], cells: &[String]) {
+        for (column, cell) in columns.iter().zip(cells) {
+            let text = truncate(cell, column.width - 4.0);
+            if column.right {
+                self.text_right(font, FONT_SIZE, column.x + column.width, &text);
+            } else {
+                self.text(font, FONT_SIZE, column.x, &text);
+            }
+        }
+    }
+
+    fn header_row(&mut self, columns: &[Column]) {
+        let titles: Vec<String> = columns.iter().map(|column| column.title.to_string()).collect();
+        self.cells(BOLD, columns, &titles);
+        self.rule(0.3);
+    }
+
+    /// A titled table whose header is repeated on every page it spans
+    fn table(&mut self, title: &str, columns: &[Column], rows: &[Vec<String>], empty: &str, total: Option<Vec<String>>) {
+        self.keep_together(2.5 * ROW_HEIGHT + ROW_HEIGHT * 3.0);
+        self.line(2.0 * ROW_HEIGHT);
+        self.text(BOLD, 12.0, MARGIN, title);
+        self.line(ROW_HEIGHT * 1.3);
+        self.header_row(columns);
+
+        if rows.is_empty() {
+            self.line(ROW_HEIGHT + 2.0);
+            self.content().set_fill_gray(0.4);
+            self.text(REGULAR, FONT_SIZE, MARGIN, empty);
+            self.content().set_fill_gray(0.0);
+            return;
+        }
+
+        for (index, row) in rows.iter().enumerate() {
+            let height = if index == 0 { ROW_HEIGHT + 2.0 } else { ROW_HEIGHT };
+            if self.line(height) {
+                self.header_row(columns);
+                self.line(ROW_HEIGHT + 2.0);
+            }
+            self.cells(REGULAR, columns, row);
+        }
+
+        if let Some(total) = total {
+            self.rule(0.3);
+            if self.line(ROW_HEIGHT + 2.0) {
+                self.header_row(columns);
+                self.line(ROW_HEIGHT + 2.0);
+            }
+            self.cells(BOLD, columns, &total);
+        }
+    }
+}
+
+/// Render a monthly statement as a PDF using the built-in Helvetica fonts, so nothing is
+/// downloaded or embedded
+pub fn render_statement(statement: &MonthlyStatement) -> Vec<u8> {
+    let month = statement.month.format("%B %Y").to_string();
+    let mut layout = Layout::new();
+
+    // Title block
+    layout.line(18.0);
+    layout.text(BOLD, 18.0, MARGIN, "Monthly statement");
+    layout.text_right(REGULAR, FONT_SIZE, PAGE_WIDTH - MARGIN, &format!("Generated {}", Utc::now().format("%Y-%m-%d")));
+    layout.line(18.0);
+    layout.text(REGULAR, 12.0, MARGIN, &month);
+    layout.line(16.0);
+    layout.text(REGULAR, FONT_SIZE, MARGIN, &format!("{} <{}>", statement.account_name, statement.email));
+    layout.rule(0.3);
+
+    // Opening and closing figures
+    layout.line(ROW_HEIGHT);
+    let figures = [
+        ("Opening balance", statement.opening_balance, REGULAR),
+        ("Income", statement.total_income, REGULAR),
+        ("Expenses", -statement.total_expenses, REGULAR),
+        ("Closing balance", statement.closing_balance, BOLD),
+    ];
+    for (label, amount, font) in figures {
+        layout.line(ROW_HEIGHT + 2.0);
+        layout.text(font, 10.0, MARGIN, label);
+        layout.text_right(font, 10.0, MARGIN + 250.0, &format_amount(amount));
+    }
+    layout.line(ROW_HEIGHT);
+    layout.content().set_fill_gray(0.4);
+    layout.text(REGULAR, 8.0, MARGIN, "The opening balance is all income minus all expenses recorded before the month.");
+    layout.content().set_fill_gray(0.0);
+
+    let top_items: Vec<Vec<String>> = statement
+        .top_items
+        .iter()
+        .map(|item| {
+            let share = if statement.total_expenses.is_zero() {
+                Decimal::ZERO
+            } else {
+                (item.total_amount * Decimal::ONE_HUNDRED / statement.total_expenses).round_dp(1)
+            };
+            vec![item.name.clone(), item.expense_count.to_string(), format!("{:.1}%", share), format_amount(item.total_amount)]
+        })
+        .collect();
+    layout.table("Top items by total", &TOP_ITEM_COLUMNS, &top_items, "No expenses this month", None);
+
+    let incomes: Vec<Vec<String>> = statement
+        .incomes
+        .iter()
+        .map(|income| transaction_row(income.date, &income.source, income.description.as_deref(), income.amount))
+        .collect();
+    let income_total = total_row(statement.incomes.len(), statement.total_income);
+    layout.table("Income", &TRANSACTION_COLUMNS, &incomes, "No income this month", Some(income_total));
+
+    let expenses: Vec<Vec<String>> = statement
+        .expenses
+        .iter()
+        .map(|expense| transaction_row(expense.date, &expense.item_name, expense.description.as_deref(), expense.amount))
+        .collect();
+    let expense_total = total_row(statement.expenses.len(), statement.total_expenses);
+    layout.table("Expenses", &TRANSACTION_COLUMNS, &expenses, "No expenses this month", Some(expense_total));
+
+    write_pdf(layout.pages, &format!("Statement {} - {}", month, statement.account_name))
+}
+
+fn transaction_row(date: chrono::NaiveDate, name: &str, description: Option<&str>, amount: Decimal) -> Vec<String> {
+    vec![date.format("%Y-%m-%d").to_string(), name.to_string(), description.unwrap_or_default().to_string(), format_amount(amount)]
+}
+
+fn total_row(count: usize, total: Decimal) -> Vec<String> {
+    let label = if count == 1 { "1 transaction".to_string() } else { format!("{} transactions", count) };
+    vec!["Total".to_string(), label, String::new(), format_amount(total)]
+}
+
+/// Put the laid out pages into a document, adding the page footers
+fn write_pdf(pages: Vec<Content>, title: &str) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let regular_id = Ref::new(3);
+    let bold_id = Ref::new(4);
+    let info_id = Ref::new(5);
+    let page_ids: Vec<Ref> = (0..pages.len() as i32).map(|index| Ref::new(6 + 2 * index)).collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id).kids(page_ids.iter().copied()).count(page_ids.len() as i32);
+    pdf.type1_font(regular_id).base_font(Name(b"Helvetica")).encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id).base_font(Name(b"Helvetica-Bold")).encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.document_info(info_id).title(TextStr(title));
+
+    let count = pages.len();
+    for (index, (mut content, page_id)) in pages.into_iter().zip(page_ids).enumerate() {
+        let footer = encode(title);
+        let page_number = format!("Page {} of {}", index + 1, count);
+        content
+            .set_fill_gray(0.4)
+            .begin_text()
+            .set_font(REGULAR, 8.0)
+            .next_line(MARGIN, FOOTER_Y)
+            .show(Str(&footer))
+            .end_text()
+            .begin_text()
+            .set_font(REGULAR, 8.0)
+            .next_line(PAGE_WIDTH - MARGIN - text_width(&page_number, 8.0), FOOTER_Y)
+            .show(Str(page_number.as_bytes()))
+            .end_text();
+
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources().fonts().pair(REGULAR, regular_id).pair(BOLD, bold_id);
+        page.finish();
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.finish()
+}
+
+/// Two decimals with thousands separators, such as `-1,234.50`
+fn format_amount(amount: Decimal) -> String {
+    let formatted = format!("{:.2}", amount.abs().round_dp(2));
+    let (whole, fraction) = formatted.split_once('.').unwrap_or((&formatted, "00"));
+
+    let mut grouped = String::new();
+    for (index, digit) in whole.chars().enumerate() {
+        if index > 0 && (whole.len() - index) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if amount.is_sign_negative() && !amount.round_dp(2).is_zero() { "-" } else { "" };
+    format!("{}{}.{}", sign, grouped, fraction)
+}
+
+/// Width of `text` in points, bold text is treated as regular, close enough for fitting columns
+fn text_width(text: &str, size: f32) -> f32 {
+    let units: u32 = text
+        .chars()
+        .map(|c| match c {
+            ' '..='~' => u32::from(HELVETICA_WIDTHS[c as usize - 32]),
+            _ => 556,
+        })
+        .sum();
+    units as f32 * size / 1000.0
+}
+
+/// Shorten `text` with an ellipsis until it fits into `width` points
+fn truncate(text: &str, width: f32) -> String {
+    if text_width(text, FONT_SIZE) <= width {
+        return text.to_string();
+    }
+    let mut shortened: String = text.to_string();
+    while !shortened.is_empty() && text_width(&shortened, FONT_SIZE) + text_width("...", FONT_SIZE) > width {
+        shortened.pop();
+    }
+    format!("{}...", shortened.trim_end())
+}
+
+/// The text in WinAnsiEncoding, what the standard fonts use, with `?` for anything it lacks
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '\u{20ac}' => 0x80,
+            '\u{2026}' => 0x85,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201c}' => 0x93,
+            '\u{201d}' => 0x94,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            c if c.is_whitespace() => b' ',
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveDateTime};
+    use uuid::Uuid;
+
+    use crate::models::expense::Expense;
+
+    fn decimal(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle.as_bytes())
+    }
+
+    fn statement(expense_count: usize) -> MonthlyStatement {
+        let expenses = (0..expense_count)
+            .map(|index| Expense {
+                id: Uuid::new_v4(),
+                user_id: Uuid::nil(),
+                item_name: format!("Item {}", index),
+                amount: decimal("10.00"),
+                date: NaiveDate::from_ymd_opt(2024, 3, 1 + (index % 28) as u32).unwrap(),
+                description: None,
+                created_at: NaiveDateTime::default(),
+                updated_at: NaiveDateTime::default(),
+                payee_id: None,
+                import_batch_id: None,
+                external_account: None,
+                external_id: None,
+            })
+            .collect();
+        let total_expenses = decimal("10.00") * Decimal::from(expense_count);
+
+        MonthlyStatement {
+            month: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            account_name: "Jane Doe".to_string(),
+            email: "jane@example.com".to_string(),
+            opening_balance: Decimal::ZERO,
+            total_income: Decimal::ZERO,
+            total_expenses,
+            closing_balance: -total_expenses,
+            incomes: Vec::new(),
+            expenses,
+            top_items: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn format_amount_groups_thousands() {
+        assert_eq!(format_amount(decimal("0")), "0.00");
+        assert_eq!(format_amount(decimal("999.5")), "999.50");
+        assert_eq!(format_amount(decimal("1234.5")), "1,234.50");
+        assert_eq!(format_amount(decimal("-1234567.891")), "-1,234,567.89");
+        assert_eq!(format_amount(decimal("123456")), "123,456.00");
+    }
+
+    #[test]
+    fn format_amount_drops_the_sign_of_zero() {
+        assert_eq!(format_amount(decimal("-0.00")), "0.00");
+        assert_eq!(format_amount(decimal("-0.004")), "0.00");
+        assert_eq!(format_amount(decimal("-0.006")), "-0.01");
+    }
+
+    #[test]
+    fn truncate_fits_text_into_the_width() {
+        assert_eq!(truncate("Groceries", 100.0), "Groceries");
+
+        let long = "A description far too long for the narrow column it is printed in";
+        let shortened = truncate(long, 100.0);
+        assert!(shortened.ends_with("..."));
+        assert!(long.starts_with(shortened.trim_end_matches("...")));
+        assert!(text_width(&shortened, FONT_SIZE) <= 100.0);
+    }
+
+    #[test]
+    fn encode_maps_to_win_ansi() {
+        assert_eq!(encode("Café"), b"Caf\xE9");
+        assert_eq!(encode("€5 – “ok” …"), b"\x805 \x96 \x93ok\x94 \x85");
+        assert_eq!(encode("a\tb"), b"a b");
+        assert_eq!(encode("Łódź 😀"), b"?\xF3d? ?");
+    }
+
+    #[test]
+    fn render_statement_writes_a_pdf() {
+        let pdf = render_statement(&statement(3));
+        assert!(pdf.starts_with(b"%PDF"));
+        assert!(contains(&pdf, "/Count 1"));
+        assert!(contains(&pdf, "Page 1 of 1"));
+    }
+
+    #[test]
+    fn render_statement_continues_long_tables_on_new_pages() {
+        // About 29 expenses fit under the summary on the first page and 51 on each page after it
+        let pdf = render_statement(&statement(100));
+        assert!(pdf.starts_with(b"%PDF"));
+        assert!(contains(&pdf, "/Count 3"));
+        assert!(contains(&pdf, "Page 3 of 3"));
+        assert!(!contains(&pdf, "Page 4 of"));
+    }
+}
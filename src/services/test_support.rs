@@ -0,0 +1,6 @@
+use rust_decimal::Decimal;
+
+/// An expected amount in the `Option` form parsed rows carry it in
+pub fn amount(value: &str) -> Option<Decimal> {
+    Some(value.parse().unwrap())
+}
@@ -0,0 +1,234 @@
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_builder::{BoxedSqlQuery, SqlQuery};
+use diesel::sql_types::{Date, Numeric, Text};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::config::errors::AppError;
+use crate::models::expense::{Expense, ExpenseQuery};
+use crate::models::income::{Income, IncomeQuery};
+use crate::models::pagination::{Cursor, CursorDirection, Paginated, SortOrder};
+use crate::models::schema::{expenses, incomes};
+use crate::models::transaction::{LedgerCount, LedgerRow, Transaction, TransactionEntry, TransactionKind, TransactionQuery};
+use crate::database::db_connection::DbConnection;
+use crate::services::{cursor, expense_service, income_service};
+use crate::services::export_service::EXPORT_BATCH_SIZE;
+use crate::services::query_filters::{contains_pattern, fetch_order, keyset_params, validate_list_params};
+
+/// The user's incomes and expenses as one ledger with a running balance in `(date, id)` order.
+/// `$1` is the user id, filters are appended as further parameters.
+const LEDGER_SQL: &str = "
+    WITH ledger AS (
+        SELECT 'income' AS kind, id, date, amount, amount AS signed_amount, source AS title, description
+        FROM incomes WHERE user_id = $1
+        UNION ALL
+        SELECT 'expense', id, date, amount, -amount, item_name, description
+        FROM expenses WHERE user_id = $1
+    ), balances AS (
+        SELECT *, SUM(signed_amount) OVER (ORDER BY date, id) AS running_balance FROM ledger
+    )";
+
+/// A ledger query under construction, keeping track of the next `$n` placeholder
+struct LedgerQuery {
+    statement: BoxedSqlQuery<'static, Pg, SqlQuery>,
+    next_param: usize,
+}
+
+impl LedgerQuery {
+    fn new(select: &str, user_id: Uuid) -> Self {
+        let statement = diesel::sql_query(format!("{} {} FROM balances WHERE TRUE", LEDGER_SQL, select))
+            .into_boxed()
+            .bind::<diesel::sql_types::Uuid, _>(user_id);
+        Self { statement, next_param: 2 }
+    }
+
+    fn param(&mut self) -> String {
+        let placeholder = format!("${}", self.next_param);
+        self.next_param += 1;
+        placeholder
+    }
+
+    fn filter(mut self, query: &TransactionQuery) -> Self {
+        if let Some(kind) = query.kind {
+            let param = self.param();
+            self.statement = self.statement.sql(format!(" AND kind = {}", param)).bind::<Text, _>(kind.as_str());
+        }
+        if let Some(from) = query.from {
+            let param = self.param();
+            self.statement = self.statement.sql(format!(" AND date >= {}", param)).bind::<Date, _>(from);
+        }
+        if let Some(to) = query.to {
+            let param = self.param();
+            self.statement = self.statement.sql(format!(" AND date <= {}", param)).bind::<Date, _>(to);
+        }
+        if let Some(min_amount) = query.min_amount {
+            let param = self.param();
+            self.statement = self.statement.sql(format!(" AND amount >= {}", param)).bind::<Numeric, _>(min_amount);
+        }
+        if let Some(max_amount) = query.max_amount {
+            let param = self.param();
+            self.statement = self.statement.sql(format!(" AND amount <= {}", param)).bind::<Numeric, _>(max_amount);
+        }
+        if let Some(search) = query.search.as_deref().filter(|search| !search.trim().is_empty()) {
+            let param = self.param();
+            self.statement = self.statement
+                .sql(format!(" AND (title ILIKE {0} OR description ILIKE {0})", param))
+                .bind::<Text, _>(contains_pattern(search));
+        }
+        self
+    }
+
+    fn after(mut self, cursor: &Cursor, order: SortOrder) -> Self {
+        let operator = if (cursor.direction == CursorDirection::Next) == (order == SortOrder::Desc) { "<" } else { ">" };
+        let date_param = self.param();
+        let id_param = self.param();
+        self.statement = self.statement
+            .sql(format!(" AND (date, id) {} ({}, {})", operator, date_param, id_param))
+            .bind::<Date, _>(cursor.date)
+            .bind::<diesel::sql_types::Uuid, _>(cursor.id);
+        self
+    }
+
+    fn order(mut self, order: SortOrder, limit: Option<i64>, offset: Option<i64>) -> Self {
+        let direction = match order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        self.statement = self.statement.sql(format!(" ORDER BY date {0}, id {0}", direction));
+        // Limit and offset are validated integers, so they can be inlined
+        if let Some(limit) = limit {
+            self.statement = self.statement.sql(format!(" LIMIT {}", limit));
+        }
+        if let Some(offset) = offset {
+            self.statement = self.statement.sql(format!(" OFFSET {}", offset));
+        }
+        self
+    }
+}
+
+/// The user's incomes and expenses merged into one feed, newest first unless `order=asc`
+pub fn get_transactions(connection: &mut DbConnection, user_id: Uuid, query: &TransactionQuery) -> Result<Paginated<TransactionEntry>, AppError> {
+    validate_list_params(query.from, query.to, query.min_amount, query.max_amount, query.limit, query.offset)?;
+
+    let total_count = LedgerQuery::new("SELECT COUNT(*) AS count", user_id)
+        .filter(query)
+        .statement
+        .get_result::<LedgerCount>(connection)?
+        .count;
+
+    let order = query.order.unwrap_or_default();
+    let keyset = keyset_params(query.cursor.as_deref(), true, order, query.limit, query.offset)?;
+    let rows_query = LedgerQuery::new("SELECT kind, id, date, running_balance", user_id).filter(query);
+
+    let (rows, next_cursor, prev_cursor) = match keyset {
+        Some((cursor, limit)) => {
+            let mut rows_query = rows_query;
+            if let Some(cursor) = &cursor {
+                rows_query = rows_query.after(cursor, order);
+            }
+            let mut rows = rows_query
+                .order(fetch_order(cursor.as_ref(), order), Some(limit + 1), None)
+                .statement
+                .load::<LedgerRow>(connection)?;
+            let (next_cursor, prev_cursor) = cursor::finish_page(&mut rows, limit, cursor.as_ref(), order, |row| (row.date, row.id));
+            (rows, next_cursor, prev_cursor)
+        }
+        None => {
+            let rows = rows_query
+                .order(order, query.limit, query.offset)
+                .statement
+                .load::<LedgerRow>(connection)?;
+            (rows, None, None)
+        }
+    };
+
+    let items = load_transactions(connection, rows)?;
+
+    Ok(Paginated { items, total_count, next_cursor, prev_cursor })
+}
+
+/// The next batch of the user's filtered incomes and expenses in `(date, id)` order, starting after `after`.
+/// Reads the two tables without running balances; paging parameters of the query are ignored.
+pub fn export_transactions(connection: &mut DbConnection, user_id: Uuid, query: &TransactionQuery, after: Option<(NaiveDate, Uuid)>) -> Result<Vec<Transaction>, AppError> {
+    let order = query.order.unwrap_or_default();
+    let incomes = match query.kind {
+        Some(TransactionKind::Expense) => Vec::new(),
+        _ => {
+            let income_query = IncomeQuery {
+                from: query.from,
+                to: query.to,
+                min_amount: query.min_amount,
+                max_amount: query.max_amount,
+                search: query.search.clone(),
+                order: Some(order),
+                ..IncomeQuery::default()
+            };
+            income_service::export_incomes(connection, user_id, &income_query, after)?
+        }
+    };
+    let expenses = match query.kind {
+        Some(TransactionKind::Income) => Vec::new(),
+        _ => {
+            let expense_query = ExpenseQuery {
+                from: query.from,
+                to: query.to,
+                min_amount: query.min_amount,
+                max_amount: query.max_amount,
+                search: query.search.clone(),
+                order: Some(order),
+                ..ExpenseQuery::default()
+            };
+            let expenses = expense_service::export_expenses(connection, user_id, &expense_query, after)?;
+            expense_service::attach_details(connection, expenses)?
+        }
+    };
+
+    // Both batches start after the same position, so the first rows of the two merged are the next batch
+    let mut transactions: Vec<Transaction> = incomes
+        .into_iter()
+        .map(Transaction::Income)
+        .chain(expenses.into_iter().map(Transaction::Expense))
+        .collect();
+    transactions.sort_by_key(Transaction::key);
+    if order == SortOrder::Desc {
+        transactions.reverse();
+    }
+    transactions.truncate(EXPORT_BATCH_SIZE as usize);
+
+    Ok(transactions)
+}
+
+/// Load the incomes and expenses behind ledger rows, keeping the rows' order
+fn load_transactions(connection: &mut DbConnection, rows: Vec<LedgerRow>) -> Result<Vec<TransactionEntry>, diesel::result::Error> {
+    let income_ids: Vec<Uuid> = rows.iter().filter(|row| row.kind == "income").map(|row| row.id).collect();
+    let expense_ids: Vec<Uuid> = rows.iter().filter(|row| row.kind == "expense").map(|row| row.id).collect();
+
+    let mut incomes: HashMap<Uuid, Income> = incomes::table
+        .filter(incomes::id.eq_any(income_ids))
+        .select(Income::as_select())
+        .load::<Income>(connection)?
+        .into_iter()
+        .map(|income| (income.id, income))
+        .collect();
+    let expense_rows = expenses::table
+        .filter(expenses::id.eq_any(expense_ids))
+        .select(Expense::as_select())
+        .load::<Expense>(connection)?;
+    let mut expenses: HashMap<Uuid, _> = expense_service::attach_details(connection, expense_rows)?
+        .into_iter()
+        .map(|expense| (expense.expense.id, expense))
+        .collect();
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let transaction = match row.kind.as_str() {
+                "income" => Transaction::Income(incomes.remove(&row.id)?),
+                _ => Transaction::Expense(expenses.remove(&row.id)?),
+            };
+            Some(TransactionEntry { transaction, running_balance: row.running_balance })
+        })
+        .collect())
+}
@@ -0,0 +1,19 @@
+use chrono::{Duration, NaiveDate, Utc};
+
+use crate::config::errors::AppError;
+
+/// How far ahead of today a transaction may be dated, enough for scheduled payments
+/// but not for typos like 2205 instead of 2025
+pub const MAX_FUTURE_DAYS: i64 = 366;
+
+/// Reject transaction dates that are implausibly far in the future
+pub fn validate_transaction_date(date: NaiveDate) -> Result<(), AppError> {
+    let latest = Utc::now().date_naive() + Duration::days(MAX_FUTURE_DAYS);
+    if date > latest {
+        return Err(AppError::Validation(format!(
+            "Date {} is too far in the future, the latest allowed date is {}",
+            date, latest
+        )));
+    }
+    Ok(())
+}